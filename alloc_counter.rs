@@ -0,0 +1,197 @@
+// !!!!!!!!!!!!!!!!!!!! Counting allocations !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/std/alloc/trait.GlobalAlloc.html
+
+/*
+The RAII section claims that every `Box` is freed when it goes out of scope. Instead of believing it, we can
+count: every heap allocation of the program goes through the "global allocator", and we're allowed to
+replace it with our own, as long as it implements `GlobalAlloc`.
+
+`CountingAlloc` hands all the real work to the system allocator and only keeps score. It is installed for
+the whole program, in every build: every allocation goes through it and pays for one atomic load. Only the
+counting waits for a `measure(|| ...)`, outside of one the counters don't move.
+
+The counters are process-wide, not per thread. While a measurement runs, every allocation of every thread is
+counted: the threads the closure spawns and joins, which is what `./main leaks` relies on, but also any
+unrelated thread that happens to allocate at the same time. Two measurements running on different threads at
+once see each other's allocations, and so would a measurement inside a test, as the test harness runs tests
+in parallel. The numbers are only exact when nothing else is running.
+*/
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::panic;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::thread;
+
+use crate::macros::LessonEntry;
+
+pub(crate) struct CountingAlloc;
+
+// There can only be one global allocator in a program, and it's chosen at
+// compile time: this one replaces `System` for every allocation, always
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct AllocStats {
+    pub(crate) allocs: usize,
+    pub(crate) frees: usize,
+    pub(crate) bytes_allocated: usize,
+    // Can go below zero if the closure frees memory allocated before it started
+    pub(crate) bytes_in_use: isize,
+    pub(crate) peak_bytes: isize,
+}
+
+impl AllocStats {
+    pub(crate) fn leaked(&self) -> isize {
+        self.allocs as isize - self.frees as isize
+    }
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocs, {} frees, {} leaked ({} bytes allocated, {} bytes at the peak)",
+            self.allocs,
+            self.frees,
+            self.leaked(),
+            self.bytes_allocated,
+            self.peak_bytes
+        )
+    }
+}
+
+// The allocator can be called from any thread at any time, so the counters
+// are atomics: they can be updated through a shared `&`, without a lock.
+// (A `Mutex` could allocate, and allocating from inside the allocator would
+// call the allocator again, forever.)
+// How many `measure`s are running. A count rather than a flag, so that the
+// first measurement to end doesn't stop the counting for the others.
+static MEASURING: AtomicUsize = AtomicUsize::new(0);
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static FREES: AtomicUsize = AtomicUsize::new(0);
+static BYTES_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static BYTES_IN_USE: AtomicIsize = AtomicIsize::new(0);
+static PEAK_BYTES: AtomicIsize = AtomicIsize::new(0);
+
+fn record_alloc(size: usize) {
+    if MEASURING.load(Ordering::Relaxed) > 0 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES_ALLOCATED.fetch_add(size, Ordering::Relaxed);
+        let in_use = BYTES_IN_USE.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+        PEAK_BYTES.fetch_max(in_use, Ordering::Relaxed);
+    }
+}
+
+fn record_free(size: usize) {
+    if MEASURING.load(Ordering::Relaxed) > 0 {
+        FREES.fetch_add(1, Ordering::Relaxed);
+        BYTES_IN_USE.fetch_sub(size as isize, Ordering::Relaxed);
+    }
+}
+
+// `unsafe impl`: the compiler can't check that we really return usable memory,
+// we promise it by forwarding every call to `System`
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_free(layout.size());
+    }
+
+    // Growing a `Vec` moves it to a new block: counted as a free and an alloc
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_free(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn snapshot() -> AllocStats {
+    AllocStats {
+        allocs: ALLOCS.load(Ordering::Relaxed),
+        frees: FREES.load(Ordering::Relaxed),
+        bytes_allocated: BYTES_ALLOCATED.load(Ordering::Relaxed),
+        bytes_in_use: BYTES_IN_USE.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+// Runs `f` and returns what it returned, along with the allocations made
+// while it ran, by any thread (see the top of the file).
+// Measurements can be nested: the inner one is part of the outer one.
+pub(crate) fn measure<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let before = snapshot();
+    // The peak of this measurement starts from what's in use right now
+    let outer_peak = PEAK_BYTES.swap(before.bytes_in_use, Ordering::Relaxed);
+    MEASURING.fetch_add(1, Ordering::Relaxed);
+
+    let result = f();
+
+    MEASURING.fetch_sub(1, Ordering::Relaxed);
+    let after = snapshot();
+    PEAK_BYTES.fetch_max(outer_peak, Ordering::Relaxed);
+
+    let stats = AllocStats {
+        allocs: after.allocs - before.allocs,
+        frees: after.frees - before.frees,
+        bytes_allocated: after.bytes_allocated - before.bytes_allocated,
+        bytes_in_use: after.bytes_in_use - before.bytes_in_use,
+        peak_bytes: after.peak_bytes - before.bytes_in_use,
+    };
+
+    (result, stats)
+}
+
+// Measures `f`, prints a one line summary and panics if anything leaked
+pub(crate) fn assert_no_leaks(name: &str, f: impl FnOnce()) -> AllocStats {
+    let ((), stats) = measure(f);
+    println!("{}: {}", name, stats);
+
+    assert_eq!(stats.leaked(), 0, "{} leaked {} allocations", name, stats.leaked());
+    stats
+}
+
+// `./main leaks`: runs every lesson under `assert_no_leaks`
+pub(crate) fn leaks_main(lessons: &[LessonEntry]) {
+    // The first `println!` allocates stdout's buffer, which lives until the
+    // program ends. Printing before measuring keeps it out of the numbers.
+    println!("Checking {} lessons for leaks", lessons.len());
+
+    let mut summary = Vec::new();
+    for lesson in lessons {
+        // Each lesson runs on a thread of its own. Some of std's thread-local
+        // caches are filled on first use (e.g. by waiting on a channel) and
+        // only freed when their thread ends, which would look like a leak.
+        let stats = assert_no_leaks(lesson.id, || {
+            if let Err(panic) = thread::spawn(lesson.run).join() {
+                panic::resume_unwind(panic);
+            }
+        });
+        summary.push((lesson.id, stats));
+    }
+
+    println!();
+    for (name, stats) in summary {
+        println!("{}: {}", name, stats);
+    }
+}
@@ -0,0 +1,775 @@
+// !!!!!!!!!!!!!!!!!!!! async/await, without a runtime !!!!!!!!!!!!!!!!!!!!
+// https://rust-lang.github.io/async-book/
+
+/*
+An `async fn` doesn't run when it's called. It returns a `Future`: a state machine that the compiler builds from
+the function's body, which moves forward a bit every time someone calls its `poll` method:
+
+    trait Future {
+        type Output;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>;
+    }
+
+`poll` returns `Poll::Ready(value)` when it's done, or `Poll::Pending` when it has to wait (for a timer, for a
+message...). Before returning `Pending`, the future stores the `Waker` found in `cx` somewhere, and whoever it's
+waiting for calls `waker.wake()` when it's time to poll again. Nothing polls a future in a loop.
+
+The "someone" calling `poll` is an executor. Rust doesn't ship one (tokio is the usual choice), so this file
+builds two: a single-threaded one, and one where several threads steal work from each other.
+
+`Pin`: an `async fn` that keeps a reference to one of its own local variables across an `.await` becomes a
+future pointing into itself. Moving it would leave that pointer dangling, so `poll` takes `Pin<&mut Self>`: a
+pointer promising the future won't move anymore. `Box::pin` gives that promise by putting it on the heap.
+*/
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::WebEvent;
+
+// !!!!!!!!!!!!!!!!!!!! A timer future !!!!!!!!!!!!!!!!!!!!
+
+/*
+A future needs someone to wake it up. For timers, that's the executor itself: when it has nothing to poll, it
+sleeps until the earliest deadline, then wakes the timers that expired. `sleep` finds the timers of the executor
+that polls it through a thread-local, which is how tokio does it too (and why tokio's `sleep` panics outside of
+a runtime).
+*/
+
+#[derive(Default)]
+pub(crate) struct Timers {
+    pending: Mutex<Vec<(Instant, Waker)>>,
+}
+
+impl Timers {
+    fn register(&self, deadline: Instant, waker: Waker) {
+        self.pending.lock().unwrap().push((deadline, waker));
+    }
+
+    // Wakes every expired timer and returns the next deadline, if any
+    fn fire_expired(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+
+        let mut i = 0;
+        while i < pending.len() {
+            if pending[i].0 <= now {
+                pending.swap_remove(i).1.wake();
+            } else {
+                i += 1;
+            }
+        }
+
+        pending.iter().map(|(deadline, _)| *deadline).min()
+    }
+}
+
+thread_local! {
+    static CURRENT_TIMERS: RefCell<Option<Arc<Timers>>> = const { RefCell::new(None) };
+}
+
+// Makes `timers` the current ones for this thread while `f` runs
+fn with_timers<T>(timers: &Arc<Timers>, f: impl FnOnce() -> T) -> T {
+    let outer = CURRENT_TIMERS.with(|current| current.replace(Some(Arc::clone(timers))));
+    let result = f();
+    CURRENT_TIMERS.with(|current| current.replace(outer));
+    result
+}
+
+pub(crate) struct Sleep {
+    deadline: Instant,
+    registered: bool,
+}
+
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Sleep { deadline: Instant::now() + duration, registered: false }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    // `Sleep` has no pointer into itself, so it's `Unpin`: `Pin<&mut Sleep>`
+    // can be used like a plain `&mut Sleep`
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        // Our executors give every task one waker that never changes, so
+        // registering once is enough
+        if !self.registered {
+            self.registered = true;
+            CURRENT_TIMERS.with(|current| {
+                let current = current.borrow();
+                let timers = current.as_ref().expect("`sleep` must be awaited inside one of this file's executors");
+                timers.register(self.deadline, cx.waker().clone());
+            });
+        }
+
+        Poll::Pending
+    }
+}
+
+// Returns `Pending` once, after asking to be polled again right away: lets
+// the other tasks run, like `thread::yield_now` for threads
+pub(crate) struct YieldNow {
+    yielded: bool,
+}
+
+pub(crate) fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! A channel future !!!!!!!!!!!!!!!!!!!!
+
+// Unbounded, any number of senders, one receiver. Sending never waits, so it
+// works from tasks and from plain threads alike.
+struct ChannelState<T> {
+    messages: VecDeque<T>,
+    // The receiver's waker, while it waits for a message
+    waker: Option<Waker>,
+    senders: usize,
+}
+
+pub(crate) struct Sender<T> {
+    state: Arc<Mutex<ChannelState<T>>>,
+}
+
+pub(crate) struct Receiver<T> {
+    state: Arc<Mutex<ChannelState<T>>>,
+}
+
+pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let state = Arc::new(Mutex::new(ChannelState { messages: VecDeque::new(), waker: None, senders: 1 }));
+    (Sender { state: Arc::clone(&state) }, Receiver { state })
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn send(&self, message: T) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.messages.push_back(message);
+            state.waker.take()
+        };
+        // Woken after unlocking, so the receiver doesn't find the lock taken
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.state.lock().unwrap().senders += 1;
+        Sender { state: Arc::clone(&self.state) }
+    }
+}
+
+// The last sender going away wakes the receiver, so `recv` can return `None`
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.senders -= 1;
+            if state.senders == 0 {
+                state.waker.take()
+            } else {
+                None
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // `Some(message)`, or `None` once every sender is gone and the channel is
+    // empty
+    pub(crate) fn recv(&mut self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+}
+
+pub(crate) struct Recv<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.receiver.state.lock().unwrap();
+
+        if let Some(message) = state.messages.pop_front() {
+            Poll::Ready(Some(message))
+        } else if state.senders == 0 {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! A single-threaded executor !!!!!!!!!!!!!!!!!!!!
+
+/*
+Tasks are futures, numbered by their index in `tasks`. The ready queue holds the numbers of the tasks to poll.
+A task's `Waker` pushes its number into the queue, from whatever thread calls it, and wakes the executor if it
+was sleeping. A task is only polled again after being woken: a task that returns `Pending` without arranging
+for a wake-up is never polled again.
+*/
+
+#[derive(Default)]
+struct ReadyQueue {
+    ids: Mutex<VecDeque<usize>>,
+    not_empty: Condvar,
+}
+
+struct TaskWaker {
+    id: usize,
+    queue: Arc<ReadyQueue>,
+}
+
+// `Wake` turns an `Arc<TaskWaker>` into a `Waker`, no unsafe `RawWaker`
+// vtable needed
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.queue.ids.lock().unwrap().push_back(self.id);
+        self.queue.not_empty.notify_one();
+    }
+}
+
+type LocalTask = Pin<Box<dyn Future<Output = ()>>>;
+
+#[derive(Default)]
+pub(crate) struct Executor {
+    // `None` once the task is done
+    tasks: Vec<Option<LocalTask>>,
+    wakers: Vec<Waker>,
+    queue: Arc<ReadyQueue>,
+    timers: Arc<Timers>,
+    pub(crate) polls: usize,
+}
+
+impl Executor {
+    pub(crate) fn new() -> Self {
+        Executor::default()
+    }
+
+    // No `Send` bound: everything runs on this thread, tasks may share `Rc`s
+    pub(crate) fn spawn(&mut self, future: impl Future<Output = ()> + 'static) {
+        let id = self.tasks.len();
+        self.tasks.push(Some(Box::pin(future)));
+        self.wakers.push(Waker::from(Arc::new(TaskWaker { id, queue: Arc::clone(&self.queue) })));
+        self.queue.ids.lock().unwrap().push_back(id);
+    }
+
+    // Runs until every task is done
+    pub(crate) fn run(&mut self) {
+        let timers = Arc::clone(&self.timers);
+        with_timers(&timers, || {
+            while self.tasks.iter().any(Option::is_some) {
+                let next_deadline = self.timers.fire_expired();
+
+                let id = {
+                    let ids = self.queue.ids.lock().unwrap();
+                    // Nothing to do: sleep until a waker or the next timer
+                    let timeout = next_deadline.map_or(Duration::MAX, |deadline| deadline - Instant::now());
+                    let (mut ids, _) =
+                        self.queue.not_empty.wait_timeout_while(ids, timeout, |ids| ids.is_empty()).unwrap();
+                    ids.pop_front()
+                };
+
+                if let Some(id) = id {
+                    self.poll_task(id);
+                }
+            }
+        });
+    }
+
+    fn poll_task(&mut self, id: usize) {
+        // A task can be woken several times before it's polled, or after it
+        // ended: those extra wake-ups are ignored
+        let Some(task) = self.tasks[id].as_mut() else {
+            return;
+        };
+
+        self.polls += 1;
+        let mut cx = Context::from_waker(&self.wakers[id]);
+        if task.as_mut().poll(&mut cx).is_ready() {
+            self.tasks[id] = None;
+        }
+    }
+}
+
+// Runs one future to completion and returns its output
+pub(crate) fn block_on<T: 'static>(future: impl Future<Output = T> + 'static) -> T {
+    let output = Rc::new(RefCell::new(None));
+    let slot = Rc::clone(&output);
+
+    let mut executor = Executor::new();
+    executor.spawn(async move {
+        *slot.borrow_mut() = Some(future.await);
+    });
+    executor.run();
+
+    let output = output.borrow_mut().take();
+    output.expect("the future didn't finish")
+}
+
+// !!!!!!!!!!!!!!!!!!!! A work-stealing executor !!!!!!!!!!!!!!!!!!!!
+
+/*
+Several worker threads, each with its own queue of tasks. A worker takes tasks from the back of its own queue,
+and when it's empty, it steals from the front of the others', so no thread stays idle while another has a pile
+of work. A task woken by a worker goes into that worker's queue; woken from anywhere else (a plain thread), it
+goes into the shared "injector" queue.
+
+Tasks now move between threads, so their futures must be `Send`, and a task is an `Arc` shared by its waker and
+the queues. `scheduled` makes sure a task is queued at most once, however many times it's woken.
+*/
+
+struct Task {
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    scheduled: AtomicBool,
+    pool: Arc<Pool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct WorkerStats {
+    pub(crate) polls: usize,
+    pub(crate) steals: usize,
+}
+
+struct Pool {
+    injector: Mutex<VecDeque<Arc<Task>>>,
+    locals: Vec<Mutex<VecDeque<Arc<Task>>>>,
+    // Tasks not finished yet: the workers stop when it reaches 0
+    live: AtomicUsize,
+    idle: Mutex<()>,
+    wake_up: Condvar,
+    timers: Arc<Timers>,
+}
+
+thread_local! {
+    // (the pool, the worker's index) when this thread is a worker
+    static CURRENT_WORKER: RefCell<Option<(usize, usize)>> = const { RefCell::new(None) };
+}
+
+impl Pool {
+    fn id(self: &Arc<Self>) -> usize {
+        Arc::as_ptr(self) as usize
+    }
+
+    fn schedule(self: &Arc<Self>, task: Arc<Task>) {
+        let worker = CURRENT_WORKER.with(|current| *current.borrow());
+
+        match worker {
+            Some((pool, index)) if pool == self.id() => self.locals[index].lock().unwrap().push_back(task),
+            _ => self.injector.lock().unwrap().push_back(task),
+        }
+        self.wake_up.notify_one();
+    }
+
+    // Own queue first (newest task, its data is likely still in the cache),
+    // then the injector, then the oldest task of another worker
+    fn find_task(&self, index: usize, stats: &mut WorkerStats) -> Option<Arc<Task>> {
+        if let Some(task) = self.locals[index].lock().unwrap().pop_back() {
+            return Some(task);
+        }
+        if let Some(task) = self.injector.lock().unwrap().pop_front() {
+            return Some(task);
+        }
+
+        let others = (1..self.locals.len()).map(|offset| (index + offset) % self.locals.len());
+        for other in others {
+            if let Some(task) = self.locals[other].lock().unwrap().pop_front() {
+                stats.steals += 1;
+                return Some(task);
+            }
+        }
+
+        None
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::AcqRel) {
+            let pool = Arc::clone(&self.pool);
+            pool.schedule(self);
+        }
+    }
+}
+
+fn run_task(task: Arc<Task>, pool: &Pool) {
+    // Cleared before polling: a wake-up during `poll` queues the task again
+    task.scheduled.store(false, Ordering::Release);
+
+    let waker = Waker::from(Arc::clone(&task));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut future = task.future.lock().unwrap();
+    if let Some(running) = future.as_mut() {
+        if running.as_mut().poll(&mut cx).is_ready() {
+            *future = None;
+            if pool.live.fetch_sub(1, Ordering::AcqRel) == 1 {
+                // That was the last one, tell the sleeping workers to stop
+                pool.wake_up.notify_all();
+            }
+        }
+    }
+}
+
+fn worker_loop(pool: &Arc<Pool>, index: usize) -> WorkerStats {
+    let mut stats = WorkerStats::default();
+    CURRENT_WORKER.with(|current| *current.borrow_mut() = Some((pool.id(), index)));
+
+    with_timers(&pool.timers, || {
+        while pool.live.load(Ordering::Acquire) > 0 {
+            let next_deadline = pool.timers.fire_expired();
+
+            if let Some(task) = pool.find_task(index, &mut stats) {
+                stats.polls += 1;
+                run_task(task, pool);
+                continue;
+            }
+
+            // Nothing to do. A wake-up can slip in between `find_task` and
+            // this wait, so the wait is capped: at worst we look again in 5ms.
+            // (A real runtime is more careful, at the cost of more code.)
+            let cap = Instant::now() + Duration::from_millis(5);
+            let until = next_deadline.map_or(cap, |deadline| deadline.min(cap));
+            let guard = pool.idle.lock().unwrap();
+            let _ = pool.wake_up.wait_timeout(guard, until.saturating_duration_since(Instant::now())).unwrap();
+        }
+    });
+
+    CURRENT_WORKER.with(|current| *current.borrow_mut() = None);
+    stats
+}
+
+pub(crate) struct WorkStealingExecutor {
+    pool: Arc<Pool>,
+    next_worker: usize,
+}
+
+impl WorkStealingExecutor {
+    pub(crate) fn new(workers: usize) -> Self {
+        let pool = Pool {
+            injector: Mutex::new(VecDeque::new()),
+            locals: (0..workers.max(1)).map(|_| Mutex::new(VecDeque::new())).collect(),
+            live: AtomicUsize::new(0),
+            idle: Mutex::new(()),
+            wake_up: Condvar::new(),
+            timers: Arc::default(),
+        };
+        WorkStealingExecutor { pool: Arc::new(pool), next_worker: 0 }
+    }
+
+    // Puts the task in one worker's queue: with `spread == false` they all
+    // pile up on worker 0, and the others have to steal
+    pub(crate) fn spawn(&mut self, future: impl Future<Output = ()> + Send + 'static, spread: bool) {
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(Box::pin(future))),
+            scheduled: AtomicBool::new(true),
+            pool: Arc::clone(&self.pool),
+        });
+
+        let worker = if spread { self.next_worker % self.pool.locals.len() } else { 0 };
+        self.next_worker += 1;
+        self.pool.live.fetch_add(1, Ordering::AcqRel);
+        self.pool.locals[worker].lock().unwrap().push_back(task);
+    }
+
+    // Runs until every task is done, returns what each worker did
+    pub(crate) fn run(self) -> Vec<WorkerStats> {
+        let pool = &self.pool;
+
+        thread::scope(|scope| {
+            let workers: Vec<_> =
+                (0..pool.locals.len()).map(|index| scope.spawn(move || worker_loop(pool, index))).collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        })
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! WebEvents, handled by async tasks !!!!!!!!!!!!!!!!!!!!
+
+/*
+closures.rs handles `WebEvent`s with callbacks. Here, each part is a task instead: the "browser" produces events
+with pauses in between, the inspector describes them, and a click counter receives the clicks it forwards. Every
+task is written as straight-line code with `.await`, and the executor interleaves them while they wait.
+*/
+
+// What RBE's `inspect` prints for each event
+pub(crate) fn describe(event: &WebEvent) -> String {
+    match event {
+        WebEvent::PageLoad => String::from("page loaded"),
+        WebEvent::PageUnload => String::from("page unloaded"),
+        WebEvent::KeyPress(c) => format!("pressed '{}'.", c),
+        WebEvent::Paste(s) => format!("pasted \"{}\".", s),
+        WebEvent::Click { x, y } => format!("clicked at x={}, y={}.", x, y),
+    }
+}
+
+async fn browser(events: Sender<WebEvent>) {
+    let script = [
+        WebEvent::PageLoad,
+        WebEvent::KeyPress('x'),
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::Paste(String::from("my text")),
+        WebEvent::Click { x: 5, y: 5 },
+        WebEvent::PageUnload,
+    ];
+
+    for event in script {
+        sleep(Duration::from_millis(2)).await;
+        events.send(event);
+    }
+    // `events` is dropped here: the inspector's loop ends
+}
+
+async fn inspector(mut events: Receiver<WebEvent>, clicks: Sender<(i64, i64)>, log: Rc<RefCell<Vec<String>>>) {
+    while let Some(event) = events.recv().await {
+        log.borrow_mut().push(describe(&event));
+        if let WebEvent::Click { x, y } = event {
+            clicks.send((x, y));
+        }
+    }
+}
+
+async fn click_counter(mut clicks: Receiver<(i64, i64)>) -> usize {
+    let mut count = 0;
+    while clicks.recv().await.is_some() {
+        count += 1;
+    }
+    count
+}
+
+// Runs the three tasks, returns the log and the number of clicks
+pub(crate) fn handle_web_events() -> (Vec<String>, usize) {
+    let (events_tx, events_rx) = channel();
+    let (clicks_tx, clicks_rx) = channel();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let count = Rc::new(RefCell::new(0));
+
+    let mut executor = Executor::new();
+    executor.spawn(browser(events_tx));
+    executor.spawn(inspector(events_rx, clicks_tx, Rc::clone(&log)));
+    let counted = Rc::clone(&count);
+    executor.spawn(async move {
+        *counted.borrow_mut() = click_counter(clicks_rx).await;
+    });
+    executor.run();
+
+    let log = log.borrow().clone();
+    let count = *count.borrow();
+    (log, count)
+}
+
+// Some CPU work, cut in pieces by `yield_now` so other tasks get a turn
+async fn crunch(n: u64, results: Arc<AtomicUsize>) {
+    let mut total = 0u64;
+    for round in 0..5 {
+        total += (0..2_000 * n).map(|i| i % 7).sum::<u64>();
+        if round % 2 == 0 {
+            yield_now().await;
+        } else {
+            sleep(Duration::from_micros(200)).await;
+        }
+    }
+    results.fetch_add(total as usize, Ordering::Relaxed);
+}
+
+pub(crate) fn async_demo() {
+    // A future does nothing until it's polled
+    let future = async {
+        println!("inside the async block");
+        42
+    };
+    println!("the future was created, nothing printed yet");
+    println!("block_on gave {}", block_on(future));
+
+    let start = Instant::now();
+    block_on(async {
+        // Two sleeps one after the other
+        sleep(Duration::from_millis(5)).await;
+        sleep(Duration::from_millis(5)).await;
+    });
+    println!("two 5ms sleeps took {:?}", start.elapsed());
+    assert!(start.elapsed() >= Duration::from_millis(10));
+
+    // A message sent from a plain thread wakes the task up
+    let (tx, mut rx) = channel();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(5));
+        tx.send("hello from a thread");
+    });
+    println!("received: {:?}", block_on(async move { rx.recv().await }));
+    sender.join().unwrap();
+
+    let (log, clicks) = handle_web_events();
+    for line in &log {
+        println!("{}", line);
+    }
+    println!("{} clicks", clicks);
+
+    let mut executor = WorkStealingExecutor::new(4);
+    let results = Arc::new(AtomicUsize::new(0));
+    for n in 1..=32 {
+        // Every task starts on worker 0
+        executor.spawn(crunch(n, Arc::clone(&results)), false);
+    }
+    let stats = executor.run();
+    println!("work stealing, per worker: {:?}", stats);
+    // Every `yield_now` costs one more poll, a `sleep` one more only if its
+    // 200µs weren't already over by the time it was first polled
+    println!(
+        "{} polls for 32 tasks, between {} and {}",
+        stats.iter().map(|worker| worker.polls).sum::<usize>(),
+        32 * 4,
+        32 * 6
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_on_returns_the_output() {
+        assert_eq!(block_on(async { 1 + 2 }), 3);
+    }
+
+    #[test]
+    fn tasks_interleave_while_sleeping() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let mut executor = Executor::new();
+
+        for (name, delay) in [("slow", 20), ("fast", 5)] {
+            let order = Rc::clone(&order);
+            executor.spawn(async move {
+                order.borrow_mut().push(format!("{} started", name));
+                sleep(Duration::from_millis(delay)).await;
+                order.borrow_mut().push(format!("{} done", name));
+            });
+        }
+        executor.run();
+
+        assert_eq!(*order.borrow(), ["slow started", "fast started", "fast done", "slow done"]);
+    }
+
+    #[test]
+    fn channel_ends_when_the_senders_are_gone() {
+        let (tx, mut rx) = channel();
+        let tx2 = tx.clone();
+
+        let received = block_on(async move {
+            tx.send(1);
+            tx2.send(2);
+            drop(tx);
+            drop(tx2);
+
+            let mut received = Vec::new();
+            while let Some(n) = rx.recv().await {
+                received.push(n);
+            }
+            received
+        });
+
+        assert_eq!(received, [1, 2]);
+    }
+
+    #[test]
+    fn pending_tasks_are_only_polled_when_woken() {
+        let mut executor = Executor::new();
+        executor.spawn(async {
+            sleep(Duration::from_millis(10)).await;
+        });
+        executor.run();
+
+        // Once to start, once when the timer fired: no busy polling
+        assert_eq!(executor.polls, 2);
+    }
+
+    #[test]
+    fn web_events_are_handled_in_order() {
+        let (log, clicks) = handle_web_events();
+        assert_eq!(log.len(), 6);
+        assert_eq!(log[0], "page loaded");
+        assert_eq!(log[3], "pasted \"my text\".");
+        assert_eq!(clicks, 2);
+    }
+
+    #[test]
+    fn work_stealing_runs_every_task() {
+        let mut executor = WorkStealingExecutor::new(3);
+        let results = Arc::new(AtomicUsize::new(0));
+        for _ in 0..20 {
+            let results = Arc::clone(&results);
+            executor.spawn(
+                async move {
+                    yield_now().await;
+                    sleep(Duration::from_millis(1)).await;
+                    results.fetch_add(1, Ordering::Relaxed);
+                },
+                false,
+            );
+        }
+
+        let stats = executor.run();
+        assert_eq!(results.load(Ordering::Relaxed), 20);
+        assert_eq!(stats.len(), 3);
+        // 20 tasks, each polled twice, or 3 times if the sleep was still
+        // pending when it was first polled (a worker busy for over 1ms
+        // can find it already over)
+        let polls = stats.iter().map(|worker| worker.polls).sum::<usize>();
+        assert!((40..=60).contains(&polls), "{} polls", polls);
+    }
+
+    #[test]
+    fn crunching_tasks_all_finish() {
+        let mut executor = WorkStealingExecutor::new(4);
+        let results = Arc::new(AtomicUsize::new(0));
+        for n in 1..=8 {
+            executor.spawn(crunch(n, Arc::clone(&results)), false);
+        }
+
+        let stats = executor.run();
+        // Each round of `crunch(n)` adds up `i % 7` for `i` in `0..2_000 * n`
+        let expected: u64 = (1..=8).map(|n| 5 * (0..2_000 * n).map(|i| i % 7).sum::<u64>()).sum();
+        assert_eq!(results.load(Ordering::Relaxed), expected as usize);
+        // 3 yields always give way, the 2 sleeps only if still pending
+        let polls = stats.iter().map(|worker| worker.polls).sum::<usize>();
+        assert!((8 * 4..=8 * 6).contains(&polls), "{} polls", polls);
+    }
+}
@@ -0,0 +1,541 @@
+// !!!!!!!!!!!!!!!!!!!! A compact binary encoding !!!!!!!!!!!!!!!!!!!!
+
+/*
+`size_of_val` tells us how many bytes a value takes in memory, but in memory a `u8` next to a `String`
+is padded, and a `String` is really a pointer, a length and a capacity (24 bytes) pointing somewhere else.
+To send a value over the network or to a file we need a flat list of bytes instead:
+
+    - integers are varints: 7 bits per byte, least significant group first (little-endian), and the
+      high bit of every byte says "another byte follows". So 27 is 1 byte, 300 is 2 bytes.
+      (https://en.wikipedia.org/wiki/LEB128) Only the shortest form is accepted, so every number has a
+      single encoding.
+    - signed integers are zigzag encoded first, so small negative numbers stay small too:
+      0 -> 0, -1 -> 1, 1 -> 2, -2 -> 3, ...
+    - floats are their 4 (or 8) IEEE 754 bytes, little-endian
+    - strings are their length (a varint) followed by their UTF-8 bytes
+    - enums are a tag (the index of the variant, a varint) followed by the variant's fields
+*/
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{Color, Pair, Person, WebEvent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DecodeErrorKind {
+    UnexpectedEnd,
+    // More than 10 bytes, or more bits than the target integer has
+    VarintOverflow,
+    // Zeros at the end, e.g. `[0x80, 0x00]` for 0: every number has one encoding
+    VarintOverlong,
+    InvalidUtf8,
+    InvalidChar(u32),
+    UnknownTag(u64),
+    TrailingBytes(usize),
+}
+
+// `offset` is the index of the byte where decoding failed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodeError {
+    pub(crate) offset: usize,
+    pub(crate) kind: DecodeErrorKind,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at byte {}: ", self.offset)?;
+
+        match self.kind {
+            DecodeErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeErrorKind::VarintOverflow => write!(f, "integer is too large"),
+            DecodeErrorKind::VarintOverlong => write!(f, "integer uses more bytes than it needs"),
+            DecodeErrorKind::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            DecodeErrorKind::InvalidChar(code) => write!(f, "{:#x} is not a valid `char`", code),
+            DecodeErrorKind::UnknownTag(tag) => write!(f, "unknown enum tag {}", tag),
+            DecodeErrorKind::TrailingBytes(n) => write!(f, "{} bytes left over after the value", n),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+// Reads bytes from the front of a slice and remembers how far it got
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, offset: 0 }
+    }
+
+    fn error(&self, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError { offset: self.offset, kind }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        // Never trust a length read from the input: check it before using it
+        if n > self.bytes.len() - self.offset {
+            return Err(self.error(DecodeErrorKind::UnexpectedEnd));
+        }
+
+        let taken = &self.bytes[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(taken)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, DecodeError> {
+        let start = self.offset;
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            let bits = u64::from(byte & 0x7f);
+
+            // The 10th byte may only carry the single bit that's left
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError { offset: start, kind: DecodeErrorKind::VarintOverflow });
+            }
+
+            value |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                // `write_varint` never ends with a zero byte, unless it's the only one
+                if byte == 0 && shift > 0 {
+                    return Err(DecodeError { offset: start, kind: DecodeErrorKind::VarintOverlong });
+                }
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError { offset: start, kind: DecodeErrorKind::VarintOverflow })
+    }
+
+    // Reads a varint and checks that it fits in `T`
+    fn read_unsigned<T: TryFrom<u64>>(&mut self) -> Result<T, DecodeError> {
+        let start = self.offset;
+        let value = self.read_varint()?;
+        T::try_from(value).map_err(|_| DecodeError { offset: start, kind: DecodeErrorKind::VarintOverflow })
+    }
+
+    fn read_signed<T: TryFrom<i64>>(&mut self) -> Result<T, DecodeError> {
+        let start = self.offset;
+        let value = unzigzag(self.read_varint()?);
+        T::try_from(value).map_err(|_| DecodeError { offset: start, kind: DecodeErrorKind::VarintOverflow })
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+pub(crate) trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+}
+
+pub(crate) trait Decode: Sized {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError>;
+
+    // Decodes exactly one value: leftover bytes are an error too
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let value = Self::decode(&mut reader)?;
+
+        match bytes.len() - reader.offset {
+            0 => Ok(value),
+            left => Err(reader.error(DecodeErrorKind::TrailingBytes(left))),
+        }
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Building blocks !!!!!!!!!!!!!!!!!!!!
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, u64::from(*self));
+    }
+}
+
+impl Decode for u8 {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_unsigned()
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, zigzag(i64::from(*self)));
+    }
+}
+
+impl Decode for i32 {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_signed()
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, zigzag(*self));
+    }
+}
+
+impl Decode for i64 {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_signed()
+    }
+}
+
+// Floats are stored as-is, varints would make them bigger
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f32 {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let bytes = reader.take(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, u64::from(*self));
+    }
+}
+
+impl Decode for char {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let start = reader.offset;
+        let code: u32 = reader.read_unsigned()?;
+
+        // Not every `u32` is a `char`, e.g. surrogates like 0xD800 aren't
+        char::from_u32(code).ok_or(DecodeError { offset: start, kind: DecodeErrorKind::InvalidChar(code) })
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.len() as u64);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Decode for String {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let len: usize = reader.read_unsigned()?;
+        let start = reader.offset;
+        let bytes = reader.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError { offset: start, kind: DecodeErrorKind::InvalidUtf8 })
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! The notes' types !!!!!!!!!!!!!!!!!!!!
+
+// A struct is just its fields, one after the other: no names, no padding
+impl Encode for Person {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.age.encode(out);
+    }
+}
+
+impl Decode for Person {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Person { name: String::decode(reader)?, age: u8::decode(reader)? })
+    }
+}
+
+impl Encode for Pair {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl Decode for Pair {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Pair(i32::decode(reader)?, f32::decode(reader)?))
+    }
+}
+
+impl Encode for WebEvent {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            WebEvent::PageLoad => write_varint(out, 0),
+            WebEvent::PageUnload => write_varint(out, 1),
+            WebEvent::KeyPress(c) => {
+                write_varint(out, 2);
+                c.encode(out);
+            }
+            WebEvent::Paste(s) => {
+                write_varint(out, 3);
+                s.encode(out);
+            }
+            WebEvent::Click { x, y } => {
+                write_varint(out, 4);
+                x.encode(out);
+                y.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for WebEvent {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let start = reader.offset;
+
+        match reader.read_varint()? {
+            0 => Ok(WebEvent::PageLoad),
+            1 => Ok(WebEvent::PageUnload),
+            2 => Ok(WebEvent::KeyPress(char::decode(reader)?)),
+            3 => Ok(WebEvent::Paste(String::decode(reader)?)),
+            4 => Ok(WebEvent::Click { x: i64::decode(reader)?, y: i64::decode(reader)? }),
+            tag => Err(DecodeError { offset: start, kind: DecodeErrorKind::UnknownTag(tag) }),
+        }
+    }
+}
+
+// The tag is the variant's index, not its discriminant: `Color::Red as i32`
+// would need 4 bytes, the index needs 1
+impl Encode for Color {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag = match self {
+            Color::Red => 0,
+            Color::Green => 1,
+            Color::Blue => 2,
+        };
+        write_varint(out, tag);
+    }
+}
+
+impl Decode for Color {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let start = reader.offset;
+
+        match reader.read_varint()? {
+            0 => Ok(Color::Red),
+            1 => Ok(Color::Green),
+            2 => Ok(Color::Blue),
+            tag => Err(DecodeError { offset: start, kind: DecodeErrorKind::UnknownTag(tag) }),
+        }
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Hex dump !!!!!!!!!!!!!!!!!!!!
+
+// Like `xxd`: the offset, 16 bytes in hex, and the printable ones as text
+pub(crate) fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}: ", row * 16));
+
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => out.push_str(&format!("{:02x} ", byte)),
+                None => out.push_str("   "),
+            }
+        }
+
+        out.push(' ');
+        for &byte in chunk {
+            out.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+// Encodes a value, prints its bytes, and what they decode back to
+fn show<T: Encode + Decode + fmt::Debug>(value: T) {
+    let bytes = value.to_bytes();
+
+    println!("{:?}: {} bytes encoded, {} bytes in memory", value, bytes.len(), std::mem::size_of_val(&value));
+    print!("{}", hex_dump(&bytes));
+    println!("decoded back: {:?}", T::from_bytes(&bytes));
+}
+
+pub(crate) fn binary_demo() {
+    show(Person { name: String::from("Peter"), age: 27 });
+    show(Pair(-300, 0.4));
+    show(WebEvent::Click { x: -1, y: 300 });
+    show(WebEvent::Paste(String::from("ç🦀")));
+    show(Color::Blue);
+
+    // Broken input gives an error, not a panic
+    for bytes in [&[0x05, b'P', b'e'][..], &[0x07][..], &[0x02, 0x80, 0x80, 0xdc, 0x03][..], &[0x00, 0xff][..]] {
+        if let Err(e) = WebEvent::from_bytes(bytes) {
+            println!("{:02x?} -> error: {}", bytes, e);
+        }
+    }
+
+    // The tests below feed thousands of random inputs to the decoders, which may fail but never panic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShift64;
+
+    fn round_trip<T: Encode + Decode + PartialEq + fmt::Debug>(value: T) {
+        assert_eq!(T::from_bytes(&value.to_bytes()), Ok(value));
+    }
+
+    // Fuzzing: feed random bytes to a decoder. It may return an error, but it
+    // must never panic (or try to allocate a huge buffer because of a bad length)
+    fn fuzz<T: Decode>(rng: &mut XorShift64, runs: u32) -> u32 {
+        let mut decoded = 0;
+
+        for _ in 0..runs {
+            let bytes: Vec<u8> = (0..rng.below(24)).map(|_| rng.next_u64() as u8).collect();
+
+            if T::from_bytes(&bytes).is_ok() {
+                decoded += 1;
+            }
+        }
+
+        decoded
+    }
+
+    #[test]
+    fn values_round_trip() {
+        round_trip(Person { name: String::from("Peter"), age: 27 });
+        round_trip(Person { name: String::new(), age: 255 });
+        round_trip(Pair(-300, 0.4));
+        round_trip(Pair(i32::MIN, f32::MAX));
+        round_trip(WebEvent::Click { x: -1, y: 300 });
+        round_trip(WebEvent::Paste(String::from("ç🦀")));
+        round_trip(Color::Blue);
+    }
+
+    fn varint(bytes: &[u8]) -> Result<u64, DecodeError> {
+        Reader::new(bytes).read_varint()
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX - 1, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            assert_eq!(varint(&bytes), Ok(value));
+        }
+
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, u64::MAX);
+        assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+
+        // Small numbers of either sign stay small
+        assert_eq!([0, -1, 1, -2].map(zigzag), [0, 1, 2, 3]);
+        assert_eq!(zigzag(i64::MAX), u64::MAX - 1);
+        assert_eq!(zigzag(i64::MIN), u64::MAX);
+        for value in [0, -1, 1, i64::MIN, i64::MIN + 1, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+            round_trip(value);
+        }
+        round_trip(i32::MIN);
+        round_trip(i32::MAX);
+        round_trip(0u8);
+        round_trip(u8::MAX);
+    }
+
+    #[test]
+    fn bad_varints_are_rejected() {
+        let too_large = DecodeError { offset: 0, kind: DecodeErrorKind::VarintOverflow };
+        let overlong = DecodeError { offset: 0, kind: DecodeErrorKind::VarintOverlong };
+
+        // 11 bytes: still more to come after the 10th
+        let mut eleven = [0xff; 11];
+        eleven[10] = 0x01;
+        assert_eq!(varint(&eleven), Err(too_large.clone()));
+        // 10 bytes, but the last one has more than the one bit left
+        let mut ten = [0xff; 10];
+        ten[9] = 0x02;
+        assert_eq!(varint(&ten), Err(too_large.clone()));
+        // Longer than needed
+        assert_eq!(varint(&[0x80, 0x00]), Err(overlong.clone()));
+        assert_eq!(varint(&[0x81, 0x80, 0x00]), Err(overlong));
+        assert_eq!(varint(&[0x80]), Err(DecodeError { offset: 1, kind: DecodeErrorKind::UnexpectedEnd }));
+
+        // Fits in a `u64`, not in the target type
+        assert_eq!(u8::from_bytes(&[0x80, 0x02]).unwrap_err(), too_large);
+        let mut too_big = Vec::new();
+        write_varint(&mut too_big, zigzag(i64::from(i32::MAX) + 1));
+        assert_eq!(i32::from_bytes(&too_big).unwrap_err(), too_large);
+    }
+
+    #[test]
+    fn lengths_are_checked() {
+        // 5 bytes promised, 2 given
+        assert_eq!(
+            String::from_bytes(&[0x05, b'h', b'i']),
+            Err(DecodeError { offset: 1, kind: DecodeErrorKind::UnexpectedEnd })
+        );
+        // The largest length there is, with nothing behind it
+        let mut huge = Vec::new();
+        write_varint(&mut huge, u64::MAX);
+        huge.push(b'x');
+        assert!(String::from_bytes(&huge).is_err());
+
+        assert_eq!(String::from_bytes(&[0x02, b'h', b'i']), Ok(String::from("hi")));
+        assert_eq!(
+            String::from_bytes(&[0x01, b'h', b'i']),
+            Err(DecodeError { offset: 2, kind: DecodeErrorKind::TrailingBytes(1) })
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        let peter = Person { name: String::from("Peter"), age: 27 };
+        assert_eq!(peter.to_bytes(), [0x05, b'P', b'e', b't', b'e', b'r', 27]);
+        assert_eq!(Color::Blue.to_bytes(), [0x02]);
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        for bytes in [&[0x05, b'P', b'e'][..], &[0x07][..], &[0x02, 0x80, 0x80, 0xdc, 0x03][..], &[0x00, 0xff][..]] {
+            assert!(WebEvent::from_bytes(bytes).is_err(), "{:02x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        let mut rng = XorShift64::new(28);
+
+        // Some random inputs have to decode, or the decoders were never
+        // tried past their first error
+        assert!(fuzz::<Person>(&mut rng, 10_000) > 0);
+        assert!(fuzz::<Pair>(&mut rng, 10_000) > 0);
+        assert!(fuzz::<WebEvent>(&mut rng, 10_000) > 0);
+        assert!(fuzz::<Color>(&mut rng, 10_000) > 0);
+    }
+}
@@ -0,0 +1,20 @@
+// Calls the Rust functions of ffi_export.rs, see `./main ffi` in unsafe_rust.rs
+
+#include <stdint.h>
+#include <stdio.h>
+
+// The declarations have to match the Rust signatures by hand: nothing checks
+// them. (Tools like cbindgen generate them from the Rust code.)
+int64_t notes_sum(const int32_t *numbers, size_t len);
+int64_t notes_count_letters(const char *word);
+
+int main(void) {
+    int32_t numbers[] = {1, 2, 3, 4, 2147483647};
+
+    printf("notes_sum = %lld\n", (long long)notes_sum(numbers, 5));
+    printf("notes_sum of nothing = %lld\n", (long long)notes_sum(NULL, 0));
+    printf("notes_count_letters(\"fizz-buzz 15\") = %lld\n", (long long)notes_count_letters("fizz-buzz 15"));
+    printf("notes_count_letters(NULL) = %lld\n", (long long)notes_count_letters(NULL));
+
+    return 0;
+}
@@ -0,0 +1,311 @@
+// !!!!!!!!!!!!!!!!!!!! Closures https://doc.rust-lang.org/rust-by-example/fn/closures.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+A closure is a function that can capture variables from the scope it's defined in. How it uses what it captured
+decides which traits it implements:
+
+    Fn      only reads its captures (captured by reference), can be called any number of times
+    FnMut   modifies its captures (captured by mutable reference), needs `&mut` to be called
+    FnOnce  moves a capture out (e.g. drops it or returns it), can only be called once
+
+Every `Fn` is also an `FnMut`, and every `FnMut` is also an `FnOnce`. The `move` keyword makes a closure take
+ownership of its captures instead of borrowing them, which is what threads need.
+*/
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::thread;
+
+use crate::WebEvent;
+
+// !!!!!!!!!!!!!!!!!!!! Taking closures as arguments !!!!!!!!!!!!!!!!!!!!
+
+// The bound says how `apply` is going to call `f`
+fn apply_fn(f: impl Fn()) {
+    f();
+    f();
+}
+
+fn apply_fn_mut(mut f: impl FnMut()) {
+    f();
+    f();
+}
+
+fn apply_fn_once(f: impl FnOnce() -> String) -> String {
+    f()
+    // f() <- Error! `f` was moved by the first call
+}
+
+// !!!!!!!!!!!!!!!!!!!! Returning closures !!!!!!!!!!!!!!!!!!!!
+
+// Every closure has its own anonymous type, so we can't write it down:
+// `impl Fn` means "some type implementing `Fn`". `move` is required, `n`
+// would be gone once `make_adder` returns.
+pub(crate) fn make_adder(n: i32) -> impl Fn(i32) -> i32 {
+    move |x| x + n
+}
+
+// A closure with its own state, kept between calls
+pub(crate) fn make_counter() -> impl FnMut() -> u32 {
+    let mut count = 0;
+    move || {
+        count += 1;
+        count
+    }
+}
+
+// `impl Fn` must be one single type. To return one of two different closures
+// they have to be boxed: `Box<dyn Fn>` is a pointer to "any `Fn`".
+pub(crate) fn make_greeter(formal: bool) -> Box<dyn Fn(&str) -> String> {
+    if formal {
+        Box::new(|name| format!("Good morning, {}.", name))
+    } else {
+        let emoji = String::from("👋");
+        Box::new(move |name| format!("hi {} {}", name, emoji))
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! A callback registry !!!!!!!!!!!!!!!!!!!!
+
+/*
+The handlers are `Box<dyn FnMut(&WebEvent) + 'a>`:
+    - `dyn`: every handler is a different closure type, they only share the trait
+    - `FnMut`: handlers may update what they captured, e.g. a counter
+    - `'a`: handlers may borrow local variables, as long as those outlive the registry
+*/
+type Handler<'a> = Box<dyn FnMut(&WebEvent) + 'a>;
+
+pub(crate) struct EventRegistry<'a> {
+    handlers: Vec<(&'static str, Handler<'a>)>,
+}
+
+impl<'a> EventRegistry<'a> {
+    pub(crate) fn new() -> Self {
+        EventRegistry { handlers: Vec::new() }
+    }
+
+    pub(crate) fn on(&mut self, name: &'static str, handler: impl FnMut(&WebEvent) + 'a) {
+        self.handlers.push((name, Box::new(handler)));
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) {
+        self.handlers.retain(|(handler_name, _)| *handler_name != name);
+    }
+
+    // `&mut self`, because calling an `FnMut` needs mutable access to it
+    pub(crate) fn dispatch(&mut self, event: &WebEvent) {
+        for (_, handler) in self.handlers.iter_mut() {
+            handler(event);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.handlers.len()
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Memoisation !!!!!!!!!!!!!!!!!!!!
+
+// Wraps a closure and remembers its results, so every argument is only
+// computed once. `F` is a type parameter: any closure with the right
+// signature fits, and no boxing is needed.
+pub(crate) struct Memoize<A, R, F>
+where
+    F: Fn(A) -> R,
+{
+    f: F,
+    cache: HashMap<A, R>,
+    pub(crate) hits: u32,
+    pub(crate) misses: u32,
+}
+
+impl<A, R, F> Memoize<A, R, F>
+where
+    A: Eq + Hash + Clone,
+    R: Clone,
+    F: Fn(A) -> R,
+{
+    pub(crate) fn new(f: F) -> Self {
+        Memoize { f, cache: HashMap::new(), hits: 0, misses: 0 }
+    }
+
+    pub(crate) fn call(&mut self, arg: A) -> R {
+        if let Some(result) = self.cache.get(&arg) {
+            self.hits += 1;
+            return result.clone();
+        }
+
+        self.misses += 1;
+        let result = (self.f)(arg.clone());
+        self.cache.insert(arg, result.clone());
+        result
+    }
+}
+
+pub(crate) fn closures_demo() {
+    // Captured by reference: `greeting` is only read, so the closure is `Fn`
+    let greeting = String::from("Hello");
+    let greet = || println!("{} from a closure", greeting);
+    apply_fn(greet);
+    // `greeting` was only borrowed, we can still use it
+    println!("{} is still here", greeting);
+
+    // Captured by mutable reference: the closure is `FnMut`
+    let mut calls = 0;
+    let count_calls = || calls += 1;
+    apply_fn_mut(count_calls);
+    println!("called {} times", calls);
+
+    // Moved out: returning `greeting` moves it out of the closure, so it's
+    // `FnOnce`
+    let give_away = move || greeting;
+    println!("{}", apply_fn_once(give_away));
+    // Error! `greeting` was moved into `give_away`
+    // println!("{}", greeting);
+    // TODO ^ Try uncommenting this line
+
+    // Captured by move, into threads: a thread may outlive the current
+    // function, so it must own everything it uses
+    let events = [WebEvent::PageLoad, WebEvent::KeyPress('q'), WebEvent::PageUnload];
+    let handle = thread::spawn(move || events.len());
+    // `events` now belongs to the thread
+    println!("the thread counted {} events", handle.join().unwrap());
+
+    // Returned closures
+    let add_five = make_adder(5);
+    println!("add_five(10) = {}", add_five(10));
+
+    let mut counter = make_counter();
+    counter();
+    println!("counter: {}", counter());
+
+    for formal in [true, false] {
+        println!("{}", make_greeter(formal)("Ekrem"));
+    }
+
+    // The registry
+    let mut clicks = 0;
+    let mut log = Vec::new();
+
+    {
+        let mut registry = EventRegistry::new();
+
+        registry.on("print", |event| println!("event: {:?}", event));
+        registry.on("count clicks", |event| {
+            if let WebEvent::Click { .. } = event {
+                clicks += 1;
+            }
+        });
+        registry.on("log", |event| log.push(format!("{:?}", event)));
+
+        registry.dispatch(&WebEvent::Click { x: 20, y: 80 });
+        registry.dispatch(&WebEvent::Paste(String::from("my text")));
+
+        registry.remove("print");
+        println!("{} handlers left", registry.len());
+        registry.dispatch(&WebEvent::Click { x: 0, y: 0 });
+
+        // `clicks` and `log` are borrowed by the handlers until `registry` is
+        // dropped, at the end of this scope
+    }
+
+    println!("{} clicks, log: {:?}", clicks, log);
+
+    // Memoisation: the closure counts how often it really runs
+    let slow_calls = std::cell::Cell::new(0);
+    let mut square = Memoize::new(|n: u64| {
+        slow_calls.set(slow_calls.get() + 1);
+        n * n
+    });
+
+    let results: Vec<u64> = [3, 4, 3, 3, 4, 5].into_iter().map(|n| square.call(n)).collect();
+    println!("{:?}: {} hits, {} misses", results, square.hits, square.misses);
+    println!("the closure really ran {} times", slow_calls.get());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn how_closures_are_called() {
+        let calls = Cell::new(0);
+        apply_fn(|| calls.set(calls.get() + 1));
+        assert_eq!(calls.get(), 2);
+
+        let mut seen = Vec::new();
+        apply_fn_mut(|| seen.push(seen.len()));
+        assert_eq!(seen, [0, 1]);
+
+        let owned = String::from("moved out");
+        assert_eq!(apply_fn_once(move || owned), "moved out");
+    }
+
+    #[test]
+    fn returned_closures() {
+        let add_five = make_adder(5);
+        assert_eq!(add_five(10), 15);
+        assert_eq!(add_five(-5), 0);
+        // Each adder captured its own `n`
+        assert_eq!(make_adder(1)(1), 2);
+
+        // Each counter has its own state
+        let mut first = make_counter();
+        let mut second = make_counter();
+        assert_eq!((first(), first(), first()), (1, 2, 3));
+        assert_eq!(second(), 1);
+
+        // Both are the same type, `Box<dyn Fn(&str) -> String>`, so they fit in one array
+        let greeters = [make_greeter(true), make_greeter(false)];
+        assert_eq!(greeters[0]("Ekrem"), "Good morning, Ekrem.");
+        assert_eq!(greeters[1]("Ekrem"), "hi Ekrem 👋");
+    }
+
+    #[test]
+    fn registry() {
+        let mut clicks = 0;
+        let mut log = Vec::new();
+        {
+            let mut registry = EventRegistry::new();
+            registry.on("count clicks", |event| {
+                if let WebEvent::Click { .. } = event {
+                    clicks += 1;
+                }
+            });
+            registry.on("log", |event| log.push(format!("{:?}", event)));
+            assert_eq!(registry.len(), 2);
+
+            registry.dispatch(&WebEvent::Click { x: 20, y: 80 });
+            registry.dispatch(&WebEvent::Paste(String::from("my text")));
+
+            registry.remove("log");
+            registry.remove("no such handler");
+            assert_eq!(registry.len(), 1);
+            registry.dispatch(&WebEvent::Click { x: 0, y: 0 });
+        }
+
+        assert_eq!(clicks, 2);
+        assert_eq!(log, ["Click { x: 20, y: 80 }", "Paste(\"my text\")"]);
+    }
+
+    #[test]
+    fn memoisation() {
+        let runs = Cell::new(0);
+        let mut square = Memoize::new(|n: u64| {
+            runs.set(runs.get() + 1);
+            n * n
+        });
+
+        let results: Vec<u64> = [3, 4, 3, 3, 4, 5].into_iter().map(|n| square.call(n)).collect();
+        assert_eq!(results, [9, 16, 9, 9, 16, 25]);
+        assert_eq!((square.hits, square.misses), (3, 3));
+        assert_eq!(runs.get(), 3);
+
+        // Owned arguments and results are cloned out of the cache
+        let mut shout = Memoize::new(|s: String| s.to_uppercase());
+        assert_eq!(shout.call(String::from("hi")), "HI");
+        assert_eq!(shout.call(String::from("hi")), "HI");
+        assert_eq!((shout.hits, shout.misses), (1, 1));
+    }
+}
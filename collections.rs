@@ -0,0 +1,367 @@
+// !!!!!!!!!!!!!!!!!!!! Collections https://doc.rust-lang.org/std/collections/index.html !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/rust-by-example/std/hash.html
+
+/*
+`Vec` is the right answer most of the time. The others are for when a `Vec` would make us search, shift or sort
+over and over:
+
+    collection      what it's for                            lookup      insert       remove       in order?
+    Vec             a list, indexed by position              O(1)        O(1)* end    O(n)         insertion
+    VecDeque        a queue, pushed and popped at both ends  O(1)        O(1)* ends   O(1) ends    insertion
+    HashMap         key -> value                             O(1)~       O(1)~        O(1)~        no
+    BTreeMap        key -> value, sorted by key              O(log n)    O(log n)     O(log n)     by key
+    HashSet         is it there? (a HashMap without values)  O(1)~       O(1)~        O(1)~        no
+    BinaryHeap      always knows its largest element         O(1) max    O(log n)     O(log n) max no
+
+    * amortized: sometimes the buffer is full and everything is moved to a bigger one
+    ~ on average: a bad hash function (or an attacker choosing the keys) makes it O(n)
+
+The tools below: the most used words in the comments of these notes, an inventory read from "3 apples" lines, and
+the top-k query, which only needs a heap of k elements instead of sorting everything.
+*/
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::Bound;
+
+use crate::errors::{self, Context, ContextError, NotesError};
+
+// !!!!!!!!!!!!!!!!!!!! Word frequency, with HashMap and HashSet !!!!!!!!!!!!!!!!!!!!
+
+// The text of every `//` and `/* */` comment. Good enough for our own files:
+// a "//" inside a string literal would be taken for a comment.
+pub(crate) fn comments(source: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = source;
+
+    loop {
+        // Whichever starts first: a `//` inside a block comment is part of it
+        let (start, end_marker) = match (rest.find("//"), rest.find("/*")) {
+            (None, None) => return found,
+            (Some(line), Some(block)) if block < line => (block, "*/"),
+            (Some(line), _) => (line, "\n"),
+            (None, Some(block)) => (block, "*/"),
+        };
+
+        let text = &rest[start + 2..];
+        let end = text.find(end_marker).unwrap_or(text.len());
+        found.push(&text[..end]);
+        rest = &text[(end + end_marker.len()).min(text.len())..];
+    }
+}
+
+// Too common to say anything about the notes
+const STOP_WORDS: [&str; 23] = [
+    "the", "and", "for", "that", "this", "with", "are", "can", "not", "but", "from", "its", "was", "you", "all",
+    "has", "have", "which", "when", "then", "their", "there", "into",
+];
+
+// Lowercase words of 3 letters or more, stop words excluded.
+// O(n) for n words: one hash and one lookup per word.
+pub(crate) fn word_frequencies(texts: &[&str]) -> HashMap<String, usize> {
+    // Building the set is O(stop words), then every `contains` is O(1)
+    let stop_words: HashSet<&str> = STOP_WORDS.into_iter().collect();
+    let mut frequencies = HashMap::new();
+
+    for text in texts {
+        // Links are skipped, or "https" would be the most used word. Then
+        // "box's" is "box" and "s", "u8" is "u".
+        let tokens = text.split_whitespace().filter(|token| !token.contains("://"));
+        for word in tokens.flat_map(|token| token.split(|c: char| !c.is_alphabetic())) {
+            let word = word.to_lowercase();
+            if word.chars().count() >= 3 && !stop_words.contains(&word[..]) {
+                // `entry` looks the key up once, for both the read and the write
+                *frequencies.entry(word).or_insert(0) += 1;
+            }
+        }
+    }
+
+    frequencies
+}
+
+// Words both texts use, and the ones only the first one uses, alphabetically
+pub(crate) fn compare_vocabulary<'a>(
+    first: &'a HashMap<String, usize>,
+    second: &'a HashMap<String, usize>,
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let first: HashSet<&str> = first.keys().map(String::as_str).collect();
+    let second: HashSet<&str> = second.keys().map(String::as_str).collect();
+
+    // A set has no order, so the results are sorted before being returned
+    let mut shared: Vec<&str> = first.intersection(&second).copied().collect();
+    let mut only_first: Vec<&str> = first.difference(&second).copied().collect();
+    shared.sort_unstable();
+    only_first.sort_unstable();
+
+    (shared, only_first)
+}
+
+// !!!!!!!!!!!!!!!!!!!! Top k, with a BinaryHeap !!!!!!!!!!!!!!!!!!!!
+
+/*
+Sorting all n words to keep k of them is O(n log n). A heap of at most k elements does it in O(n log k): `Reverse`
+turns the max-heap into a min-heap, so the smallest of the k best is always on top, ready to be replaced by a
+better one. With k = 10 and thousands of words, the heap stays tiny.
+*/
+pub(crate) fn top_k(frequencies: &HashMap<String, usize>, k: usize) -> Vec<(&str, usize)> {
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+
+    for (word, &count) in frequencies {
+        // Ties are broken alphabetically: `Reverse(word)` makes "apple" beat
+        // "banana", so the result doesn't depend on the HashMap's order
+        heap.push(Reverse((count, Reverse(word.as_str()))));
+        if heap.len() > k {
+            // Drops the smallest
+            heap.pop();
+        }
+    }
+
+    // `into_sorted_vec` is ascending, and ascending `Reverse`s is descending
+    heap.into_sorted_vec().into_iter().map(|Reverse((count, Reverse(word)))| (word, count)).collect()
+}
+
+// !!!!!!!!!!!!!!!!!!!! An inventory, with BTreeMap and VecDeque !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Change {
+    Added(u32, String),
+    Removed(u32, String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(count, item) => write!(f, "+{} {}", count, item),
+            Change::Removed(count, item) => write!(f, "-{} {}", count, item),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Inventory {
+    // A `BTreeMap`, so listing the inventory is already alphabetical
+    items: BTreeMap<String, u32>,
+    // The last changes, oldest first. Adding at the back and dropping from the
+    // front is O(1) for a `VecDeque`, but O(n) for a `Vec`.
+    history: VecDeque<Change>,
+}
+
+impl Inventory {
+    const HISTORY: usize = 5;
+
+    pub(crate) fn new() -> Self {
+        Inventory::default()
+    }
+
+    // "3 apples\n2 pears", in the format of `errors::parse_count_item`
+    pub(crate) fn from_lines(text: &str) -> Result<Self, ContextError> {
+        let mut inventory = Inventory::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (count, item) = errors::parse_count_item(line.trim())?;
+            inventory.add(count, item).with_context(|| format!("adding '{}'", line.trim()))?;
+        }
+        Ok(inventory)
+    }
+
+    fn record(&mut self, change: Change) {
+        if self.history.len() == Self::HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(change);
+    }
+
+    // O(log n). Nothing changes if the count wouldn't fit in a `u32`.
+    pub(crate) fn add(&mut self, count: u32, item: &str) -> Result<(), NotesError> {
+        let stock = self.items.entry(item.to_string()).or_insert(0);
+        *stock = stock.checked_add(count).ok_or(NotesError::TooMany { what: "items" })?;
+        self.record(Change::Added(count, item.to_string()));
+        Ok(())
+    }
+
+    // Removes up to `count` and returns how many were removed. An item whose
+    // count reaches 0 leaves the map.
+    pub(crate) fn remove(&mut self, count: u32, item: &str) -> u32 {
+        let Some(stock) = self.items.get_mut(item) else {
+            return 0;
+        };
+
+        let removed = count.min(*stock);
+        *stock -= removed;
+        if *stock == 0 {
+            self.items.remove(item);
+        }
+        self.record(Change::Removed(removed, item.to_string()));
+        removed
+    }
+
+    pub(crate) fn count(&self, item: &str) -> u32 {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn items(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.items.iter().map(|(item, &count)| (item.as_str(), count))
+    }
+
+    // Items from `from` (included) to `to` (excluded), alphabetically, none
+    // if `to` comes before `from`.
+    // O(log n + m) for m results: a `HashMap` would have to look at every key.
+    pub(crate) fn range<'a>(&'a self, from: &'a str, to: &'a str) -> impl Iterator<Item = (&'a str, u32)> {
+        // `BTreeMap::range` panics on a backwards range, `from..from` is empty
+        let to = to.max(from);
+        // `from..to` would be a `Range<&str>`, which only works for sized types
+        self.items
+            .range::<str, _>((Bound::Included(from), Bound::Excluded(to)))
+            .map(|(item, &count)| (item.as_str(), count))
+    }
+
+    pub(crate) fn history(&self) -> impl Iterator<Item = &Change> {
+        self.history.iter()
+    }
+}
+
+impl fmt::Display for Inventory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (item, count) in self.items() {
+            writeln!(f, "{:>4} {}", count, item)?;
+        }
+        Ok(())
+    }
+}
+
+// The comments of a few of the notes' own files
+fn notes_comments() -> (Vec<&'static str>, Vec<&'static str>, Vec<&'static str>) {
+    let main = comments(include_str!("main.rs"));
+    let closures = comments(include_str!("closures.rs"));
+    let iterators = comments(include_str!("iterators.rs"));
+    (main, closures, iterators)
+}
+
+pub(crate) fn collections_demo() {
+    let (main, closures, iterators) = notes_comments();
+    let frequencies = word_frequencies(&main);
+    println!("{} comments in main.rs, {} different words", main.len(), frequencies.len());
+    for (word, count) in top_k(&frequencies, 10) {
+        println!("{:>5} {}", count, word);
+    }
+
+    let closures = word_frequencies(&closures);
+    let iterators = word_frequencies(&iterators);
+    let (shared, only_closures) = compare_vocabulary(&closures, &iterators);
+    println!("closures.rs and iterators.rs share {} words, e.g. {:?}", shared.len(), &shared[..shared.len().min(8)]);
+    let only_closures: HashSet<&str> = only_closures.into_iter().collect();
+    let most_used: Vec<_> = top_k(&closures, closures.len())
+        .into_iter()
+        .filter(|(word, _)| only_closures.contains(word))
+        .take(5)
+        .collect();
+    println!("most used words of closures.rs that iterators.rs never uses: {:?}", most_used);
+
+    match Inventory::from_lines("3 apples\n2 pears\n10 walnuts\n4 apples") {
+        Ok(mut inventory) => {
+            print!("{}", inventory);
+            println!("removed {} pears", inventory.remove(5, "pears"));
+            if let Err(e) = inventory.add(6, "bananas") {
+                println!("{}", errors::report(&e));
+            }
+            println!("from a to c: {:?}", inventory.range("a", "c").collect::<Vec<_>>());
+            let history: Vec<String> = inventory.history().map(Change::to_string).collect();
+            println!("last changes: {}", history.join(", "));
+        }
+        Err(e) => println!("{}", errors::report(&e)),
+    }
+
+    if let Err(e) = Inventory::from_lines("3 apples\nsome pears") {
+        println!("{}", errors::report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_are_extracted() {
+        let source = "let a = 1; // one\n/* two\n lines */ let b = 2;\n// three";
+        assert_eq!(comments(source), [" one", " two\n lines ", " three"]);
+        assert_eq!(comments("/* unterminated"), [" unterminated"]);
+        assert!(comments("let x = 5;").is_empty());
+    }
+
+    #[test]
+    fn words_are_counted() {
+        let frequencies = word_frequencies(&["The box, the Box and the BOX", "a box's lid: u8"]);
+        assert_eq!(frequencies.get("box"), Some(&4));
+        assert_eq!(frequencies.get("lid"), Some(&1));
+        // Stop words and short words are skipped
+        assert_eq!(frequencies.get("the"), None);
+        assert_eq!(frequencies.get("u8"), None);
+        assert_eq!(frequencies.len(), 2);
+    }
+
+    #[test]
+    fn top_k_is_sorted_and_deterministic() {
+        let frequencies: HashMap<String, usize> =
+            [("pear", 2), ("apple", 5), ("fig", 2), ("kiwi", 1), ("date", 2)].map(|(w, c)| (w.to_string(), c)).into();
+
+        assert_eq!(top_k(&frequencies, 3), [("apple", 5), ("date", 2), ("fig", 2)]);
+        assert_eq!(top_k(&frequencies, 10).len(), 5);
+        assert!(top_k(&frequencies, 0).is_empty());
+    }
+
+    #[test]
+    fn vocabularies_are_compared() {
+        let first = word_frequencies(&["closures capture variables"]);
+        let second = word_frequencies(&["iterators capture nothing"]);
+        let (shared, only_first) = compare_vocabulary(&first, &second);
+        assert_eq!(shared, ["capture"]);
+        assert_eq!(only_first, ["closures", "variables"]);
+    }
+
+    #[test]
+    fn inventory() {
+        let mut inventory = Inventory::from_lines("3 apples\n2 pears\n\n4 apples").unwrap();
+        assert_eq!(inventory.count("apples"), 7);
+        assert_eq!(inventory.items().collect::<Vec<_>>(), [("apples", 7), ("pears", 2)]);
+
+        assert_eq!(inventory.remove(5, "pears"), 2);
+        assert_eq!(inventory.count("pears"), 0);
+        assert_eq!(inventory.remove(1, "plums"), 0);
+
+        inventory.add(1, "bananas").unwrap();
+        inventory.add(1, "cherries").unwrap();
+        inventory.add(1, "cherries").unwrap();
+        assert_eq!(inventory.range("b", "c").collect::<Vec<_>>(), [("bananas", 1)]);
+        assert_eq!(inventory.range("a", "z").count(), 3);
+        // Empty and backwards ranges have no items, and don't panic
+        assert_eq!(inventory.range("bananas", "bananas").count(), 0);
+        assert_eq!(inventory.range("c", "b").count(), 0);
+        assert_eq!(inventory.range("z", "").count(), 0);
+
+        // Only the last 5 changes are kept
+        let history: Vec<String> = inventory.history().map(Change::to_string).collect();
+        assert_eq!(history, ["+4 apples", "-2 pears", "+1 bananas", "+1 cherries", "+1 cherries"]);
+    }
+
+    #[test]
+    fn counts_dont_overflow() {
+        let mut inventory = Inventory::new();
+        inventory.add(u32::MAX, "apples").unwrap();
+        assert_eq!(inventory.add(1, "apples"), Err(NotesError::TooMany { what: "items" }));
+        // The failed add changed nothing
+        assert_eq!(inventory.count("apples"), u32::MAX);
+        assert_eq!(inventory.history().count(), 1);
+
+        let error = Inventory::from_lines(&format!("{} pears\n1 pears", u32::MAX)).unwrap_err();
+        assert_eq!(errors::report(&error), "error: adding '1 pears'\ncaused by: too many items to count");
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        let error = Inventory::from_lines("3 apples\nx pears").unwrap_err();
+        assert_eq!(
+            errors::report(&error),
+            "error: invalid count 'x'\ncaused by: not a number\ncaused by: invalid digit found in string"
+        );
+    }
+}
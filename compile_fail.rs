@@ -0,0 +1,124 @@
+// !!!!!!!!!!!!!!!!!!!! Checking that code does NOT compile !!!!!!!!!!!!!!!!!!!!
+
+/*
+Some lessons are about code the compiler rejects: dangling references, missing match arms... Writing them as
+commented-out lines ("TODO ^ Try uncommenting this line") works, but nothing tells us if the comment is still
+true. A `CompileFailCase` is a small, complete program that must fail to compile with a given error code.
+`check` writes it to a temporary file and runs the local `rustc` on it.
+
+Run every case with:
+    ./main compile-fail
+*/
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub(crate) struct CompileFailCase {
+    pub(crate) name: &'static str,
+    // e.g. "E0597", see `rustc --explain E0597`
+    pub(crate) expected_error: &'static str,
+    pub(crate) source: &'static str,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Outcome {
+    // Failed with the expected error, as it should
+    FailedAsExpected,
+    // Compiled fine: the lesson is wrong
+    Compiled,
+    // Failed, but with other errors, e.g. a typo in the snippet
+    WrongErrors(Vec<String>),
+    // There's no `rustc` on this machine
+    Skipped,
+}
+
+// The error codes in rustc's output: "error[E0597]: ..." -> "E0597"
+fn error_codes(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("error["))
+        .filter_map(|rest| rest.split_once(']'))
+        .map(|(code, _)| code.to_string())
+        .collect()
+}
+
+// One directory per case, so several cases can be checked at the same time
+fn scratch_dir(name: &str) -> io::Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("rust-notes-compile-fail-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+impl CompileFailCase {
+    pub(crate) fn check(&self) -> io::Result<Outcome> {
+        let dir = scratch_dir(self.name)?;
+        let file = dir.join(format!("{}.rs", self.name));
+        fs::write(&file, self.source)?;
+
+        // `--emit=metadata` stops after type and borrow checking: no code is
+        // generated, so it's much faster than a real build
+        let output = Command::new(env::var("RUSTC").unwrap_or_else(|_| String::from("rustc")))
+            .args(["--edition", "2021", "--crate-type", "lib", "--emit=metadata", "--out-dir"])
+            .arg(&dir)
+            .arg(&file)
+            .output();
+
+        let _ = fs::remove_dir_all(&dir);
+
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Outcome::Skipped),
+            Err(e) => return Err(e),
+        };
+
+        if output.status.success() {
+            return Ok(Outcome::Compiled);
+        }
+
+        let codes = error_codes(&String::from_utf8_lossy(&output.stderr));
+        if codes.iter().any(|code| code == self.expected_error) {
+            Ok(Outcome::FailedAsExpected)
+        } else {
+            Ok(Outcome::WrongErrors(codes))
+        }
+    }
+}
+
+// Checks every case and returns how many of them are wrong
+pub(crate) fn check_all(cases: &[&CompileFailCase]) -> usize {
+    let mut wrong = 0;
+
+    for case in cases {
+        match case.check() {
+            Ok(Outcome::FailedAsExpected) => println!("ok      {} ({})", case.name, case.expected_error),
+            Ok(Outcome::Skipped) => println!("skipped {} (no rustc found)", case.name),
+            Ok(Outcome::Compiled) => {
+                wrong += 1;
+                println!("WRONG   {} compiled, expected {}", case.name, case.expected_error);
+            }
+            Ok(Outcome::WrongErrors(codes)) => {
+                wrong += 1;
+                println!("WRONG   {} failed with {:?}, expected {}", case.name, codes, case.expected_error);
+            }
+            Err(e) => {
+                wrong += 1;
+                println!("ERROR   {}: {}", case.name, e);
+            }
+        }
+    }
+
+    wrong
+}
+
+// `./main compile-fail`
+pub(crate) fn compile_fail_main(cases: &[&CompileFailCase]) {
+    let wrong = check_all(cases);
+
+    if wrong > 0 {
+        eprintln!("{} of {} compile-fail cases are wrong", wrong, cases.len());
+        std::process::exit(1);
+    }
+}
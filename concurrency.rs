@@ -0,0 +1,445 @@
+// !!!!!!!!!!!!!!!!!!!! Concurrency https://doc.rust-lang.org/rust-by-example/std_misc/threads.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+Rust's promise here is "fearless concurrency": data races don't compile. Two marker traits do the work:
+    - `Send`: a value can be moved to another thread
+    - `Sync`: a value can be shared between threads (`&T` is `Send`)
+`Rc` is neither, `Arc` is both; `RefCell` isn't `Sync`, `Mutex` is. The compiler checks them on every
+`thread::spawn`, so sharing something unsafely is a type error, not a bug found in production.
+
+What the compiler can't prevent are deadlocks (two threads waiting for each other forever) and logic races
+(results arriving in a different order every run). Both show up below, together with ways to deal with them.
+*/
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Barrier, Condvar, Mutex, MutexGuard, RwLock, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::iterators::fizzbuzz_word;
+
+// !!!!!!!!!!!!!!!!!!!! spawn and scope !!!!!!!!!!!!!!!!!!!!
+
+// `thread::spawn` needs a `'static` closure: the thread may outlive the
+// function, so it has to own what it uses (`move`). `join` waits for it and
+// gives back what the closure returned.
+pub(crate) fn spawn_and_join() -> Vec<u64> {
+    let handles: Vec<thread::JoinHandle<u64>> =
+        (1..=4u64).map(|n| thread::spawn(move || (1..=n * 10).sum())).collect();
+
+    handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+}
+
+// `thread::scope` guarantees every thread ends before `scope` returns, so the
+// threads may borrow local data, no `move` or `Arc` needed
+pub(crate) fn parallel_sum(values: &[u64], threads: usize) -> u64 {
+    let chunk_size = values.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> =
+            values.chunks(chunk_size).map(|chunk| scope.spawn(move || chunk.iter().sum::<u64>())).collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap()).sum()
+    })
+}
+
+// !!!!!!!!!!!!!!!!!!!! Channels !!!!!!!!!!!!!!!!!!!!
+
+/*
+`mpsc` is "multiple producers, single consumer": `Sender`s can be cloned and moved to other threads, the
+`Receiver` stays in one place. Receiving returns an error once every `Sender` is dropped, so a `for` loop over
+the receiver ends by itself when the producers are done.
+
+A pipeline: each stage is a thread reading from one channel and writing to the next. `sync_channel(n)` holds at
+most `n` messages, so a fast stage waits for a slow one instead of filling the memory.
+*/
+pub(crate) fn fizzbuzz_pipeline(end: u32) -> Vec<String> {
+    let (numbers_tx, numbers_rx) = mpsc::sync_channel::<u32>(16);
+    let (words_tx, words_rx) = mpsc::sync_channel::<String>(16);
+
+    let generate = thread::spawn(move || {
+        for n in 1..end {
+            numbers_tx.send(n).unwrap();
+        }
+        // `numbers_tx` is dropped here, which ends the next stage's loop
+    });
+
+    let convert = thread::spawn(move || {
+        for n in numbers_rx {
+            words_tx.send(fizzbuzz_word(n).to_string()).unwrap();
+        }
+    });
+
+    // The last stage is this thread
+    let words: Vec<String> = words_rx.into_iter().collect();
+
+    generate.join().unwrap();
+    convert.join().unwrap();
+    words
+}
+
+// !!!!!!!!!!!!!!!!!!!! Shared state !!!!!!!!!!!!!!!!!!!!
+
+// `Arc` shares ownership between threads, `Mutex` lets one of them at a time
+// change what's inside. The lock is released when the guard is dropped.
+pub(crate) fn mutex_counter(threads: usize, increments: usize) -> usize {
+    let counter = Arc::new(Mutex::new(0));
+
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..increments {
+                    *counter.lock().unwrap() += 1;
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let total = *counter.lock().unwrap();
+    total
+}
+
+// The same counter without a lock: `fetch_add` is a single indivisible
+// operation. `Relaxed` is enough, we only need the count, not an order
+// between this and other memory accesses.
+pub(crate) fn atomic_counter(threads: usize, increments: usize) -> usize {
+    let counter = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..increments {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    counter.into_inner()
+}
+
+// `RwLock`: any number of readers at once, or a single writer. Good for data
+// that's read a lot and rarely changed, like a dictionary of fizzbuzz words.
+pub(crate) fn rwlock_dictionary() -> Vec<String> {
+    let words = RwLock::new(vec![(3, "fizz"), (5, "buzz")]);
+
+    thread::scope(|scope| {
+        // Several readers can hold the lock together
+        let readers: Vec<_> = (0..3)
+            .map(|_| scope.spawn(|| words.read().unwrap().iter().map(|(_, word)| *word).collect::<String>()))
+            .collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), "fizzbuzz");
+        }
+
+        // The writer waits until no reader is left
+        scope.spawn(|| words.write().unwrap().push((7, "bazz"))).join().unwrap();
+    });
+
+    let words = words.into_inner().unwrap();
+    (100..=105)
+        .map(|n| {
+            let word: String = words.iter().filter(|(d, _)| n % d == 0).map(|(_, word)| *word).collect();
+            if word.is_empty() {
+                n.to_string()
+            } else {
+                word
+            }
+        })
+        .collect()
+}
+
+// !!!!!!!!!!!!!!!!!!!! Condvar !!!!!!!!!!!!!!!!!!!!
+
+/*
+A `Condvar` lets a thread sleep until another one says something changed, instead of checking in a loop. It's
+always used with a `Mutex` and a condition: `wait` releases the lock while sleeping and takes it back when woken
+up. Wake-ups can be spurious, so the condition is checked again every time (`wait_while` does the loop).
+*/
+
+// A queue of jobs shared by workers: `pop` sleeps while it's empty
+pub(crate) struct WorkQueue<T> {
+    state: Mutex<(VecDeque<T>, bool)>,
+    ready: Condvar,
+}
+
+impl<T> WorkQueue<T> {
+    pub(crate) fn new() -> Self {
+        WorkQueue { state: Mutex::new((VecDeque::new(), false)), ready: Condvar::new() }
+    }
+
+    pub(crate) fn push(&self, job: T) {
+        self.state.lock().unwrap().0.push_back(job);
+        self.ready.notify_one();
+    }
+
+    // No more jobs will come: wake everyone up, so they can stop
+    pub(crate) fn close(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.ready.notify_all();
+    }
+
+    // `None` once the queue is closed and empty
+    pub(crate) fn pop(&self) -> Option<T> {
+        let guard = self.state.lock().unwrap();
+        let mut state = self.ready.wait_while(guard, |(jobs, closed)| jobs.is_empty() && !*closed).unwrap();
+        state.0.pop_front()
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! An ordered parallel fizzbuzz !!!!!!!!!!!!!!!!!!!!
+
+/*
+Workers finish in any order, but the output must be the same as printing 1, 2, fizz... one by one. Each worker
+takes a chunk of the range from a `WorkQueue` and sends `(chunk index, text)` back on a channel. The collector
+keeps chunks that arrive early in a `BTreeMap` and writes one only when all the previous ones are written.
+*/
+pub(crate) fn parallel_fizzbuzz(end: u32, workers: usize, chunk_size: u32) -> String {
+    let queue = WorkQueue::new();
+    let (results_tx, results_rx) = mpsc::channel::<(usize, String)>();
+
+    // Like the number of workers, a chunk has at least one number
+    let chunk_size = chunk_size.max(1);
+    let chunks: Vec<(u32, u32)> =
+        (1..end).step_by(chunk_size as usize).map(|start| (start, start.saturating_add(chunk_size).min(end))).collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        queue.push((index, *chunk));
+    }
+    queue.close();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            let results_tx = results_tx.clone();
+            let queue = &queue;
+            scope.spawn(move || {
+                while let Some((index, (start, end))) = queue.pop() {
+                    let mut text = String::new();
+                    for n in start..end {
+                        writeln!(text, "{}", fizzbuzz_word(n)).unwrap();
+                    }
+                    results_tx.send((index, text)).unwrap();
+                }
+            });
+        }
+        // Only the workers' clones are left: the loop below ends when they do
+        drop(results_tx);
+
+        let mut output = String::new();
+        let mut waiting = BTreeMap::new();
+        let mut next = 0;
+
+        for (index, text) in results_rx {
+            waiting.insert(index, text);
+            while let Some(text) = waiting.remove(&next) {
+                output.push_str(&text);
+                next += 1;
+            }
+        }
+
+        assert!(waiting.is_empty());
+        output
+    })
+}
+
+// !!!!!!!!!!!!!!!!!!!! Deadlocks !!!!!!!!!!!!!!!!!!!!
+
+/*
+Thread 1 locks `a` then `b`, thread 2 locks `b` then `a`. If each gets its first lock before the other's second,
+both wait forever. Nothing crashes, the program just stops moving. `Mutex::lock` has no timeout, so
+`lock_with_timeout` polls `try_lock` instead: after `timeout` without getting the lock, we report a possible
+deadlock and back off, releasing what we hold, instead of hanging.
+
+The fix is a rule, not a timeout: every thread takes the locks in the same order.
+*/
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct LockTimeout {
+    pub(crate) waited: Duration,
+}
+
+pub(crate) fn lock_with_timeout<T>(mutex: &Mutex<T>, timeout: Duration) -> Result<MutexGuard<'_, T>, LockTimeout> {
+    let start = Instant::now();
+
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Ok(guard),
+            Err(TryLockError::Poisoned(e)) => return Ok(e.into_inner()),
+            Err(TryLockError::WouldBlock) => {
+                if start.elapsed() >= timeout {
+                    return Err(LockTimeout { waited: start.elapsed() });
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+// Runs two threads that lock `a` and `b`. With `same_order == false` they
+// lock them in opposite orders, and a `Barrier` makes sure both hold their
+// first lock before trying the second: a guaranteed deadlock. Returns how many
+// threads detected it.
+pub(crate) fn deadlock_scenario(same_order: bool, timeout: Duration) -> usize {
+    let a = Mutex::new(0);
+    let b = Mutex::new(0);
+    let both_hold_one = Barrier::new(2);
+    let detected = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for thread_index in 0..2 {
+            let (first, second) = if same_order || thread_index == 0 { (&a, &b) } else { (&b, &a) };
+            let (both_hold_one, detected) = (&both_hold_one, &detected);
+
+            scope.spawn(move || {
+                let mut first_guard = first.lock().unwrap();
+                if !same_order {
+                    both_hold_one.wait();
+                }
+
+                match lock_with_timeout(second, timeout) {
+                    Ok(mut second_guard) => {
+                        *first_guard += 1;
+                        *second_guard += 1;
+                    }
+                    Err(LockTimeout { waited }) => {
+                        detected.fetch_add(1, Ordering::Relaxed);
+                        println!("thread {}: possible deadlock, no lock after {:?}, backing off", thread_index, waited);
+                        // Returning drops `first_guard`, which lets the other thread go on
+                    }
+                }
+            });
+        }
+    });
+
+    detected.into_inner()
+}
+
+pub(crate) fn concurrency_demo() {
+    println!("sums computed by 4 threads: {:?}", spawn_and_join());
+
+    let values: Vec<u64> = (1..=1000).collect();
+    println!("1 + ... + 1000 on 4 scoped threads: {}", parallel_sum(&values, 4));
+
+    let words = fizzbuzz_pipeline(16);
+    println!("pipeline: {}", words.join(" "));
+
+    println!("mutex counter: {}", mutex_counter(8, 1000));
+    println!("atomic counter: {}", atomic_counter(8, 1000));
+    println!("with bazz, behind a RwLock: {}", rwlock_dictionary().join(" "));
+
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let start = Instant::now();
+    let parallel = parallel_fizzbuzz(1_000_000, workers, 10_000);
+    let parallel_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut sequential = String::new();
+    for n in 1..1_000_000 {
+        writeln!(sequential, "{}", fizzbuzz_word(n)).unwrap();
+    }
+    let sequential_time = start.elapsed();
+
+    assert_eq!(parallel, sequential);
+    println!(
+        "fizzbuzz up to a million: {} lines in order on {} workers in {:?} ({:?} on one thread)",
+        parallel.lines().count(),
+        workers,
+        parallel_time,
+        sequential_time
+    );
+
+    let detected = deadlock_scenario(false, Duration::from_millis(100));
+    println!("opposite lock order: {} threads detected a deadlock", detected);
+    assert!(detected > 0);
+    assert_eq!(deadlock_scenario(true, Duration::from_millis(100)), 0);
+    println!("same lock order: no deadlock");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawned_threads_return_values() {
+        assert_eq!(spawn_and_join(), [55, 210, 465, 820]);
+    }
+
+    #[test]
+    fn scoped_sum_matches() {
+        let values: Vec<u64> = (1..=101).collect();
+        for threads in [1, 2, 3, 7, 200] {
+            assert_eq!(parallel_sum(&values, threads), 5151);
+        }
+        assert_eq!(parallel_sum(&[], 4), 0);
+    }
+
+    #[test]
+    fn pipeline_keeps_the_order() {
+        assert_eq!(fizzbuzz_pipeline(6), ["1", "2", "fizz", "4", "buzz"]);
+    }
+
+    #[test]
+    fn counters_dont_lose_increments() {
+        assert_eq!(mutex_counter(4, 500), 2000);
+        assert_eq!(atomic_counter(4, 500), 2000);
+    }
+
+    #[test]
+    fn work_queue_hands_out_every_job_once() {
+        let queue = WorkQueue::new();
+        let done = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| {
+                    while let Some(job) = queue.pop() {
+                        done.fetch_add(job, Ordering::Relaxed);
+                    }
+                });
+            }
+            for job in 1..=100 {
+                queue.push(job);
+            }
+            queue.close();
+        });
+
+        assert_eq!(done.into_inner(), 5050);
+    }
+
+    #[test]
+    fn parallel_fizzbuzz_is_in_order() {
+        let mut expected = String::new();
+        for n in 1..1000 {
+            writeln!(expected, "{}", fizzbuzz_word(n)).unwrap();
+        }
+
+        for (workers, chunk_size) in [(1, 1000), (4, 7), (8, 1), (3, 0), (0, 50)] {
+            assert_eq!(parallel_fizzbuzz(1000, workers, chunk_size), expected);
+        }
+        assert_eq!(parallel_fizzbuzz(1, 4, 10), "");
+    }
+
+    #[test]
+    fn deadlocks_are_detected() {
+        assert!(deadlock_scenario(false, Duration::from_millis(50)) > 0);
+        assert_eq!(deadlock_scenario(true, Duration::from_millis(50)), 0);
+    }
+
+    #[test]
+    fn lock_with_timeout_gives_up() {
+        let mutex = Mutex::new(1);
+        let _held = mutex.lock().unwrap();
+
+        thread::scope(|scope| {
+            let result = scope.spawn(|| lock_with_timeout(&mutex, Duration::from_millis(20)).map(|_| ())).join();
+            assert!(matches!(result.unwrap(), Err(LockTimeout { waited }) if waited >= Duration::from_millis(20)));
+        });
+    }
+}
@@ -0,0 +1,311 @@
+// !!!!!!!!!!!!!!!!!!!! Drop, and tracing ownership !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/rust-by-example/trait/drop.html
+
+/*
+The `Drop` trait only has one method, `drop`, which is called automatically when an object goes out of scope.
+`Box`, `Vec` and `String` implement it to free their heap memory, but any type can implement it.
+
+`Traced<T>` wraps a value and writes down what happens to it: where it's created, which function it's moved
+into, who borrows it and when it's dropped. Running a lesson inside `trace` prints all of it in order, so the
+ownership and borrowing comments of main.rs become output we can read.
+
+Moving is just copying bytes, Rust runs no code for it, so moves have to be marked by hand with
+`moved_into`. Drops don't: the compiler inserts the call to `drop` for us.
+
+The functions being traced are the ones of main.rs: `destroy_box` and `eat_box_i32` take any `Box<T>`, so a
+`Box<Traced<i32>>` goes through the same code as the `Box<i32>` of the notes.
+*/
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::panic::Location;
+
+use crate::{borrow_i32, destroy_box, eat_box_i32};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TraceEvent {
+    Created,
+    MovedInto(&'static str),
+    BorrowedBy(&'static str),
+    Dropped,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TraceEntry {
+    pub(crate) name: &'static str,
+    pub(crate) event: TraceEvent,
+    pub(crate) value: String,
+    // `None` for drops: `drop` is called by the compiler, not by a line of ours
+    pub(crate) location: Option<&'static Location<'static>>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let event = match self.event {
+            TraceEvent::Created => String::from("created"),
+            TraceEvent::MovedInto(function) => format!("moved into `{}`", function),
+            TraceEvent::BorrowedBy(function) => format!("borrowed by `{}`", function),
+            TraceEvent::Dropped => String::from("dropped"),
+        };
+
+        match self.location {
+            Some(location) => {
+                write!(f, "{:<16} {:<28} {:<8} {}:{}", self.name, event, self.value, location.file(), location.line())
+            }
+            None => write!(f, "{:<16} {:<28} {}", self.name, event, self.value),
+        }
+    }
+}
+
+// The timeline of the lesson that is being traced, if any
+thread_local! {
+    static TIMELINE: RefCell<Option<Vec<TraceEntry>>> = const { RefCell::new(None) };
+}
+
+fn log(entry: TraceEntry) {
+    TIMELINE.with(|timeline| {
+        if let Some(entries) = timeline.borrow_mut().as_mut() {
+            entries.push(entry);
+        }
+    });
+}
+
+pub(crate) struct Traced<T: fmt::Debug> {
+    name: &'static str,
+    value: T,
+}
+
+impl<T: fmt::Debug> Traced<T> {
+    // `#[track_caller]` makes `Location::caller()` the line that called us,
+    // the same trick `unwrap` uses to report where it panicked
+    #[track_caller]
+    pub(crate) fn new(name: &'static str, value: T) -> Self {
+        let traced = Traced { name, value };
+        traced.log(TraceEvent::Created, Some(Location::caller()));
+        traced
+    }
+
+    // Takes the box by value and gives it back, just to write the move down:
+    // `destroy_box(a.moved_into("destroy_box"))`
+    #[track_caller]
+    pub(crate) fn moved_into(self: Box<Self>, function: &'static str) -> Box<Self> {
+        self.log(TraceEvent::MovedInto(function), Some(Location::caller()));
+        self
+    }
+
+    #[track_caller]
+    pub(crate) fn borrowed_by(&self, function: &'static str) -> &T {
+        self.log(TraceEvent::BorrowedBy(function), Some(Location::caller()));
+        &self.value
+    }
+
+    fn log(&self, event: TraceEvent, location: Option<&'static Location<'static>>) {
+        log(TraceEntry { name: self.name, event, value: format!("{:?}", self.value), location });
+    }
+}
+
+// Printing is reading too, and shows the value alone
+impl<T: fmt::Debug + fmt::Display> fmt::Display for Traced<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+// Reading through a `Traced` doesn't need to be logged, so it derefs to `T`
+impl<T: fmt::Debug> Deref for Traced<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> Drop for Traced<T> {
+    fn drop(&mut self) {
+        self.log(TraceEvent::Dropped, None);
+        // After this, the fields are dropped too, then the `Box` around us, if
+        // any, frees its memory
+    }
+}
+
+// Runs a lesson and returns everything that was logged while it ran
+pub(crate) fn trace(f: impl FnOnce()) -> Vec<TraceEntry> {
+    let outer = TIMELINE.with(|timeline| timeline.replace(Some(Vec::new())));
+    f();
+    TIMELINE.with(|timeline| timeline.replace(outer)).unwrap_or_default()
+}
+
+pub(crate) fn print_timeline(lesson: &str, entries: &[TraceEntry]) {
+    println!("Timeline of \"{}\":", lesson);
+    for (i, entry) in entries.iter().enumerate() {
+        println!("{:>3}. {}", i + 1, entry);
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! The lessons of main.rs, traced !!!!!!!!!!!!!!!!!!!!
+
+fn ownership_lesson() {
+    let a = Box::new(Traced::new("a", 5i32));
+
+    // *Move* `a` into `b`: nothing is logged, moving doesn't run any code
+    let b = a;
+
+    destroy_box(b.moved_into("destroy_box"));
+    // `b` was dropped at the end of `destroy_box`, before this line
+}
+
+fn borrowing_lesson() {
+    let boxed_i32 = Box::new(Traced::new("boxed_i32", 5_i32));
+    let stacked_i32 = Traced::new("stacked_i32", 6_i32);
+
+    borrow_i32(boxed_i32.borrowed_by("borrow_i32"));
+    borrow_i32(stacked_i32.borrowed_by("borrow_i32"));
+
+    eat_box_i32(boxed_i32.moved_into("eat_box_i32"));
+
+    // `stacked_i32` lives until the end of the function
+}
+
+fn drop_order_lesson() {
+    let _first = Traced::new("_first", "declared first");
+    let _second = Traced::new("_second", "declared second");
+
+    {
+        let _inner = Traced::new("_inner", "inner scope");
+        // `_inner` is dropped here, at the end of its scope
+    }
+
+    let early = Traced::new("early", "dropped early");
+    // `drop` is just a function taking its argument by value: `fn drop<T>(_x: T) {}`
+    drop(early);
+
+    // Variables are dropped in the reverse order they were declared:
+    // `_second` first, then `_first`
+}
+
+// The ownership rules, checked on a timeline: every traced value is dropped
+// exactly once, and nothing happens to it afterwards
+fn assert_dropped_once(timeline: &[TraceEntry]) {
+    for entry in timeline {
+        let events: Vec<&TraceEvent> = timeline.iter().filter(|e| e.name == entry.name).map(|e| &e.event).collect();
+
+        assert_eq!(events.iter().filter(|event| ***event == TraceEvent::Dropped).count(), 1);
+        assert_eq!(events.last(), Some(&&TraceEvent::Dropped));
+    }
+}
+
+pub(crate) fn drop_trace_demo() {
+    let lessons: [(&str, fn()); 3] = [
+        ("ownership", ownership_lesson),
+        ("borrowing", borrowing_lesson),
+        ("drop order", drop_order_lesson),
+    ];
+
+    for (name, lesson) in lessons {
+        let timeline = trace(lesson);
+        print_timeline(name, &timeline);
+        assert_dropped_once(&timeline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(timeline: &[TraceEntry]) -> Vec<(&'static str, TraceEvent)> {
+        timeline.iter().map(|entry| (entry.name, entry.event.clone())).collect()
+    }
+
+    #[test]
+    fn main_rs_functions() {
+        // `destroy_box` and `eat_box_i32` own their argument: it's dropped inside
+        let timeline = trace(|| destroy_box(Box::new(Traced::new("c", 1)).moved_into("destroy_box")));
+        assert_eq!(
+            events(&timeline),
+            [("c", TraceEvent::Created), ("c", TraceEvent::MovedInto("destroy_box")), ("c", TraceEvent::Dropped)]
+        );
+
+        let timeline = trace(|| {
+            let boxed = Box::new(Traced::new("boxed", 2));
+            eat_box_i32(boxed);
+            log(TraceEntry { name: "after", event: TraceEvent::Created, value: String::new(), location: None });
+        });
+        assert_eq!(
+            events(&timeline),
+            [("boxed", TraceEvent::Created), ("boxed", TraceEvent::Dropped), ("after", TraceEvent::Created)]
+        );
+
+        // `borrow_i32` only borrows: the value is dropped by its owner, later
+        let timeline = trace(|| {
+            let stacked = Traced::new("stacked", 3);
+            borrow_i32(stacked.borrowed_by("borrow_i32"));
+            log(TraceEntry { name: "after", event: TraceEvent::Created, value: String::new(), location: None });
+        });
+        assert_eq!(
+            events(&timeline),
+            [
+                ("stacked", TraceEvent::Created),
+                ("stacked", TraceEvent::BorrowedBy("borrow_i32")),
+                ("after", TraceEvent::Created),
+                ("stacked", TraceEvent::Dropped),
+            ]
+        );
+    }
+
+    #[test]
+    fn lessons() {
+        assert_eq!(
+            events(&trace(ownership_lesson)),
+            [("a", TraceEvent::Created), ("a", TraceEvent::MovedInto("destroy_box")), ("a", TraceEvent::Dropped)]
+        );
+        assert_eq!(
+            events(&trace(borrowing_lesson)),
+            [
+                ("boxed_i32", TraceEvent::Created),
+                ("stacked_i32", TraceEvent::Created),
+                ("boxed_i32", TraceEvent::BorrowedBy("borrow_i32")),
+                ("stacked_i32", TraceEvent::BorrowedBy("borrow_i32")),
+                ("boxed_i32", TraceEvent::MovedInto("eat_box_i32")),
+                ("boxed_i32", TraceEvent::Dropped),
+                ("stacked_i32", TraceEvent::Dropped),
+            ]
+        );
+        assert_eq!(
+            events(&trace(drop_order_lesson)),
+            [
+                ("_first", TraceEvent::Created),
+                ("_second", TraceEvent::Created),
+                ("_inner", TraceEvent::Created),
+                ("_inner", TraceEvent::Dropped),
+                ("early", TraceEvent::Created),
+                ("early", TraceEvent::Dropped),
+                ("_second", TraceEvent::Dropped),
+                ("_first", TraceEvent::Dropped),
+            ]
+        );
+    }
+
+    #[test]
+    fn entries() {
+        let timeline = trace(|| {
+            let _x = Traced::new("x", 5);
+        });
+        // The location is the line that called `new`, not a line of `Traced`
+        let created = &timeline[0];
+        assert_eq!(created.location.map(|location| location.file()), Some(file!()));
+        assert!(created.to_string().starts_with("x                created                      5 "));
+        assert_eq!(timeline[1].to_string().trim_end(), "x                dropped                      5");
+
+        // Nothing is logged outside of `trace`
+        drop(Traced::new("untraced", 0));
+        assert!(trace(|| {}).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn values_must_be_dropped() {
+        let timeline = trace(|| std::mem::forget(Traced::new("forgotten", 1)));
+        assert_dropped_once(&timeline);
+    }
+}
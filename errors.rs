@@ -0,0 +1,287 @@
+// !!!!!!!!!!!!!!!!!!!! Error handling https://doc.rust-lang.org/rust-by-example/error.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`unwrap()` says "this can't fail, and if it does, crash". That's fine in a quick example, but a function that can
+fail should say so in its type and let the caller decide: it returns a `Result<T, E>`.
+
+    - A custom error enum lists everything that can go wrong, and implements `Display` (for people) and `Error`
+      (so it fits with every other error, and can point to its cause with `source`).
+    - `?` returns early with the error, converting it with `From` on the way: after
+      `impl From<ParseIntError> for NotesError`, `text.parse::<i32>()?` works in a function returning
+      `Result<_, NotesError>`.
+    - `Box<dyn Error>` is "any error". Handy when a function mixes several error types and the caller will only
+      print them.
+    - Context: "invalid digit found in string" alone doesn't help much. Wrapping the error in one that says what
+      we were doing, and keeping the original as its `source`, gives a chain of causes we can print in full.
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+
+// !!!!!!!!!!!!!!!!!!!! A custom error type !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NotesError {
+    // A number didn't parse, the `ParseIntError` says why
+    ParseInt(ParseIntError),
+    // The input had fewer words than needed
+    MissingWord { expected: &'static str, input: String },
+    InvalidName { name: String, reason: &'static str },
+    // A sum went past the largest number its type can hold
+    TooMany { what: &'static str },
+}
+
+impl fmt::Display for NotesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // The cause isn't repeated here, `source` gives it
+            NotesError::ParseInt(_) => write!(f, "not a number"),
+            NotesError::MissingWord { expected, input } => write!(f, "missing the {} in '{}'", expected, input),
+            NotesError::InvalidName { name, reason } => write!(f, "invalid name '{}': {}", name, reason),
+            NotesError::TooMany { what } => write!(f, "too many {} to count", what),
+        }
+    }
+}
+
+impl Error for NotesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NotesError::ParseInt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// What lets `?` turn a `ParseIntError` into a `NotesError`
+impl From<ParseIntError> for NotesError {
+    fn from(e: ParseIntError) -> Self {
+        NotesError::ParseInt(e)
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Context !!!!!!!!!!!!!!!!!!!!
+
+// An error that says what we were doing when `source` happened
+#[derive(Debug)]
+pub(crate) struct ContextError {
+    context: String,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.context)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+// Adds `.context(...)` to every `Result` whose error is an `Error`, the same
+// idea as the `anyhow` crate
+pub(crate) trait Context<T> {
+    fn context(self, context: &str) -> Result<T, ContextError>;
+
+    // The closure only runs if there's an error, so building the message
+    // costs nothing on success
+    fn with_context(self, context: impl FnOnce() -> String) -> Result<T, ContextError>;
+}
+
+impl<T, E: Error + Send + Sync + 'static> Context<T> for Result<T, E> {
+    fn context(self, context: &str) -> Result<T, ContextError> {
+        self.with_context(|| context.to_string())
+    }
+
+    fn with_context(self, context: impl FnOnce() -> String) -> Result<T, ContextError> {
+        self.map_err(|e| ContextError { context: context(), source: Box::new(e) })
+    }
+}
+
+// The error, followed by every cause, one per line:
+//     error: reading line 2 of the order
+//     caused by: invalid count 'x'
+//     caused by: invalid digit found in string
+pub(crate) fn report(error: &dyn Error) -> String {
+    let mut out = format!("error: {}", error);
+
+    let mut cause = error.source();
+    while let Some(e) = cause {
+        out.push_str(&format!("\ncaused by: {}", e));
+        cause = e.source();
+    }
+
+    out
+}
+
+// !!!!!!!!!!!!!!!!!!!! The examples of main.rs, without unwrap !!!!!!!!!!!!!!!!!!!!
+
+// "Parsing a string": `"5".parse().unwrap()` and `"10".parse::<i32>().unwrap()`.
+// `?` replaces each `unwrap()`, converting the `ParseIntError` with `From`.
+pub(crate) fn parse_sum(a: &str, b: &str) -> Result<i32, NotesError> {
+    let parsed: i32 = a.parse()?;
+    let turbo_parsed = b.parse::<i32>()?;
+
+    Ok(parsed + turbo_parsed)
+}
+
+// "Associated functions & Methods": `"Ekrem".parse().unwrap()` can't fail
+// (parsing a `String` just copies it), but an empty or numeric name should
+// not be accepted silently
+pub(crate) fn parse_name(text: &str) -> Result<String, NotesError> {
+    let name = text.trim();
+
+    if name.is_empty() {
+        return Err(NotesError::InvalidName { name: name.to_string(), reason: "it's empty" });
+    }
+    if !name.chars().all(|c| c.is_alphabetic() || c == ' ' || c == '-') {
+        return Err(NotesError::InvalidName { name: name.to_string(), reason: "only letters, spaces and '-' are allowed" });
+    }
+
+    Ok(name.to_string())
+}
+
+// "let else": instead of `panic!`, the `else` branch returns an error. It
+// still has to diverge, `return` does.
+pub(crate) fn split_count_item(s: &str) -> Result<(&str, &str), NotesError> {
+    let mut it = s.split(' ');
+    let (Some(count_str), Some(item)) = (it.next(), it.next()) else {
+        return Err(NotesError::MissingWord { expected: "item", input: s.to_string() });
+    };
+
+    Ok((count_str, item))
+}
+
+// "3 apples" -> (3, "apples"), with context explaining which part was wrong
+pub(crate) fn parse_count_item(s: &str) -> Result<(u32, &str), ContextError> {
+    let (count_str, item) = split_count_item(s).with_context(|| format!("reading '{}'", s))?;
+
+    let count = count_str
+        .parse::<u32>()
+        .map_err(NotesError::from)
+        .with_context(|| format!("invalid count '{}'", count_str))?;
+
+    Ok((count, item))
+}
+
+// Every line of an order, e.g. "3 apples\n2 pears". Errors of different types
+// meet here (`ContextError`, `NotesError`), `Box<dyn Error>` takes both.
+pub(crate) fn total_items(order: &str) -> Result<u32, Box<dyn Error>> {
+    let mut total: u32 = 0;
+
+    for (i, line) in order.lines().enumerate() {
+        let (count, _) = parse_count_item(line).with_context(|| format!("reading line {} of the order", i + 1))?;
+        // `+=` would panic in a debug build, and wrap around in a release one
+        total = total
+            .checked_add(count)
+            .ok_or(NotesError::TooMany { what: "items" })
+            .with_context(|| format!("adding line {} of the order", i + 1))?;
+    }
+
+    if total == 0 {
+        return Err(Box::new(NotesError::MissingWord { expected: "items", input: order.to_string() }));
+    }
+
+    Ok(total)
+}
+
+pub(crate) fn errors_demo() {
+    match parse_sum("5", "10") {
+        Ok(sum) => println!("Sum: {:?}", sum),
+        Err(e) => println!("{}", report(&e)),
+    }
+
+    for name in ["Ekrem", "", "R2-D2"] {
+        match parse_name(name) {
+            Ok(name) => println!("Hello, my name is {}", name),
+            Err(e) => println!("{}", report(&e)),
+        }
+    }
+
+    for s in ["a b c d", "alone"] {
+        match split_count_item(s) {
+            Ok((count_str, item)) => println!("{}, {}", count_str, item),
+            Err(e) => println!("{}", report(&e)),
+        }
+    }
+
+    for order in ["3 apples\n2 pears", "3 apples\nx pears", "3 apples\npears"] {
+        match total_items(order) {
+            Ok(total) => println!("{} items", total),
+            // `Box<dyn Error>` derefs to `dyn Error`
+            Err(e) => println!("{}", report(e.as_ref())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn question_mark_converts_parse_errors() {
+        assert_eq!(parse_sum("5", "10"), Ok(15));
+        assert!(matches!(parse_sum("5", "ten"), Err(NotesError::ParseInt(_))));
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(parse_name(" Ekrem "), Ok(String::from("Ekrem")));
+        assert_eq!(parse_name("Jean-Luc Picard"), Ok(String::from("Jean-Luc Picard")));
+        assert!(matches!(parse_name("  "), Err(NotesError::InvalidName { .. })));
+        assert!(matches!(parse_name("R2-D2"), Err(NotesError::InvalidName { .. })));
+    }
+
+    #[test]
+    fn let_else_returns_an_error() {
+        assert_eq!(split_count_item("3 apples"), Ok(("3", "apples")));
+        assert_eq!(
+            split_count_item("3"),
+            Err(NotesError::MissingWord { expected: "item", input: String::from("3") })
+        );
+    }
+
+    #[test]
+    fn the_whole_chain_is_reported() {
+        let error = total_items("3 apples\nx pears").unwrap_err();
+        assert_eq!(
+            report(error.as_ref()),
+            "error: reading line 2 of the order\n\
+             caused by: invalid count 'x'\n\
+             caused by: not a number\n\
+             caused by: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn errors_can_be_found_in_the_chain() -> Result<(), Box<dyn Error>> {
+        assert_eq!(total_items("3 apples\n2 pears")?, 5);
+
+        let error = total_items("pears").unwrap_err();
+        let mut cause: Option<&dyn Error> = Some(error.as_ref());
+        let mut found = None;
+        while let Some(e) = cause {
+            found = found.or(e.downcast_ref::<NotesError>());
+            cause = e.source();
+        }
+        assert!(matches!(found, Some(NotesError::MissingWord { expected: "item", .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn totals_dont_overflow() {
+        let max = format!("{} apples", u32::MAX);
+        assert_eq!(total_items(&max).unwrap(), u32::MAX);
+        assert_eq!(total_items(&format!("{}\n0 pears", max)).unwrap(), u32::MAX);
+
+        let error = total_items(&format!("{}\n1 pear\n2 plums", max)).unwrap_err();
+        assert_eq!(
+            report(error.as_ref()),
+            "error: adding line 2 of the order\n\
+             caused by: too many items to count"
+        );
+    }
+}
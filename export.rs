@@ -0,0 +1,390 @@
+// !!!!!!!!!!!!!!!!!!!! Exporting the notes https://rust-lang.github.io/mdBook/format/summary.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`./main serve` needs the notes running. `./main export` writes the same lessons to files, for any static file
+server:
+
+    ./main export html <dir>      index.html and one <lesson>.html page per lesson, the pages of serve.rs
+    ./main export mdbook <dir>    an mdBook: book.toml, src/SUMMARY.md, src/main.md and one src/<lesson>.md per lesson
+    ./main export json <file>     the sample data of the notes (people, shapes, events), see json.rs
+
+`mdbook build <dir>` turns the second one into a searchable site. A chapter has the lesson's prose, its code and
+what it printed, with every link kept as a Markdown reference:
+
+    The same pages as [https://doc.rust-lang.org/rust-by-example/fn/closures.html][1]...
+
+    [1]: https://doc.rust-lang.org/rust-by-example/fn/closures.html
+
+main.rs is no lesson, but the notes start there: hello world, primitives, structs, ownership, borrowing and
+traits, with a rust-by-example link for each part. The book opens with it as a chapter of its own, and its output
+is what `./main` prints with no arguments.
+
+The outputs come from running each chapter, like serve.rs does, all at once with `thread::scope`.
+*/
+
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::json;
+use crate::macros::LessonEntry;
+use crate::serve::{self, Links};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Html,
+    MdBook,
+}
+
+#[derive(Debug)]
+pub(crate) struct ExportError {
+    path: PathBuf,
+    source: std::io::Error,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't write {}: {}", self.path.display(), self.source)
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Markdown !!!!!!!!!!!!!!!!!!!!
+
+// A code fence longer than any run of backticks in `text`, so it can't be
+// closed early
+fn fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+// The numbered references of a chapter, in order of appearance
+struct References<'a>(Vec<&'a str>);
+
+impl<'a> References<'a> {
+    fn number(&mut self, link: &'a str) -> usize {
+        match self.0.iter().position(|known| *known == link) {
+            Some(i) => i + 1,
+            None => {
+                self.0.push(link);
+                self.0.len()
+            }
+        }
+    }
+
+    // Outside of `code`: links become references, and what Markdown would take
+    // for HTML or emphasis is escaped
+    fn inline(&mut self, text: &'a str) -> String {
+        let mut markdown = String::new();
+        for (i, part) in text.split('`').enumerate() {
+            if i % 2 == 1 {
+                let _ = write!(markdown, "`{}`", part);
+                continue;
+            }
+            let mut rest = part;
+            while let Some(start) = rest.find("https://") {
+                markdown.push_str(&escape_markdown(&rest[..start]));
+                let link = &rest[start..];
+                let end = link.find(|c: char| c.is_whitespace() || "()`\"<>".contains(c)).unwrap_or(link.len());
+                let link = link[..end].trim_end_matches(['.', ',', ':']);
+                let _ = write!(markdown, "[{}][{}]", link, self.number(link));
+                rest = &rest[start + link.len()..];
+            }
+            markdown.push_str(&escape_markdown(rest));
+        }
+        markdown
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '<' | '*' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub(crate) fn chapter_markdown(lesson: &LessonEntry, output: &str) -> String {
+    let (prose, code) = serve::split_prose(lesson.source);
+    let mut references = References(Vec::new());
+
+    let mut chapter = format!("# {}\n\n*{}*\n\n", escape_markdown(lesson.title), escape_markdown(lesson.body));
+
+    // The pages the lesson follows come first, the other links of the prose
+    // get the next numbers
+    let followed = serve::references(lesson.source);
+    for link in &followed {
+        let _ = writeln!(chapter, "- [{}][{}]", link, references.number(link));
+    }
+    if !followed.is_empty() {
+        chapter.push('\n');
+    }
+
+    // Indented paragraphs are code blocks in Markdown too: they stay as they are
+    for paragraph in prose.split("\n\n").map(|p| p.trim_matches('\n')).filter(|p| !p.is_empty()) {
+        if paragraph.lines().all(|line| line.starts_with("    ") || line.trim().is_empty()) {
+            let _ = write!(chapter, "{}\n\n", paragraph);
+        } else {
+            let lines: Vec<String> = paragraph.lines().map(|line| references.inline(line)).collect();
+            let _ = write!(chapter, "{}\n\n", lines.join("\n"));
+        }
+    }
+
+    let fence = fence(code);
+    let _ = write!(chapter, "## Code: {}.rs\n\n{}rust\n{}\n{}\n\n", lesson.id, fence, code.trim_end(), fence);
+    let fence = self::fence(output);
+    let _ = write!(chapter, "## Output\n\n{}text\n{}\n{}\n", fence, output.trim_end(), fence);
+
+    if !references.0.is_empty() {
+        chapter.push('\n');
+        for (i, link) in references.0.iter().enumerate() {
+            let _ = writeln!(chapter, "[{}]: {}", i + 1, link);
+        }
+    }
+    chapter
+}
+
+pub(crate) fn summary_markdown(lessons: &[LessonEntry]) -> String {
+    let mut summary = String::from("# Summary\n\n");
+    for lesson in lessons {
+        let _ = writeln!(summary, "- [{}]({}.md)", escape_markdown(lesson.title), lesson.id);
+    }
+    summary
+}
+
+// !!!!!!!!!!!!!!!!!!!! Files !!!!!!!!!!!!!!!!!!!!
+
+// There's nothing to run from here: the notes of main.rs are `./main` itself
+pub(crate) const MAIN_NOTES: LessonEntry = LessonEntry {
+    id: "main",
+    title: "The main notes",
+    body: "Hello world to traits, following rust-by-example in main.rs.",
+    run: || {},
+    source: include_str!("main.rs"),
+};
+
+// The chapters of the book: the notes of main.rs, then every lesson
+pub(crate) fn book_chapters(lessons: &[LessonEntry]) -> Vec<LessonEntry> {
+    std::iter::once(MAIN_NOTES).chain(lessons.iter().copied()).collect()
+}
+
+// What a chapter printed: `./main run <lesson>`, or `./main` alone for main.rs
+fn run_chapter(program: &Path, chapter: &LessonEntry) -> std::io::Result<String> {
+    if chapter.id == MAIN_NOTES.id {
+        serve::run_notes(program, &[])
+    } else {
+        serve::run_lesson(program, chapter.id)
+    }
+}
+
+// Every file of the export, relative to its directory. `outputs[i]` is what
+// `lessons[i]` printed.
+pub(crate) fn export_files(lessons: &[LessonEntry], outputs: &[String], format: Format) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    match format {
+        Format::Html => {
+            files.push((PathBuf::from("index.html"), serve::index_page(lessons, Links::Files)));
+            for (index, (lesson, output)) in lessons.iter().zip(outputs).enumerate() {
+                let page = serve::lesson_page(lessons, index, output, Links::Files);
+                files.push((PathBuf::from(format!("{}.html", lesson.id)), page));
+            }
+        }
+        Format::MdBook => {
+            let book = "[book]\ntitle = \"Rust notes\"\nsrc = \"src\"\n";
+            files.push((PathBuf::from("book.toml"), book.to_string()));
+            files.push((Path::new("src").join("SUMMARY.md"), summary_markdown(lessons)));
+            for (lesson, output) in lessons.iter().zip(outputs) {
+                let chapter = chapter_markdown(lesson, output);
+                files.push((Path::new("src").join(format!("{}.md", lesson.id)), chapter));
+            }
+        }
+    }
+    files
+}
+
+pub(crate) fn write_files(dir: &Path, files: &[(PathBuf, String)]) -> Result<(), ExportError> {
+    for (file, contents) in files {
+        let path = dir.join(file);
+        let parent = path.parent().unwrap_or(dir);
+        fs::create_dir_all(parent)
+            .and_then(|()| fs::write(&path, contents))
+            .map_err(|source| ExportError { path: path.clone(), source })?;
+    }
+    Ok(())
+}
+
+// The data needs no lesson to run, it's written right away
+fn export_json(file: &Path) -> Result<(), ExportError> {
+    let mut text = json::export_lesson_data().to_pretty();
+    text.push('\n');
+    fs::write(file, text).map_err(|source| ExportError { path: file.to_path_buf(), source })
+}
+
+// `./main export html|mdbook <dir>` and `./main export json <file>`
+pub(crate) fn export_main(lessons: &[LessonEntry], args: &[String]) {
+    let (format, dir) = match args {
+        [format, dir] if format == "html" => (Format::Html, Path::new(dir)),
+        [format, dir] if format == "mdbook" => (Format::MdBook, Path::new(dir)),
+        [format, file] if format == "json" => {
+            match export_json(Path::new(file)) {
+                Ok(()) => println!("wrote {}", file),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {
+            eprintln!("usage: ./main export html|mdbook <dir>, or ./main export json <file>");
+            std::process::exit(2);
+        }
+    };
+
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("error: can't find the notes' own binary: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // The HTML pages are the ones of `./main serve`, which has no page for main.rs
+    let chapters = match format {
+        Format::Html => lessons.to_vec(),
+        Format::MdBook => book_chapters(lessons),
+    };
+
+    // Every chapter at once: each is a process of its own
+    let outputs: Vec<String> = thread::scope(|scope| {
+        let runs: Vec<_> = chapters
+            .iter()
+            .map(|chapter| scope.spawn(|| run_chapter(&program, chapter)))
+            .collect();
+        runs.into_iter()
+            .zip(&chapters)
+            .map(|(run, chapter)| match run.join().unwrap() {
+                Ok(output) => output,
+                Err(e) => format!("(can't run {}: {})\n", chapter.id, e),
+            })
+            .collect()
+    });
+
+    let files = export_files(&chapters, &outputs, format);
+    match write_files(dir, &files) {
+        Ok(()) => println!("wrote {} files to {}", files.len(), dir.display()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_io::ScratchDir;
+
+    fn lesson(source: &'static str) -> LessonEntry {
+        LessonEntry { id: "closures", title: "Closures", body: "Fn, FnMut and FnOnce.", run: || {}, source }
+    }
+
+    #[test]
+    fn chapters() {
+        let source = "// !!! Closures https://doc.rust-lang.org/rust-by-example/fn/closures.html !!!\n\
+                      /*\nA *closure* <captures>, see `a<b>`\n\
+                      and https://doc.rust-lang.org/book/ch13-01-closures.html.\n\
+                      \n    let f = |x| x + 1;\n*/\n\nfn main() {}\n";
+        assert_eq!(
+            chapter_markdown(&lesson(source), "2\n"),
+            "# Closures\n\n*Fn, FnMut and FnOnce.*\n\n\
+             - [https://doc.rust-lang.org/rust-by-example/fn/closures.html][1]\n\
+             - [https://doc.rust-lang.org/book/ch13-01-closures.html][2]\n\n\
+             A \\*closure\\* \\<captures>, see `a<b>`\n\
+             and [https://doc.rust-lang.org/book/ch13-01-closures.html][2].\n\n\
+             \x20   let f = |x| x + 1;\n\n\
+             ## Code: closures.rs\n\n```rust\nfn main() {}\n```\n\n\
+             ## Output\n\n```text\n2\n```\n\n\
+             [1]: https://doc.rust-lang.org/rust-by-example/fn/closures.html\n\
+             [2]: https://doc.rust-lang.org/book/ch13-01-closures.html\n"
+        );
+
+        assert_eq!(fence("no backticks"), "```");
+        assert_eq!(fence("let s = \"````\";"), "`````");
+    }
+
+    #[test]
+    fn every_lesson_is_a_chapter() {
+        let outputs = vec![String::from("printed\n"); crate::LESSONS.len()];
+        let files = export_files(&crate::LESSONS, &outputs, Format::MdBook);
+        assert_eq!(files.len(), crate::LESSONS.len() + 2);
+
+        let (path, summary) = &files[1];
+        assert_eq!(path, &Path::new("src").join("SUMMARY.md"));
+        for lesson in crate::LESSONS {
+            assert!(summary.contains(&format!("]({}.md)", lesson.id)), "{} is missing", lesson.id);
+        }
+        assert!(summary.contains("- [macro_rules!](macros.md)"));
+
+        let html = export_files(&crate::LESSONS, &outputs, Format::Html);
+        assert_eq!(html[0].0, PathBuf::from("index.html"));
+        assert!(html[0].1.contains("<a href=\"closures.html\">Closures</a>"));
+    }
+
+    #[test]
+    fn the_book_starts_with_main_rs() {
+        let chapters = book_chapters(&crate::LESSONS);
+        assert_eq!(chapters.len(), crate::LESSONS.len() + 1);
+        let outputs = vec![String::from("printed\n"); chapters.len()];
+        let files = export_files(&chapters, &outputs, Format::MdBook);
+
+        assert!(files[1].1.starts_with("# Summary\n\n- [The main notes](main.md)\n"), "{}", files[1].1);
+        let (path, chapter) = &files[2];
+        assert_eq!(path, &Path::new("src").join("main.md"));
+        assert!(chapter.contains("## Code: main.rs\n"));
+
+        // Every rust-by-example page main.rs follows is a reference of the chapter
+        let links = serve::references(MAIN_NOTES.source);
+        let pages: Vec<&str> = links.into_iter().filter(|link| link.contains("/rust-by-example/")).collect();
+        assert!(pages.contains(&"https://doc.rust-lang.org/rust-by-example/scope/borrow.html"));
+        for page in pages {
+            let reference = format!("]: {}", page);
+            let found = chapter.lines().any(|line| line.starts_with('[') && line.ends_with(&reference));
+            assert!(found, "{} is missing", page);
+        }
+    }
+
+    #[test]
+    fn lesson_data_as_json() {
+        let dir = ScratchDir::new("export-json").unwrap();
+        let file = dir.path().join("data.json");
+        export_json(&file).unwrap();
+
+        let text = fs::read_to_string(&file).unwrap();
+        assert_eq!(json::parse(&text), Ok(json::export_lesson_data()));
+        assert!(export_json(&dir.path().join("missing").join("data.json")).is_err());
+    }
+
+    #[test]
+    fn files_are_written() {
+        let dir = ScratchDir::new("export").unwrap();
+        let files = [(Path::new("src").join("a.md"), String::from("# A\n"))];
+        write_files(dir.path(), &files).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("src").join("a.md")).unwrap(), "# A\n");
+
+        // A file where the directory should be
+        let blocked = [(Path::new("src").join("a.md").join("b.md"), String::new())];
+        let error = write_files(dir.path(), &blocked).unwrap_err();
+        assert!(error.to_string().starts_with("can't write "), "{}", error);
+        assert!(error.source().is_some());
+    }
+}
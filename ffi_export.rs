@@ -0,0 +1,48 @@
+// Rust functions that C can call, see "Exporting functions to C" in unsafe_rust.rs.
+//
+// This file is a module of the notes, and also a crate of its own: `./main ffi`
+// compiles it alone into a static library (`--crate-type staticlib`) and links
+// it with call_rust.c. So it can't use anything from the rest of the notes.
+
+use std::ffi::{c_char, CStr};
+
+// `#[no_mangle]` keeps the name as written, instead of the mangled name Rust
+// normally gives functions (something like `_ZN10ffi_export9notes_sum17h...E`),
+// so that C can find it. `extern "C"` makes it use C's calling convention.
+// Only plain C types can cross: integers, floats, pointers.
+//
+// Both are `unsafe fn`: they trust pointers they can't check, so a caller on
+// the Rust side needs an `unsafe` block too, and has to keep the promise below.
+
+/// Adds up `len` numbers.
+///
+/// # Safety
+///
+/// `numbers` points to `len` valid, initialised `i32`s, or `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn notes_sum(numbers: *const i32, len: usize) -> i64 {
+    if len == 0 {
+        return 0;
+    }
+
+    // SAFETY: the caller promised `numbers` points to `len` `i32`s
+    let numbers = unsafe { std::slice::from_raw_parts(numbers, len) };
+    numbers.iter().map(|&n| i64::from(n)).sum()
+}
+
+/// Returns how many bytes of `word` are ASCII letters, or -1 for a null pointer.
+///
+/// # Safety
+///
+/// `word` is null, or points to a NUL-terminated string that stays valid
+/// during the call.
+#[no_mangle]
+pub unsafe extern "C" fn notes_count_letters(word: *const c_char) -> i64 {
+    if word.is_null() {
+        return -1;
+    }
+
+    // SAFETY: not null, and the caller promised it's NUL-terminated
+    let word = unsafe { CStr::from_ptr(word) };
+    word.to_bytes().iter().filter(|b| b.is_ascii_alphabetic()).count() as i64
+}
@@ -0,0 +1,496 @@
+// !!!!!!!!!!!!!!!!!!!! File I/O https://doc.rust-lang.org/rust-by-example/std_misc/file.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`std::fs` has one-call helpers (`fs::read_to_string`, `fs::write`) and `File`, for more control.
+
+    - `File` talks to the operating system on every `read` and `write`. `BufReader` and `BufWriter` keep a buffer
+      in memory (8 KiB by default) and only go to the OS when it's empty or full. Reading line by line, or writing
+      many small pieces, gets a lot faster.
+    - A `BufWriter` flushes its buffer when it's dropped, but a `drop` can't return an error: call `flush()` to
+      see if the last write failed.
+    - `Path` (borrowed) and `PathBuf` (owned) are to file names what `&str` and `String` are to text, without
+      assuming the name is valid UTF-8: `display()` prints them.
+    - Every function can fail: the file is missing, we're not allowed to read it, the disk is full...
+      `io::Error::kind()` tells which, so the message can say what to do about it.
+
+Writing a file in place isn't safe: if the program dies halfway, the file is left half written, and the old
+content is lost. Writing to a temporary file next to it and renaming it over the old one is: on the same file
+system, `rename` replaces the file in one step. Readers see the old file or the new one, never a mix.
+
+Applied to the learner's own annotations, kept per lesson in a text file:
+
+    ./main note closures "FnOnce can only be called once"
+    ./main note closures
+    ./main note
+*/
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::macros::{self, LessonEntry};
+
+// !!!!!!!!!!!!!!!!!!!! Errors !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug)]
+pub(crate) enum AnnotationsError {
+    // `action` is what we were doing, e.g. "read"
+    Io { path: PathBuf, action: &'static str, source: io::Error },
+    InvalidLine { path: PathBuf, line: usize, message: String },
+}
+
+impl fmt::Display for AnnotationsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io { path, action, source } => {
+                write!(f, "can't {} {}", action, path.display())?;
+                // The two errors a learner can do something about
+                match source.kind() {
+                    io::ErrorKind::NotFound => write!(f, " (it doesn't exist, add a note to create it)"),
+                    io::ErrorKind::PermissionDenied => write!(f, " (permission denied, check who owns it)"),
+                    _ => Ok(()),
+                }
+            }
+            Self::InvalidLine { path, line, message } => write!(f, "{}: line {}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl Error for AnnotationsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidLine { .. } => None,
+        }
+    }
+}
+
+// `map_err` helper: `.map_err(io_error(path, "read"))`
+fn io_error<'a>(path: &'a Path, action: &'static str) -> impl FnOnce(io::Error) -> AnnotationsError + 'a {
+    move |source| AnnotationsError::Io { path: path.to_path_buf(), action, source }
+}
+
+// !!!!!!!!!!!!!!!!!!!! The file format !!!!!!!!!!!!!!!!!!!!
+
+/*
+One annotation per line, the lesson id and the text separated by a tab:
+
+    # comments and empty lines are skipped
+    closures	FnOnce can only be called once
+    closures	move copies or moves the captured variables
+
+Tabs, newlines and backslashes in the text are written `\t`, `\n` and `\\`, so an annotation is always one line.
+*/
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err(String::from("'\\' at the end of the line")),
+        }
+    }
+    Ok(unescaped)
+}
+
+// !!!!!!!!!!!!!!!!!!!! Annotations !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Annotations {
+    // Lesson id -> its annotations, oldest first
+    by_lesson: BTreeMap<String, Vec<String>>,
+}
+
+impl Annotations {
+    pub(crate) fn add(&mut self, lesson: &str, text: &str) {
+        self.by_lesson.entry(lesson.to_string()).or_default().push(text.to_string());
+    }
+
+    pub(crate) fn for_lesson(&self, lesson: &str) -> &[String] {
+        self.by_lesson.get(lesson).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn lessons(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.by_lesson.iter().map(|(lesson, texts)| (lesson.as_str(), texts.as_slice()))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.by_lesson.values().map(Vec::len).sum()
+    }
+
+    // Any `BufRead`: a file, or a `&[u8]` in the tests. `path` is only used in
+    // error messages.
+    pub(crate) fn read_from(reader: impl BufRead, path: &Path) -> Result<Self, AnnotationsError> {
+        let mut annotations = Annotations::default();
+
+        // `lines()` reads one line at a time into a new `String`, without the
+        // `\n`. Each line can fail: the file may not be UTF-8.
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(io_error(path, "read"))?;
+            let invalid =
+                |message: String| AnnotationsError::InvalidLine { path: path.to_path_buf(), line: index + 1, message };
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((lesson, text)) = line.split_once('\t') else {
+                return Err(invalid(String::from("expected a lesson id, a tab and the annotation")));
+            };
+            annotations.add(lesson, &unescape(text).map_err(invalid)?);
+        }
+
+        Ok(annotations)
+    }
+
+    pub(crate) fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "# Annotations of the Rust notes: <lesson>\\t<text>, one per line")?;
+        for (lesson, texts) in self.lessons() {
+            for text in texts {
+                writeln!(writer, "{}\t{}", lesson, escape(text))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Loading and saving !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) fn load(path: &Path) -> Result<Annotations, AnnotationsError> {
+    let file = File::open(path).map_err(io_error(path, "read"))?;
+    Annotations::read_from(BufReader::new(file), path)
+}
+
+// A missing file is only the first run: no annotations yet
+pub(crate) fn load_or_default(path: &Path) -> Result<Annotations, AnnotationsError> {
+    default_if_missing(load(path))
+}
+
+// Any other error, like a file we may not read, is still an error
+fn default_if_missing(loaded: Result<Annotations, AnnotationsError>) -> Result<Annotations, AnnotationsError> {
+    match loaded {
+        Err(AnnotationsError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+            Ok(Annotations::default())
+        }
+        result => result,
+    }
+}
+
+// "notes.txt" -> ".notes.txt.tmp", in the same directory: `rename` can only
+// replace a file in one step within a file system
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map_or_else(|| "annotations".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{}.tmp", name))
+}
+
+pub(crate) fn save(path: &Path, annotations: &Annotations) -> Result<(), AnnotationsError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(io_error(dir, "create"))?;
+    }
+
+    let temporary = temporary_path(path);
+    let write = || -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        annotations.write_to(&mut writer)?;
+        // `into_inner` flushes, and returns the error if that fails
+        let file = writer.into_inner().map_err(io::IntoInnerError::into_error)?;
+        // The data is on the disk before the rename makes it visible
+        file.sync_all()
+    };
+
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&temporary);
+        return Err(io_error(&temporary, "write")(e));
+    }
+    fs::rename(&temporary, path).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        io_error(path, "replace")(e)
+    })
+}
+
+// `$RUST_NOTES_ANNOTATIONS`, or ~/.rust-notes/annotations.txt
+pub(crate) fn default_path() -> PathBuf {
+    if let Some(path) = env::var_os("RUST_NOTES_ANNOTATIONS") {
+        return PathBuf::from(path);
+    }
+    let home = env::var_os("HOME").map_or_else(env::temp_dir, PathBuf::from);
+    home.join(".rust-notes").join("annotations.txt")
+}
+
+fn print_annotations(lesson: &str, texts: &[String]) {
+    println!("{}:", lesson);
+    for text in texts {
+        println!("    - {}", text);
+    }
+}
+
+// `./main note [lesson [text...]]`
+pub(crate) fn note_main(lessons: &[LessonEntry], args: &[String]) {
+    let path = default_path();
+    let result = match args {
+        [] => load_or_default(&path).map(|annotations| {
+            for (lesson, texts) in annotations.lessons() {
+                print_annotations(lesson, texts);
+            }
+            println!("{} annotations in {}", annotations.len(), path.display());
+        }),
+        [lesson, text @ ..] if macros::find_lesson(lessons, lesson).is_some() => {
+            load_or_default(&path).and_then(|mut annotations| {
+                if !text.is_empty() {
+                    annotations.add(lesson, &text.join(" "));
+                    save(&path, &annotations)?;
+                }
+                print_annotations(lesson, annotations.for_lesson(lesson));
+                Ok(())
+            })
+        }
+        _ => {
+            eprintln!("usage: ./main note [<lesson> [<text>...]], see `./main lessons` for the ids");
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// A directory of our own in the temporary directory, removed when dropped
+pub(crate) struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub(crate) fn new(name: &str) -> io::Result<Self> {
+        let dir = env::temp_dir().join(format!("rust-notes-file-io-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir)?;
+        Ok(ScratchDir(dir))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn paths() {
+    let path = Path::new("/home/learner/.rust-notes/annotations.txt");
+    println!("{}", path.display());
+    println!("    parent:    {:?}", path.parent());
+    println!("    file name: {:?}", path.file_name());
+    println!("    stem:      {:?}", path.file_stem());
+    println!("    extension: {:?}", path.extension());
+    println!("    as .bak:   {}", path.with_extension("bak").display());
+    println!("    temporary: {}", temporary_path(path).display());
+    // `join` adds a component. An absolute one replaces everything before it.
+    println!("    joined:    {}", Path::new("/home/learner").join(".rust-notes").display());
+    // Clippy warns about it, it's the surprise this line is here to show
+    #[allow(clippy::join_absolute_paths)]
+    let replaced = Path::new("/home/learner").join("/etc");
+    println!("    replaced:  {}", replaced.display());
+    let components: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    println!("    components: {:?}", components);
+}
+
+pub(crate) fn file_io_demo() {
+    paths();
+
+    let dir = match ScratchDir::new("demo") {
+        Ok(dir) => dir,
+        Err(e) => return println!("no temporary directory: {}", e),
+    };
+    let path = dir.path().join("notes").join("annotations.txt");
+
+    // The first run: nothing saved yet
+    match load(&path) {
+        Ok(_) => println!("found annotations already?"),
+        Err(e) => println!("{}", e),
+    }
+
+    let mut annotations = load_or_default(&path).unwrap_or_default();
+    annotations.add("closures", "FnOnce can only be called once");
+    annotations.add("closures", "`move` moves or copies\tevery captured variable");
+    annotations.add("lifetimes", "'a is a name for a scope,\nnot a duration");
+    match save(&path, &annotations) {
+        Ok(()) => println!("saved {} annotations to {}", annotations.len(), path.display()),
+        Err(e) => println!("error: {}", e),
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(text) => print!("the file:\n{}", text),
+        Err(e) => println!("error: {}", e),
+    }
+
+    match load(&path) {
+        Ok(loaded) => {
+            println!("loaded back, the same: {}", loaded == annotations);
+            for (lesson, texts) in loaded.lessons() {
+                print_annotations(lesson, texts);
+            }
+        }
+        Err(e) => println!("error: {}", e),
+    }
+
+    // Saving where a directory is in the way fails, and says why
+    let blocked = dir.path().join("blocked");
+    let _ = fs::create_dir_all(blocked.join("annotations.txt"));
+    if let Err(e) = save(&blocked.join("annotations.txt"), &annotations) {
+        println!("error: {}", e);
+        println!("caused by: {}", e.source().map_or(String::new(), ToString::to_string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_survives_escaping() {
+        for text in ["plain", "tab\there", "two\nlines", "back\\slash \\n", ""] {
+            assert_eq!(unescape(&escape(text)).as_deref(), Ok(text));
+            assert!(!escape(text).contains(['\n', '\t']));
+        }
+        assert!(unescape("\\x").is_err());
+        assert!(unescape("end\\").is_err());
+    }
+
+    #[test]
+    fn the_format_is_read_line_by_line() {
+        let text = "# comment\n\nclosures\tone\nlifetimes\ttwo\\nlines\nclosures\tthree\n";
+        let annotations = Annotations::read_from(text.as_bytes(), Path::new("test.txt")).unwrap();
+
+        assert_eq!(annotations.for_lesson("closures"), ["one", "three"]);
+        assert_eq!(annotations.for_lesson("lifetimes"), ["two\nlines"]);
+        assert!(annotations.for_lesson("macros").is_empty());
+        assert_eq!(annotations.len(), 3);
+
+        let error = Annotations::read_from("closures\tok\nno tab\n".as_bytes(), Path::new("test.txt")).unwrap_err();
+        assert_eq!(error.to_string(), "test.txt: line 2: expected a lesson id, a tab and the annotation");
+    }
+
+    #[test]
+    fn save_then_load() -> Result<(), Box<dyn Error>> {
+        let dir = ScratchDir::new("save-then-load")?;
+        let path = dir.path().join("sub").join("annotations.txt");
+
+        let mut annotations = Annotations::default();
+        annotations.add("closures", "a\tb");
+        annotations.add("iterators", "lazy");
+        save(&path, &annotations)?;
+        assert_eq!(load(&path)?, annotations);
+
+        // Saving again replaces the file, and leaves no temporary file behind
+        annotations.add("closures", "more");
+        save(&path, &annotations)?;
+        assert_eq!(load(&path)?.for_lesson("closures"), ["a\tb", "more"]);
+        assert!(!temporary_path(&path).exists());
+        assert_eq!(fs::read_dir(path.parent().unwrap())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_files() -> Result<(), Box<dyn Error>> {
+        let dir = ScratchDir::new("missing")?;
+        let path = dir.path().join("nothing.txt");
+
+        let error = load(&path).unwrap_err();
+        assert!(matches!(&error, AnnotationsError::Io { source, .. } if source.kind() == io::ErrorKind::NotFound));
+        assert!(error.to_string().ends_with("(it doesn't exist, add a note to create it)"));
+
+        assert_eq!(load_or_default(&path)?, Annotations::default());
+        Ok(())
+    }
+
+    // Whoever runs the tests, even root
+    #[test]
+    fn permission_denied_is_an_error() {
+        let denied = || AnnotationsError::Io {
+            path: PathBuf::from("notes/annotations.txt"),
+            action: "read",
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        };
+        assert_eq!(denied().to_string(), "can't read notes/annotations.txt (permission denied, check who owns it)");
+        assert!(matches!(
+            default_if_missing(Err(denied())),
+            Err(AnnotationsError::Io { source, .. }) if source.kind() == io::ErrorKind::PermissionDenied
+        ));
+
+        let missing = AnnotationsError::Io {
+            path: PathBuf::from("notes/annotations.txt"),
+            action: "read",
+            source: io::Error::from(io::ErrorKind::NotFound),
+        };
+        assert_eq!(missing.to_string(), "can't read notes/annotations.txt (it doesn't exist, add a note to create it)");
+        assert!(default_if_missing(Err(missing)).is_ok_and(|annotations| annotations == Annotations::default()));
+    }
+
+    // The same with a real file, when we aren't root
+    #[cfg(unix)]
+    #[test]
+    fn permission_denied() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = ScratchDir::new("permissions")?;
+        let path = dir.path().join("annotations.txt");
+        fs::write(&path, "closures\tsecret\n")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o000))?;
+
+        match load(&path) {
+            // root can read anything, there's nothing to test then
+            Ok(_) => {}
+            Err(error) => {
+                let denied = io::ErrorKind::PermissionDenied;
+                assert!(matches!(&error, AnnotationsError::Io { source, .. } if source.kind() == denied));
+                assert!(error.to_string().contains("permission denied"));
+                // Not a missing file: no silent empty annotations either
+                assert!(load_or_default(&path).is_err());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn failed_saves_keep_the_old_file() -> Result<(), Box<dyn Error>> {
+        let dir = ScratchDir::new("failed-save")?;
+        let path = dir.path().join("annotations.txt");
+        let mut annotations = Annotations::default();
+        annotations.add("closures", "old");
+        save(&path, &annotations)?;
+
+        // A directory where the temporary file should go: the write fails
+        // before the rename, the old file is untouched
+        fs::create_dir(temporary_path(&path))?;
+        annotations.add("closures", "new");
+        assert!(matches!(save(&path, &annotations), Err(AnnotationsError::Io { action: "write", .. })));
+        assert_eq!(load(&path)?.for_lesson("closures"), ["old"]);
+        Ok(())
+    }
+}
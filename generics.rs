@@ -0,0 +1,391 @@
+// !!!!!!!!!!!!!!!!!!!! Generics https://doc.rust-lang.org/rust-by-example/generics.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`struct Pair(i32, f32)` only holds an `i32` and an `f32`. `Pair<T, U>` holds any two types: `T` and `U` are type
+parameters, filled in where the pair is used (`Pair<&str, u8>`, `Pair<f64, f64>`...).
+
+    - Bounds: a generic function can only do with `T` what every `T` can do, which is almost nothing. `T: PartialOrd`
+      asks for types that can be compared, and in exchange allows `<`. Methods can have their own bounds:
+      `max` only exists on pairs of two comparable values of the same type.
+    - `where` clauses say the same as bounds, but after the signature, which reads better when there are many.
+    - Associated types: a trait can name a type that each implementation chooses (`Iterator::Item`).
+    - Const generics: a parameter can also be a value, like the size of an array: `Matrix<2, 3>`.
+
+Generics cost nothing at run time. The compiler copies a generic function for each set of types it's used with
+("monomorphisation"), and each copy is as fast as if it was written by hand for those types. The price is paid in
+compile time and binary size instead.
+*/
+
+use std::any;
+use std::fmt;
+use std::mem;
+use std::ops::{Add, Mul};
+
+use crate::compile_fail::CompileFailCase;
+
+// !!!!!!!!!!!!!!!!!!!! Pair<T, U> !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Pair<T, U>(pub(crate) T, pub(crate) U);
+
+// `impl<T, U>`: these methods exist for every `Pair`, whatever `T` and `U` are
+impl<T, U> Pair<T, U> {
+    // The types follow the values: a `Pair<i32, f32>` becomes a `Pair<f32, i32>`
+    pub(crate) fn swap(self) -> Pair<U, T> {
+        Pair(self.1, self.0)
+    }
+
+    pub(crate) fn map_first<V>(self, f: impl FnOnce(T) -> V) -> Pair<V, U> {
+        Pair(f(self.0), self.1)
+    }
+}
+
+// Only for pairs of one comparable type: `Pair(1, 2.0).max()` doesn't compile
+impl<T: PartialOrd> Pair<T, T> {
+    pub(crate) fn max(&self) -> &T {
+        if self.0 >= self.1 {
+            &self.0
+        } else {
+            &self.1
+        }
+    }
+}
+
+impl<T: fmt::Display, U: fmt::Display> fmt::Display for Pair<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.0, self.1)
+    }
+}
+
+// The `Pair` of main.rs is one of these
+impl From<crate::Pair> for Pair<i32, f32> {
+    fn from(pair: crate::Pair) -> Self {
+        Pair(pair.0, pair.1)
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Bounds and where clauses !!!!!!!!!!!!!!!!!!!!
+
+// `T: PartialOrd` lets us use `>`. The result borrows from `items`.
+pub(crate) fn largest<T: PartialOrd>(items: &[T]) -> Option<&T> {
+    let mut largest = items.first()?;
+    for item in items {
+        if item > largest {
+            largest = item;
+        }
+    }
+    Some(largest)
+}
+
+// The same bounds as `fn sum<T: Copy + Add<Output = T>>`, with a `where`
+// clause: easier to read once there are several of them
+pub(crate) fn sum_pairs<T, U>(pairs: &[Pair<T, U>]) -> Pair<T, U>
+where
+    T: Copy + Add<Output = T> + Default,
+    U: Copy + Add<Output = U> + Default,
+{
+    pairs.iter().fold(Pair(T::default(), U::default()), |total, pair| Pair(total.0 + pair.0, total.1 + pair.1))
+}
+
+// `where` can also constrain types that aren't parameters, like the items of
+// an iterator
+pub(crate) fn join<I>(items: I, separator: &str) -> String
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+{
+    items.into_iter().map(|item| item.to_string()).collect::<Vec<_>>().join(separator)
+}
+
+// !!!!!!!!!!!!!!!!!!!! Associated types !!!!!!!!!!!!!!!!!!!!
+
+/*
+With a type parameter, `trait Container<T>`, a type could implement `Container<i32>` and `Container<String>`, and
+every function using a container would need the extra `T`: `fn count<T, C: Container<T>>(c: &C, item: &T)`. An
+associated type says that each container has exactly one item type, and lets functions name it `C::Item`.
+*/
+pub(crate) trait Container {
+    type Item;
+
+    fn items(&self) -> Vec<&Self::Item>;
+
+    fn count(&self, item: &Self::Item) -> usize
+    where
+        Self::Item: PartialEq,
+    {
+        self.items().into_iter().filter(|&candidate| candidate == item).count()
+    }
+}
+
+impl<T> Container for Pair<T, T> {
+    type Item = T;
+
+    fn items(&self) -> Vec<&T> {
+        vec![&self.0, &self.1]
+    }
+}
+
+impl<T> Container for Vec<T> {
+    type Item = T;
+
+    fn items(&self) -> Vec<&T> {
+        self.iter().collect()
+    }
+}
+
+// `C::Item`, no extra type parameter
+pub(crate) fn first_item<C: Container>(container: &C) -> Option<&C::Item> {
+    container.items().into_iter().next()
+}
+
+// !!!!!!!!!!!!!!!!!!!! Const generics !!!!!!!!!!!!!!!!!!!!
+
+// The size is part of the type: a `Matrix<2, 3>` and a `Matrix<3, 2>` are as
+// different as an `i32` and a `String`. No `Vec`, the numbers live inline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Matrix<const R: usize, const C: usize> {
+    pub(crate) rows: [[i64; C]; R],
+}
+
+impl<const R: usize, const C: usize> Matrix<R, C> {
+    pub(crate) fn new(rows: [[i64; C]; R]) -> Self {
+        Matrix { rows }
+    }
+
+    pub(crate) fn transpose(&self) -> Matrix<C, R> {
+        let mut rows = [[0; R]; C];
+        for (i, row) in self.rows.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                rows[j][i] = value;
+            }
+        }
+        Matrix { rows }
+    }
+}
+
+// Only square matrices have an identity
+impl<const N: usize> Matrix<N, N> {
+    pub(crate) fn identity() -> Self {
+        let mut rows = [[0; N]; N];
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[i] = 1;
+        }
+        Matrix { rows }
+    }
+}
+
+// (R x C) * (C x K) = (R x K). Multiplying a `Matrix<2, 3>` by another
+// `Matrix<2, 3>` is a compile error, not a panic at run time.
+impl<const R: usize, const C: usize, const K: usize> Mul<Matrix<C, K>> for Matrix<R, C> {
+    type Output = Matrix<R, K>;
+
+    fn mul(self, other: Matrix<C, K>) -> Matrix<R, K> {
+        let mut rows = [[0; K]; R];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = (0..C).map(|k| self.rows[i][k] * other.rows[k][j]).sum();
+            }
+        }
+        Matrix { rows }
+    }
+}
+
+impl<const R: usize, const C: usize> fmt::Display for Matrix<R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in &self.rows {
+            writeln!(f, "[{}]", join(row.iter().map(|value| format!("{:>3}", value)), " "))?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) const WRONG_DIMENSIONS: CompileFailCase = CompileFailCase {
+    name: "wrong_dimensions",
+    expected_error: "E0308",
+    source: r#"
+pub struct Matrix<const R: usize, const C: usize> {
+    pub rows: [[i64; C]; R],
+}
+
+pub fn mul<const R: usize, const C: usize, const K: usize>(a: Matrix<R, C>, b: Matrix<C, K>) -> Matrix<R, K> {
+    let _ = (a, b);
+    Matrix { rows: [[0; K]; R] }
+}
+
+pub fn main() {
+    let a = Matrix { rows: [[1, 2, 3], [4, 5, 6]] };
+    let b = Matrix { rows: [[1, 2, 3], [4, 5, 6]] };
+    // Error! mismatched types: expected `Matrix<3, _>`, found `Matrix<2, 3>`
+    let _ = mul(a, b);
+}
+"#,
+};
+
+pub(crate) const MAX_OF_MIXED_PAIR: CompileFailCase = CompileFailCase {
+    name: "max_of_mixed_pair",
+    expected_error: "E0599",
+    source: r#"
+pub struct Pair<T, U>(pub T, pub U);
+
+impl<T: PartialOrd> Pair<T, T> {
+    pub fn max(&self) -> &T {
+        if self.0 >= self.1 { &self.0 } else { &self.1 }
+    }
+}
+
+pub fn main() {
+    // Error! no method named `max` found for `Pair<i32, f32>`: it only exists
+    // when both types are the same
+    let _ = Pair(1, 2.0_f32).max();
+}
+"#,
+};
+
+pub(crate) const COMPILE_FAIL_CASES: [&CompileFailCase; 2] = [&WRONG_DIMENSIONS, &MAX_OF_MIXED_PAIR];
+
+// !!!!!!!!!!!!!!!!!!!! Monomorphisation !!!!!!!!!!!!!!!!!!!!
+
+// Each `Pair<T, U>` is its own type, laid out for its `T` and `U`: no boxes,
+// no type tags. `(type, size in bytes)`.
+pub(crate) fn pair_sizes() -> [(&'static str, usize); 6] {
+    fn entry<T>() -> (&'static str, usize) {
+        (any::type_name::<T>(), mem::size_of::<T>())
+    }
+
+    [
+        entry::<Pair<u8, u8>>(),
+        // 3 bytes of padding after the `u8`, so the `u32` is aligned
+        entry::<Pair<u8, u32>>(),
+        entry::<Pair<i32, f32>>(),
+        entry::<Pair<u64, u8>>(),
+        entry::<Pair<String, ()>>(),
+        entry::<Matrix<2, 3>>(),
+    ]
+}
+
+pub(crate) fn generics_demo() {
+    let pair = Pair::from(crate::Pair(1, 0.1));
+    println!("{} swapped is {}", pair, pair.swap());
+    println!("{}", Pair("Peter", 27).map_first(str::len));
+    println!("max of {} is {}", Pair(3, 8), Pair(3, 8).max());
+    println!("max of {} is {}", Pair("pear", "apple"), Pair("pear", "apple").max());
+
+    println!("largest: {:?} {:?} {:?}", largest(&[3, 9, 2]), largest(&['x', 'b']), largest::<f64>(&[]));
+    println!("sum: {}", sum_pairs(&[Pair(1, 0.5), Pair(2, 0.25), Pair(3, 0.125)]));
+    println!("joined: {}", join([Pair(1, 'a'), Pair(2, 'b')], " + "));
+
+    let numbers = vec![1, 2, 2, 3];
+    println!("{:?} has {} twos, first item {:?}", numbers, numbers.count(&2), first_item(&numbers));
+    println!("{} has {} sevens", Pair(7, 7), Pair(7, 7).count(&7));
+
+    let a = Matrix::new([[1, 2, 3], [4, 5, 6]]);
+    print!("a =\n{}a transposed =\n{}", a, a.transpose());
+    print!("a * a transposed =\n{}", a * a.transpose());
+    println!("a * identity == a: {}", a * Matrix::<3, 3>::identity() == a);
+
+    for (name, size) in pair_sizes() {
+        println!("size of {}: {} bytes", name, size);
+    }
+
+    // One copy of `largest` per type, at a different address
+    let for_i32: fn(&[i32]) -> Option<&i32> = largest::<i32>;
+    let for_char: fn(&[char]) -> Option<&char> = largest::<char>;
+    println!("largest::<i32> at {:p}, largest::<char> at {:p}", for_i32 as *const (), for_char as *const ());
+
+    println!(
+        "{} cases in this chapter must fail to compile, run `./main compile-fail` to check them",
+        COMPILE_FAIL_CASES.len()
+    );
+    for case in COMPILE_FAIL_CASES {
+        println!("    {} ({})", case.name, case.expected_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::Outcome;
+
+    // `WRONG_DIMENSIONS` with a 3x2 matrix on the right: a 2x3 times a 3x2 is fine
+    const RIGHT_DIMENSIONS: &str = r#"
+pub struct Matrix<const R: usize, const C: usize> {
+    pub rows: [[i64; C]; R],
+}
+
+pub fn mul<const R: usize, const C: usize, const K: usize>(a: Matrix<R, C>, b: Matrix<C, K>) -> Matrix<R, K> {
+    let _ = (a, b);
+    Matrix { rows: [[0; K]; R] }
+}
+
+pub fn main() {
+    let a = Matrix { rows: [[1, 2, 3], [4, 5, 6]] };
+    let b = Matrix { rows: [[1, 2], [3, 4], [5, 6]] };
+    let _ = mul(a, b);
+}
+"#;
+
+    #[test]
+    fn pairs() {
+        let pair = Pair::from(crate::Pair(1, 0.5));
+        assert_eq!(pair.swap(), Pair(0.5, 1));
+        assert_eq!(Pair(2, 9).max(), &9);
+        assert_eq!(Pair(9.5, 2.0).max(), &9.5);
+        assert_eq!(Pair("a", 1).map_first(|s| s.len()), Pair(1, 1));
+        assert_eq!(Pair("a", 1).to_string(), "(a, 1)");
+    }
+
+    #[test]
+    fn bounded_functions() {
+        assert_eq!(largest(&[1, 5, 3]), Some(&5));
+        assert_eq!(largest::<u8>(&[]), None);
+        assert_eq!(largest(&["b", "c", "a"]), Some(&"c"));
+        assert_eq!(sum_pairs(&[Pair(1, 2.5), Pair(2, 0.5)]), Pair(3, 3.0));
+        assert_eq!(sum_pairs::<u8, u8>(&[]), Pair(0, 0));
+        assert_eq!(join(1..=3, ", "), "1, 2, 3");
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(Pair(1, 1).count(&1), 2);
+        assert_eq!(vec!["a", "b", "a"].count(&"a"), 2);
+        assert_eq!(first_item(&Pair('x', 'y')), Some(&'x'));
+        assert_eq!(first_item(&Vec::<i32>::new()), None);
+    }
+
+    #[test]
+    fn matrices() {
+        let a = Matrix::new([[1, 2], [3, 4], [5, 6]]);
+        let b = Matrix::new([[1, 0, 2], [0, 1, 3]]);
+
+        assert_eq!(a.transpose(), Matrix::new([[1, 3, 5], [2, 4, 6]]));
+        assert_eq!(a * b, Matrix::new([[1, 2, 8], [3, 4, 18], [5, 6, 28]]));
+        assert_eq!(b * a, Matrix::new([[11, 14], [18, 22]]));
+        assert_eq!(Matrix::<2, 2>::identity() * (b * a), b * a);
+    }
+
+    #[test]
+    fn sizes_depend_on_the_types() {
+        let sizes = pair_sizes();
+        assert_eq!(sizes[0].1, 2);
+        assert_eq!(sizes[1].1, 8);
+        assert_eq!(sizes[2].1, mem::size_of::<crate::Pair>());
+        assert_eq!(sizes[3].1, 16);
+        // A `()` takes no room at all
+        assert_eq!(sizes[4].1, mem::size_of::<String>());
+        assert_eq!(sizes[5].1, 6 * mem::size_of::<i64>());
+    }
+
+    // Needs `rustc`: matching dimensions compile, the cases don't
+    #[test]
+    fn cases_fail_to_compile() {
+        let fixed = CompileFailCase { name: "right_dimensions", expected_error: "E0308", source: RIGHT_DIMENSIONS };
+        match fixed.check().unwrap() {
+            Outcome::Skipped => return,
+            outcome => assert_eq!(outcome, Outcome::Compiled),
+        }
+
+        for case in COMPILE_FAIL_CASES {
+            assert_eq!(case.check().unwrap(), Outcome::FailedAsExpected, "{}", case.name);
+        }
+    }
+}
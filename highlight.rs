@@ -0,0 +1,518 @@
+// !!!!!!!!!!!!!!!!!!!! A Rust syntax highlighter https://doc.rust-lang.org/reference/tokens.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+The tokenizer of lifetimes.rs splits simple input into words, numbers and quoted text. Coloring Rust code needs a
+lexer that knows Rust's own tokens, and a few of them are tricky:
+
+    'a          a lifetime, or the start of a char? 'a' is a char, 'a is a lifetime, '∞' is a char again
+    r#"..."#    a raw string: no escapes, and it ends at `"` followed by as many `#` as it started with
+    comments    block comments nest, each opening needs its own closing: a comment can comment out code that
+                has comments
+    65.4321_f32 a float with a suffix, but 1..10 is a range and 1.max(2) a method call
+    println!    a macro: an identifier followed by `!`, unless it's `!=`
+
+Every byte of the input ends up in exactly one token, whitespace included, so joining the tokens gives the input
+back: highlighting can never lose or change code. `to_ansi` colors it for a terminal, `to_html` for the pages of
+serve.rs and export.rs. `./main source <lesson>` prints a lesson's code with colors.
+*/
+
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal};
+
+use crate::macros::{self, LessonEntry};
+use crate::serve::escape_html;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum TokenKind {
+    Whitespace,
+    Comment,
+    Keyword,
+    Identifier,
+    // `println!`, with the `!`
+    Macro,
+    Lifetime,
+    Char,
+    // Normal, raw and byte strings
+    String,
+    Number,
+    // `#[derive(Debug)]` and `#![allow(dead_code)]`, brackets included
+    Attribute,
+    Punctuation,
+}
+
+impl TokenKind {
+    // The CSS class of serve.rs' style sheet
+    fn class(self) -> Option<&'static str> {
+        match self {
+            TokenKind::Comment => Some("comment"),
+            TokenKind::Keyword => Some("keyword"),
+            TokenKind::Macro => Some("macro"),
+            TokenKind::Lifetime => Some("lifetime"),
+            TokenKind::Char | TokenKind::String => Some("string"),
+            TokenKind::Number => Some("number"),
+            TokenKind::Attribute => Some("attribute"),
+            TokenKind::Whitespace | TokenKind::Identifier | TokenKind::Punctuation => None,
+        }
+    }
+
+    // The ANSI "select graphic rendition" parameters: 1 is bold, 2 dim, 3x a
+    // foreground color (31 red, 32 green, 33 yellow, 34 blue, 35 magenta, 36 cyan)
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            TokenKind::Comment => Some("2"),
+            TokenKind::Keyword => Some("1;35"),
+            TokenKind::Macro => Some("36"),
+            TokenKind::Lifetime => Some("33"),
+            TokenKind::Char | TokenKind::String => Some("32"),
+            TokenKind::Number => Some("34"),
+            TokenKind::Attribute => Some("31"),
+            TokenKind::Whitespace | TokenKind::Identifier | TokenKind::Punctuation => None,
+        }
+    }
+}
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    // Byte offset of the token in the input
+    pub(crate) offset: usize,
+}
+
+// !!!!!!!!!!!!!!!!!!!! The lexer !!!!!!!!!!!!!!!!!!!!
+
+// Each `*_len` function gets the input from the token's first character on,
+// and returns how many bytes the token takes
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn identifier_len(rest: &str) -> usize {
+    rest.find(|c| !is_identifier_continue(c)).unwrap_or(rest.len())
+}
+
+// `/* a /* b */ c */` is one comment: every `/*` needs its own `*/`. An
+// unclosed comment runs to the end of the input, like rustc reads it.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    rest.len()
+}
+
+// From the opening quote to the closing one, skipping escaped characters
+fn quoted_len(rest: &str, quote: char) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((_, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return chars.next().map_or(rest.len(), |(end, _)| end);
+        }
+    }
+    rest.len()
+}
+
+// `r"..."`, `r#"..."#`, `br##"..."##`... The body can hold quotes, as long as
+// they aren't followed by enough `#`.
+fn raw_string_len(rest: &str) -> Option<usize> {
+    let after_prefix = rest.strip_prefix("br").or_else(|| rest.strip_prefix('r'))?;
+    let hashes = after_prefix.len() - after_prefix.trim_start_matches('#').len();
+    let body = after_prefix[hashes..].strip_prefix('"')?;
+
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let end = body.find(&closing).map_or(body.len(), |end| end + closing.len());
+    Some(rest.len() - body.len() + end)
+}
+
+// After a `'`: a char literal ('a', '∞', '\n', '\u{1F980}') or a lifetime ('a,
+// 'static, and loop labels like 'outer)
+fn quote_kind_len(rest: &str) -> (TokenKind, usize) {
+    let mut chars = rest[1..].chars();
+    match (chars.next(), chars.next()) {
+        (Some('\\'), _) => (TokenKind::Char, quoted_len(rest, '\'')),
+        (Some(c), Some('\'')) => (TokenKind::Char, 1 + c.len_utf8() + 1),
+        (Some(c), _) if is_identifier_start(c) => (TokenKind::Lifetime, 1 + identifier_len(&rest[1..])),
+        _ => (TokenKind::Punctuation, 1),
+    }
+}
+
+// 42, 3_000, 0xff_u8, 1e-3, 65.4321_f32. A `.` belongs to the number only when
+// a digit follows it: `1..10` and `1.max(2)` keep theirs.
+fn number_len(rest: &str) -> usize {
+    let radix_prefix = ["0x", "0o", "0b"].iter().any(|prefix| rest.starts_with(prefix));
+    let mut seen_dot = false;
+    let mut chars = rest.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        match c {
+            c if is_identifier_continue(c) => {
+                // The sign of an exponent: 1e-3. In 0x1e-3 `e` is a digit, and
+                // in 1usize-1 it's part of the suffix.
+                let mantissa = rest[..i].chars().all(|c| c.is_ascii_digit() || c == '_' || c == '.');
+                let exponent = !radix_prefix && mantissa && (c == 'e' || c == 'E');
+                if exponent && matches!(next, Some('+' | '-')) {
+                    chars.next();
+                }
+            }
+            '.' if !radix_prefix && !seen_dot && next.is_some_and(|next| next.is_ascii_digit()) => seen_dot = true,
+            _ => return i,
+        }
+    }
+    rest.len()
+}
+
+// `#[...]` or `#![...]`, up to the matching `]`
+fn attribute_len(rest: &str) -> Option<usize> {
+    let open = if rest.starts_with("#[") { 1 } else if rest.starts_with("#![") { 2 } else { return None };
+    let mut depth = 0;
+    for (i, c) in rest.char_indices().skip(open) {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 1 => return Some(i + 1),
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    Some(rest.len())
+}
+
+pub(crate) struct Lexer<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Lexer { input, offset: 0 }
+    }
+
+    // The kind and the length of the token starting at `rest`
+    fn scan(rest: &str) -> (TokenKind, usize) {
+        let mut chars = rest.chars();
+        let first = chars.next().unwrap_or_default();
+        let second = chars.next();
+
+        if first.is_whitespace() {
+            return (TokenKind::Whitespace, rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len()));
+        }
+        if rest.starts_with("//") {
+            return (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()));
+        }
+        if rest.starts_with("/*") {
+            return (TokenKind::Comment, block_comment_len(rest));
+        }
+        if let Some(len) = raw_string_len(rest) {
+            return (TokenKind::String, len);
+        }
+        if let Some(len) = attribute_len(rest) {
+            return (TokenKind::Attribute, len);
+        }
+
+        match (first, second) {
+            ('"', _) => (TokenKind::String, quoted_len(rest, '"')),
+            ('b', Some('"')) => (TokenKind::String, 1 + quoted_len(&rest[1..], '"')),
+            ('b', Some('\'')) => (TokenKind::Char, 1 + quoted_len(&rest[1..], '\'')),
+            ('\'', _) => quote_kind_len(rest),
+            (c, _) if c.is_ascii_digit() => (TokenKind::Number, number_len(rest)),
+            // A raw identifier: `r#type` is an identifier named "type"
+            ('r', Some('#')) if rest[2..].starts_with(is_identifier_start) => {
+                (TokenKind::Identifier, 2 + identifier_len(&rest[2..]))
+            }
+            (c, _) if is_identifier_start(c) => {
+                let len = identifier_len(rest);
+                let after = &rest[len..];
+                if KEYWORDS.contains(&&rest[..len]) {
+                    (TokenKind::Keyword, len)
+                } else if after.starts_with('!') && !after.starts_with("!=") {
+                    (TokenKind::Macro, len + 1)
+                } else {
+                    (TokenKind::Identifier, len)
+                }
+            }
+            (c, _) => (TokenKind::Punctuation, c.len_utf8()),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let rest = &self.input[self.offset..];
+        if rest.is_empty() {
+            return None;
+        }
+        let (kind, len) = Lexer::scan(rest);
+        let token = Token { kind, text: &rest[..len], offset: self.offset };
+        self.offset += len;
+        Some(token)
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Output !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) fn to_html(code: &str) -> String {
+    let mut html = String::with_capacity(code.len() * 2);
+    for token in Lexer::new(code) {
+        match token.kind.class() {
+            Some(class) => {
+                let _ = write!(html, "<span class=\"{}\">{}</span>", class, escape_html(token.text));
+            }
+            None => html.push_str(&escape_html(token.text)),
+        }
+    }
+    html
+}
+
+pub(crate) fn to_ansi(code: &str) -> String {
+    let mut colored = String::with_capacity(code.len() * 2);
+    for token in Lexer::new(code) {
+        match token.kind.ansi() {
+            // "\x1b[0m" resets the color
+            Some(color) => {
+                let _ = write!(colored, "\x1b[{}m{}\x1b[0m", color, token.text);
+            }
+            None => colored.push_str(token.text),
+        }
+    }
+    colored
+}
+
+// Colors only for a person looking at a terminal: piped into a file, or read by
+// serve.rs, escape codes would be garbage. `NO_COLOR` turns them off anyway
+// (https://no-color.org).
+pub(crate) fn colors_enabled() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+pub(crate) fn print_code(code: &str) {
+    if colors_enabled() {
+        println!("{}", to_ansi(code));
+    } else {
+        println!("{}", code);
+    }
+}
+
+// `./main source <lesson>`: a lesson's file, with colors
+pub(crate) fn source_main(lessons: &[LessonEntry], args: &[String]) {
+    match args.first().and_then(|id| macros::find_lesson(lessons, id)) {
+        Some(lesson) => print_code(lesson.source.trim_end()),
+        None => {
+            eprintln!("usage: ./main source <lesson>, see `./main lessons` for the ids");
+            std::process::exit(2);
+        }
+    }
+}
+
+pub(crate) fn highlight_demo() {
+    let snippet = "let c: &'static str = r#\"'∞'\"#; // not a char\n'outer: for n in 1..=3 { x /= 65.4321_f32; }";
+    for token in Lexer::new(snippet).filter(|token| token.kind != TokenKind::Whitespace) {
+        println!("{:>3} {:<12} {}", token.offset, format!("{:?}", token.kind), token.text);
+    }
+    print_code(snippet);
+
+    println!("{}", to_html("println!(\"{}\", 'a');"));
+
+    // Every file of the notes, lexed: nothing lost, and what they're made of
+    let mut total = 0;
+    let mut counts = std::collections::BTreeMap::new();
+    for lesson in crate::LESSONS {
+        let tokens: Vec<Token> = Lexer::new(lesson.source).collect();
+        assert_eq!(tokens.iter().map(|token| token.text).collect::<String>(), lesson.source);
+        total += tokens.len();
+        for token in tokens {
+            *counts.entry(token.kind).or_insert(0) += 1;
+        }
+    }
+    println!("{} tokens in {} lessons:", total, crate::LESSONS.len());
+    for (kind, count) in counts {
+        println!("    {:<12} {}", format!("{:?}", kind), count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN_RS: &str = include_str!("main.rs");
+
+    fn tokens(code: &str) -> Vec<(TokenKind, &str)> {
+        Lexer::new(code)
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    // The snippet, found in main.rs so it can't drift from the notes
+    fn from_main_rs(snippet: &str) -> &str {
+        assert!(MAIN_RS.contains(snippet), "main.rs has no {:?}", snippet);
+        snippet
+    }
+
+    #[test]
+    fn nothing_is_lost() {
+        for source in crate::LESSONS.iter().map(|lesson| lesson.source).chain([MAIN_RS]) {
+            let mut offset = 0;
+            for token in Lexer::new(source) {
+                assert_eq!(token.offset, offset);
+                assert!(!token.text.is_empty());
+                offset += token.text.len();
+            }
+            assert_eq!(offset, source.len());
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let derive = from_main_rs(
+            "#[derive(Debug, Clone, PartialEq)] /*\n \
+             derive(Debug) asks the compiler to auto-generate a suitable implementation of the Debug trait.\n \
+             (https://doc.rust-lang.org/std/fmt/trait.Debug.html)\n \
+             derive(PartialEq) lets us compare two people with `==` and `assert_eq!`.\n*/\nstruct Person {",
+        );
+        let derived = tokens(derive);
+        assert_eq!(derived[0], (TokenKind::Attribute, "#[derive(Debug, Clone, PartialEq)]"));
+        assert_eq!(derived[1].0, TokenKind::Comment);
+        assert!(derived[1].1.ends_with("`assert_eq!`.\n*/"));
+        assert_eq!(
+            derived[2..],
+            [(TokenKind::Keyword, "struct"), (TokenKind::Identifier, "Person"), (TokenKind::Punctuation, "{")]
+        );
+
+        assert_eq!(
+            tokens("/* a /* b */ c */ d // e\nf"),
+            [
+                (TokenKind::Comment, "/* a /* b */ c */"),
+                (TokenKind::Identifier, "d"),
+                (TokenKind::Comment, "// e"),
+                (TokenKind::Identifier, "f"),
+            ]
+        );
+        // Unclosed: to the end
+        assert_eq!(tokens("/* a /* b */"), [(TokenKind::Comment, "/* a /* b */")]);
+    }
+
+    #[test]
+    fn chars_and_lifetimes() {
+        let sheep = from_main_rs("fn name(&self) -> &'static str;");
+        assert!(tokens(sheep).contains(&(TokenKind::Lifetime, "'static")));
+
+        assert_eq!(
+            tokens("'∞' 'a' 'a '\\'' '\\u{1F980}' b'x' 'outer: loop"),
+            [
+                (TokenKind::Char, "'∞'"),
+                (TokenKind::Char, "'a'"),
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Char, "'\\''"),
+                (TokenKind::Char, "'\\u{1F980}'"),
+                (TokenKind::Char, "b'x'"),
+                (TokenKind::Lifetime, "'outer"),
+                (TokenKind::Punctuation, ":"),
+                (TokenKind::Keyword, "loop"),
+            ]
+        );
+        // In main.rs, '∞' is in a comment: the comment wins
+        let scalar_types = from_main_rs("/*\n        Scalar Types\n            Signed integers");
+        assert_eq!(tokens(scalar_types)[0].0, TokenKind::Comment);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            tokens(r###"r#"a "quoted" \n"# r"\d" br##"#"##"###),
+            [
+                (TokenKind::String, r###"r#"a "quoted" \n"#"###),
+                (TokenKind::String, r#"r"\d""#),
+                (TokenKind::String, r###"br##"#"##"###),
+            ]
+        );
+        assert_eq!(
+            tokens(r#""say \"hi\"" b"\x00" r#type"#),
+            [
+                (TokenKind::String, r#""say \"hi\"""#),
+                (TokenKind::String, r#"b"\x00""#),
+                (TokenKind::Identifier, "r#type"),
+            ]
+        );
+
+        let eprintln = from_main_rs("eprintln!(\"{} Ekrem\", \"Bot\");");
+        assert_eq!(
+            tokens(eprintln)[..3],
+            [(TokenKind::Macro, "eprintln!"), (TokenKind::Punctuation, "("), (TokenKind::String, "\"{} Ekrem\"")]
+        );
+        assert_eq!(tokens("a != b")[1], (TokenKind::Punctuation, "!"));
+    }
+
+    #[test]
+    fn numbers() {
+        let decimal = from_main_rs("let decimal = 65.4321_f32;");
+        assert_eq!(tokens(decimal)[3], (TokenKind::Number, "65.4321_f32"));
+        let red = from_main_rs("Red = 0xff0000,");
+        assert_eq!(tokens(red)[2], (TokenKind::Number, "0xff0000"));
+
+        assert_eq!(
+            tokens("1..10 1.max(2) 1e-3 0x1e-3 3_000u64 1usize-1 x.0"),
+            [
+                (TokenKind::Number, "1"),
+                (TokenKind::Punctuation, "."),
+                (TokenKind::Punctuation, "."),
+                (TokenKind::Number, "10"),
+                (TokenKind::Number, "1"),
+                (TokenKind::Punctuation, "."),
+                (TokenKind::Identifier, "max"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::Number, "2"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Number, "1e-3"),
+                (TokenKind::Number, "0x1e"),
+                (TokenKind::Punctuation, "-"),
+                (TokenKind::Number, "3"),
+                (TokenKind::Number, "3_000u64"),
+                (TokenKind::Number, "1usize"),
+                (TokenKind::Punctuation, "-"),
+                (TokenKind::Number, "1"),
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Punctuation, "."),
+                (TokenKind::Number, "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ansi_and_html() {
+        assert_eq!(to_ansi("let x = 'a'; // ∞"), "\x1b[1;35mlet\x1b[0m x = \x1b[32m'a'\x1b[0m; \x1b[2m// ∞\x1b[0m");
+        assert_eq!(
+            to_html("fn f<'a>(s: &'a str) { println!(\"<{}>\", s) }"),
+            "<span class=\"keyword\">fn</span> f&lt;<span class=\"lifetime\">'a</span>&gt;(s: &amp;\
+             <span class=\"lifetime\">'a</span> str) { <span class=\"macro\">println!</span>(\
+             <span class=\"string\">&quot;&lt;{}&gt;&quot;</span>, s) }"
+        );
+    }
+}
@@ -0,0 +1,451 @@
+// !!!!!!!!!!!!!!!!!!!! Iterators https://doc.rust-lang.org/rust-by-example/trait/iter.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+The `Iterator` trait only asks for one method:
+
+    fn next(&mut self) -> Option<Self::Item>
+
+Returning `Some(item)` hands out the next item, `None` says there are no more. Everything else (`map`,
+`filter`, `take_while`, `fold`, `sum`, `collect`...) comes for free, built on top of `next`.
+
+Adapters like `map` and `filter` are lazy: they only wrap the iterator, nothing runs until something consumes
+it (a `for` loop, `collect`, `sum`...). That's why a chain of adapters compiles to roughly the same loop we
+would have written by hand.
+*/
+
+use std::fmt::{self, Write};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::{is_divisible_by, PointTest, Rectangle};
+
+// !!!!!!!!!!!!!!!!!!!! Custom iterators !!!!!!!!!!!!!!!!!!!!
+
+// 0, 1, 1, 2, 3, 5, 8... every Fibonacci number that fits in a `u64`
+pub(crate) struct Fibonacci {
+    curr: Option<u64>,
+    // `None` once we've gone past `u64::MAX`
+    next: Option<u64>,
+}
+
+pub(crate) fn fibonacci() -> Fibonacci {
+    Fibonacci { curr: Some(0), next: Some(1) }
+}
+
+impl Iterator for Fibonacci {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        // `?` on an `Option`: returns `None` once we're past the last number
+        let curr = self.curr?;
+
+        // The last number that fits is still returned, the one after it is
+        // `None`, and so is everything after that
+        self.curr = self.next;
+        self.next = self.next.and_then(|next| curr.checked_add(next));
+
+        Some(curr)
+    }
+}
+
+// What fizzbuzz prints for `n`, as a value instead of a `println!`. The words
+// are `&'static str`, so making one doesn't allocate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Word {
+    Text(&'static str),
+    Number(u32),
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Word::Text(text) => write!(f, "{}", text),
+            Word::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+pub(crate) fn fizzbuzz_word(n: u32) -> Word {
+    if is_divisible_by(n, 15) {
+        Word::Text("fizzbuzz")
+    } else if is_divisible_by(n, 3) {
+        Word::Text("fizz")
+    } else if is_divisible_by(n, 5) {
+        Word::Text("buzz")
+    } else {
+        Word::Number(n)
+    }
+}
+
+// fizzbuzz for `start..end`, one word at a time
+pub(crate) struct FizzBuzz {
+    n: u32,
+    end: u32,
+}
+
+pub(crate) fn fizzbuzz_stream(start: u32, end: u32) -> FizzBuzz {
+    FizzBuzz { n: start, end }
+}
+
+impl Iterator for FizzBuzz {
+    type Item = Word;
+
+    fn next(&mut self) -> Option<Word> {
+        if self.n >= self.end {
+            return None;
+        }
+
+        let word = fizzbuzz_word(self.n);
+        self.n += 1;
+        Some(word)
+    }
+
+    // We know exactly how many words are left, which lets `collect` allocate
+    // the right amount of memory up front
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.end.saturating_sub(self.n) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for FizzBuzz {}
+
+// The unit cells `(x, y)` a `Rectangle` touches, row by row from the top.
+// The corners are `f32`, so a corner inside a cell counts that cell in.
+pub(crate) struct GridCells {
+    left: i32,
+    right: i32,
+    bottom: i32,
+    x: i32,
+    y: i32,
+}
+
+impl Rectangle {
+    pub(crate) fn cells(&self) -> GridCells {
+        let PointTest { x: x1, y: y1 } = self.top_left;
+        let PointTest { x: x2, y: y2 } = self.bottom_right;
+
+        let left = x1.min(x2).floor() as i32;
+        let top = y1.max(y2).ceil() as i32 - 1;
+
+        GridCells {
+            left,
+            right: (x1.max(x2).ceil() as i32).max(left + 1),
+            bottom: (y1.min(y2).floor() as i32).min(top),
+            x: left,
+            y: top,
+        }
+    }
+}
+
+impl Iterator for GridCells {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<(i32, i32)> {
+        if self.y < self.bottom {
+            return None;
+        }
+
+        let cell = (self.x, self.y);
+
+        self.x += 1;
+        if self.x == self.right {
+            // Next row down
+            self.x = self.left;
+            self.y -= 1;
+        }
+
+        Some(cell)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = if self.y < self.bottom {
+            0
+        } else {
+            let full_rows = (self.y - self.bottom) as usize;
+            full_rows * (self.right - self.left) as usize + (self.right - self.x) as usize
+        };
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for GridCells {}
+
+// !!!!!!!!!!!!!!!!!!!! Loops as adapter chains !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/rust-by-example/fn/hof.html
+
+// The `while n < 20` loop of main.rs, writing to a `String` instead of the
+// terminal so both versions can be compared (and benchmarked)
+pub(crate) fn fizzbuzz_while(out: &mut String) {
+    let mut n = 1;
+
+    while n < 20 {
+        if n % 15 == 0 {
+            out.push_str("fizzbuzz");
+        } else if n % 3 == 0 {
+            out.push_str("fizz");
+        } else if n % 5 == 0 {
+            out.push_str("buzz");
+        } else {
+            write!(out, "{}", n).unwrap();
+        }
+        out.push('\n');
+
+        n += 1;
+    }
+}
+
+// The same loop as an iterator: the range replaces the counter, `map` the
+// `if` chain, and `for_each` the body. (The body writes the same way the loop
+// does: going through `Display` would make this version slower, and we want
+// to time the iterator, not the formatting.)
+pub(crate) fn fizzbuzz_iter(out: &mut String) {
+    (1..20).map(fizzbuzz_word).for_each(|word| {
+        match word {
+            Word::Text(text) => out.push_str(text),
+            Word::Number(n) => write!(out, "{}", n).unwrap(),
+        }
+        out.push('\n');
+    });
+}
+
+fn is_odd(n: u32) -> bool {
+    n % 2 == 1
+}
+
+// The example of the HOF chapter: the sum of all odd squares under `upper`
+pub(crate) fn sum_odd_squares_loop(upper: u32) -> u32 {
+    let mut acc = 0;
+
+    for n in 0.. {
+        let n_squared = n * n;
+
+        if n_squared >= upper {
+            // Exceeded the upper limit, break the loop
+            break;
+        } else if is_odd(n_squared) {
+            acc += n_squared;
+        }
+    }
+
+    acc
+}
+
+// (`fold` is what `sum` does under the hood, it's spelled out here on purpose)
+#[allow(clippy::unnecessary_fold)]
+pub(crate) fn sum_odd_squares_iter(upper: u32) -> u32 {
+    (0..)
+        .map(|n| n * n) // All natural numbers squared
+        .take_while(|&n_squared| n_squared < upper) // Below the upper limit
+        .filter(|&n_squared| is_odd(n_squared)) // That are odd
+        .fold(0, |acc, n_squared| acc + n_squared) // Sum them
+}
+
+// !!!!!!!!!!!!!!!!!!!! Benchmark !!!!!!!!!!!!!!!!!!!!
+
+/*
+"Iterators are zero-cost abstractions": let's measure instead of believing it. Run with:
+    ./main bench-iterators [iterations]
+
+Build with `-O` first (`rustc --edition 2021 -O main.rs`), without optimisations the iterator version pays for
+every small function call `map` and `for_each` make, and loses.
+*/
+
+// Runs `f` `iterations` times and returns the average time of one run.
+// `black_box` stops the compiler from noticing that the result is never used
+// and removing the whole loop.
+fn bench(iterations: u32, mut f: impl FnMut(&mut String)) -> Duration {
+    let mut out = String::with_capacity(128);
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        out.clear();
+        f(black_box(&mut out));
+        black_box(&out);
+    }
+
+    start.elapsed() / iterations.max(1)
+}
+
+// `./main bench-iterators [iterations]`
+pub(crate) fn bench_main(args: &[String]) {
+    let iterations = match args.first().map(|arg| arg.parse::<u32>()) {
+        None => 100_000,
+        Some(Ok(iterations)) if iterations > 0 => iterations,
+        Some(_) => {
+            eprintln!("usage: ./main bench-iterators [iterations]");
+            std::process::exit(2);
+        }
+    };
+
+    let while_time = bench(iterations, fizzbuzz_while);
+    let iter_time = bench(iterations, fizzbuzz_iter);
+    let sum_loop_time = bench(iterations, |out| write!(out, "{}", sum_odd_squares_loop(black_box(1000))).unwrap());
+    let sum_iter_time = bench(iterations, |out| write!(out, "{}", sum_odd_squares_iter(black_box(1000))).unwrap());
+
+    println!("{} iterations, average time per run:", iterations);
+    println!("    fizzbuzz, while loop    {:>10?}", while_time);
+    println!("    fizzbuzz, iterator      {:>10?}", iter_time);
+    println!("    odd squares, loop       {:>10?}", sum_loop_time);
+    println!("    odd squares, iterator   {:>10?}", sum_iter_time);
+}
+
+pub(crate) fn iterators_demo() {
+    let first: Vec<u64> = fibonacci().take(10).collect();
+    println!("The first 10 Fibonacci numbers: {:?}", first);
+
+    // The iterator ends by itself, before overflowing
+    println!("{} Fibonacci numbers fit in a u64, the last one is {:?}", fibonacci().count(), fibonacci().last());
+
+    // Adapters work on our iterators too: the even Fibonacci numbers under 100
+    let even: Vec<u64> = fibonacci().take_while(|&n| n < 100).filter(|n| n % 2 == 0).collect();
+    println!("Even ones under 100: {:?}", even);
+
+    let words = fizzbuzz_stream(1, 16);
+    println!("{} words: {}", words.len(), words.map(|word| word.to_string()).collect::<Vec<_>>().join(" "));
+
+    // Both versions of the `while n < 20` loop print the same thing
+    let mut by_while = String::new();
+    let mut by_iter = String::new();
+    fizzbuzz_while(&mut by_while);
+    fizzbuzz_iter(&mut by_iter);
+    println!("The while loop and the iterator print the same: {}", by_while == by_iter);
+
+    let rectangle = Rectangle {
+        top_left: PointTest { x: 0.5, y: 2.0 },
+        bottom_right: PointTest { x: 3.0, y: 0.2 },
+    };
+    let cells = rectangle.cells();
+    println!("{:?} touches {} cells:", rectangle, cells.len());
+    for y in (0..2).rev() {
+        let row: Vec<(i32, i32)> = rectangle.cells().filter(|&(_, cell_y)| cell_y == y).collect();
+        println!("    {:?}", row);
+    }
+
+    println!(
+        "Sum of odd squares under 1000: {} (loop), {} (iterator)",
+        sum_odd_squares_loop(1000),
+        sum_odd_squares_iter(1000)
+    );
+
+    println!("Run `./main bench-iterators` to time the loops against the iterators");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(x1: f32, y1: f32, x2: f32, y2: f32) -> Rectangle {
+        Rectangle {
+            top_left: PointTest { x: x1, y: y1 },
+            bottom_right: PointTest { x: x2, y: y2 },
+        }
+    }
+
+    #[test]
+    fn fibonacci_numbers() {
+        let first: Vec<u64> = fibonacci().take(10).collect();
+        assert_eq!(first, [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+
+        // F(93) is the last one under `u64::MAX`, F(94) would overflow
+        let mut all = fibonacci();
+        assert_eq!(all.by_ref().count(), 94);
+        assert_eq!(fibonacci().last(), Some(12_200_160_415_121_876_738));
+        // Once done, it stays done
+        assert_eq!(all.next(), None);
+        assert_eq!(all.next(), None);
+
+        let mut numbers = fibonacci();
+        let mut previous = numbers.next().unwrap();
+        for n in numbers.skip(1) {
+            assert!(n > previous);
+            previous = n;
+        }
+    }
+
+    #[test]
+    fn fizzbuzz_words() {
+        let words: Vec<String> = fizzbuzz_stream(9, 16).map(|word| word.to_string()).collect();
+        assert_eq!(words, ["fizz", "buzz", "11", "fizz", "13", "14", "fizzbuzz"]);
+        assert_eq!(fizzbuzz_word(7), Word::Number(7));
+
+        let mut stream = fizzbuzz_stream(1, 4);
+        assert_eq!(stream.len(), 3);
+        stream.next();
+        assert_eq!(stream.len(), 2);
+        assert_eq!(stream.collect::<Vec<_>>(), [Word::Number(2), Word::Text("fizz")]);
+
+        // Empty and backwards ranges
+        assert_eq!(fizzbuzz_stream(5, 5).len(), 0);
+        assert_eq!(fizzbuzz_stream(5, 5).next(), None);
+        assert_eq!(fizzbuzz_stream(10, 1).len(), 0);
+        assert_eq!(fizzbuzz_stream(10, 1).next(), None);
+        // Stops before `u32::MAX` instead of overflowing
+        assert_eq!(fizzbuzz_stream(u32::MAX - 2, u32::MAX).count(), 2);
+    }
+
+    #[test]
+    fn grid_cells() {
+        let cells: Vec<(i32, i32)> = rectangle(0.5, 2.0, 3.0, 0.2).cells().collect();
+        assert_eq!(cells, [(0, 1), (1, 1), (2, 1), (0, 0), (1, 0), (2, 0)]);
+
+        // The corners can be given in any order
+        let swapped: Vec<(i32, i32)> = rectangle(3.0, 0.2, 0.5, 2.0).cells().collect();
+        assert_eq!(swapped, cells);
+
+        // Negative coordinates round away from the origin
+        let negative: Vec<(i32, i32)> = rectangle(-1.5, 0.0, -0.5, -1.0).cells().collect();
+        assert_eq!(negative, [(-2, -1), (-1, -1)]);
+    }
+
+    #[test]
+    fn flat_rectangles() {
+        // Zero width: one column
+        let line: Vec<(i32, i32)> = rectangle(1.5, 2.0, 1.5, 0.0).cells().collect();
+        assert_eq!(line, [(1, 1), (1, 0)]);
+
+        // Zero height: one row
+        let line: Vec<(i32, i32)> = rectangle(0.0, 0.5, 2.0, 0.5).cells().collect();
+        assert_eq!(line, [(0, 0), (1, 0)]);
+
+        // Both: the single cell the point is in
+        let point: Vec<(i32, i32)> = rectangle(1.5, 1.5, 1.5, 1.5).cells().collect();
+        assert_eq!(point, [(1, 1)]);
+    }
+
+    #[test]
+    fn grid_cells_know_their_length() {
+        for rectangle in [
+            rectangle(0.5, 2.0, 3.0, 0.2),
+            rectangle(1.5, 2.0, 1.5, 0.0),
+            rectangle(0.0, 0.5, 2.0, 0.5),
+            rectangle(-3.0, 4.0, 5.5, -2.5),
+        ] {
+            let mut cells = rectangle.cells();
+            let mut left = rectangle.cells().count();
+            assert_eq!(cells.len(), left);
+            while cells.next().is_some() {
+                left -= 1;
+                assert_eq!(cells.len(), left);
+            }
+            assert_eq!(left, 0);
+        }
+    }
+
+    #[test]
+    fn loops_and_iterators_agree() {
+        let mut by_while = String::new();
+        let mut by_iter = String::new();
+        fizzbuzz_while(&mut by_while);
+        fizzbuzz_iter(&mut by_iter);
+        assert_eq!(by_while, by_iter);
+        assert!(by_while.starts_with("1\n2\nfizz\n4\nbuzz\n"));
+        assert_eq!(by_while.lines().count(), 19);
+
+        for upper in [0, 1, 2, 10, 1000, 123_456] {
+            assert_eq!(sum_odd_squares_loop(upper), sum_odd_squares_iter(upper), "under {}", upper);
+        }
+        // 1 + 9 + 25 + 49 + 81
+        assert_eq!(sum_odd_squares_iter(100), 165);
+    }
+}
@@ -0,0 +1,779 @@
+// !!!!!!!!!!!!!!!!!!!! A hand-written JSON encoder/decoder !!!!!!!!!!!!!!!!!!!!
+// https://www.json.org
+
+/*
+A small capstone: everything here is built from things the notes already cover.
+    - `JsonValue` is an enum whose variants hold other `JsonValue`s (a recursive type)
+    - the tokenizer is an `Iterator`, it reads one `char` at a time and never needs the whole input
+    - the parser is a set of functions calling each other recursively
+    - every failure is a `JsonError` value instead of a `panic!`, with the line and column it happened at
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+
+use crate::{EvenNumber, Person, PointTest, Rectangle, WebEvent};
+
+// Objects keep their keys in a `Vec`, so they're printed in the order they were inserted
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonError {
+    // The text isn't valid JSON
+    Syntax { line: usize, column: usize, message: String },
+    // The text is valid JSON, but doesn't have the shape of the type we asked for
+    Shape(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Syntax { line, column, message } => write!(f, "{}:{}: {}", line, column, message),
+            Self::Shape(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+// !!!!!!!!!!!!!!!!!!!! Tokenizer !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    Null,
+    True,
+    False,
+    Number(f64),
+    String(String),
+}
+
+// Where a token starts, 1-based like in text editors
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn error(self, message: impl Into<String>) -> JsonError {
+        JsonError::Syntax { line: self.line, column: self.column, message: message.into() }
+    }
+}
+
+struct Tokenizer<I: Iterator<Item = char>> {
+    chars: Peekable<I>,
+    line: usize,
+    column: usize,
+}
+
+impl<I: Iterator<Item = char>> Tokenizer<I> {
+    fn new(chars: I) -> Self {
+        Tokenizer { chars: chars.peekable(), line: 1, column: 1 }
+    }
+
+    fn current_position(&self) -> Position {
+        Position { line: self.line, column: self.column }
+    }
+
+    // Every character goes through here, so the position is always right
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn keyword(&mut self, start: Position, word: &str, token: Token) -> Result<Token, JsonError> {
+        for expected in word.chars() {
+            if self.bump() != Some(expected) {
+                return Err(start.error(format!("invalid literal, expected `{}`", word)));
+            }
+        }
+
+        Ok(token)
+    }
+
+    fn number(&mut self, start: Position) -> Result<Token, JsonError> {
+        let mut text = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                text.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        // JSON is stricter than `f64::from_str`: no leading zeros, no "1." or ".5"
+        let digits = text.strip_prefix('-').unwrap_or(&text);
+        let int_part = digits.split(['.', 'e', 'E']).next().unwrap_or("");
+        let valid_int = !int_part.is_empty() && (int_part == "0" || !int_part.starts_with('0'));
+        let valid_fraction = digits.split(['e', 'E']).next().is_some_and(|s| !s.ends_with('.'));
+
+        match text.parse::<f64>() {
+            Ok(n) if valid_int && valid_fraction && n.is_finite() => Ok(Token::Number(n)),
+            _ => Err(start.error(format!("invalid number `{}`", text))),
+        }
+    }
+
+    fn hex_escape(&mut self, start: Position) -> Result<u32, JsonError> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self.bump().and_then(|c| c.to_digit(16));
+            code = code * 16 + digit.ok_or_else(|| start.error("invalid \\u escape"))?;
+        }
+
+        Ok(code)
+    }
+
+    fn string(&mut self, start: Position) -> Result<Token, JsonError> {
+        let mut text = String::new();
+
+        loop {
+            let escape_start = self.current_position();
+
+            match self.bump() {
+                None => return Err(start.error("unterminated string")),
+                Some('"') => return Ok(Token::String(text)),
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.hex_escape(escape_start)?;
+
+                            // Characters outside the Basic Multilingual Plane are
+                            // written as two escapes, a "surrogate pair"
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.bump() != Some('\\') || self.bump() != Some('u') {
+                                    return Err(escape_start.error("unpaired surrogate"));
+                                }
+                                let low = self.hex_escape(escape_start)?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(escape_start.error("unpaired surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+
+                            char::from_u32(code).ok_or_else(|| escape_start.error("unpaired surrogate"))?
+                        }
+                        _ => return Err(escape_start.error("invalid escape")),
+                    };
+
+                    text.push(c);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(escape_start.error("control characters must be escaped"));
+                }
+                Some(c) => text.push(c),
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Tokenizer<I> {
+    type Item = Result<(Token, Position), JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.chars.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+
+        let start = self.current_position();
+
+        let token = match *self.chars.peek()? {
+            '{' | '}' | '[' | ']' | ':' | ',' => {
+                let token = match self.bump()? {
+                    '{' => Token::LeftBrace,
+                    '}' => Token::RightBrace,
+                    '[' => Token::LeftBracket,
+                    ']' => Token::RightBracket,
+                    ':' => Token::Colon,
+                    _ => Token::Comma,
+                };
+                Ok(token)
+            }
+            'n' => self.keyword(start, "null", Token::Null),
+            't' => self.keyword(start, "true", Token::True),
+            'f' => self.keyword(start, "false", Token::False),
+            '-' | '0'..='9' => self.number(start),
+            '"' => {
+                self.bump();
+                self.string(start)
+            }
+            c => {
+                self.bump();
+                Err(start.error(format!("unexpected character `{}`", c)))
+            }
+        };
+
+        Some(token.map(|token| (token, start)))
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Parser !!!!!!!!!!!!!!!!!!!!
+
+// Deeply nested input like "[[[[[[..." would otherwise overflow the stack,
+// because every level is one more recursive call
+const MAX_DEPTH: usize = 128;
+
+struct Parser<I: Iterator<Item = char>> {
+    tokens: Tokenizer<I>,
+}
+
+impl<I: Iterator<Item = char>> Parser<I> {
+    fn next_token(&mut self) -> Result<(Token, Position), JsonError> {
+        let end = self.tokens.current_position();
+        self.tokens.next().unwrap_or_else(|| Err(end.error("unexpected end of input")))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        let (token, start) = self.next_token()?;
+        self.value_from(token, start, depth)
+    }
+
+    fn value_from(&mut self, token: Token, start: Position, depth: usize) -> Result<JsonValue, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(start.error(format!("nested deeper than {} levels", MAX_DEPTH)));
+        }
+
+        match token {
+            Token::Null => Ok(JsonValue::Null),
+            Token::True => Ok(JsonValue::Bool(true)),
+            Token::False => Ok(JsonValue::Bool(false)),
+            Token::Number(n) => Ok(JsonValue::Number(n)),
+            Token::String(s) => Ok(JsonValue::String(s)),
+            Token::LeftBracket => self.array(depth),
+            Token::LeftBrace => self.object(depth),
+            other => Err(start.error(format!("expected a value, found {:?}", other))),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        let mut items = Vec::new();
+
+        let (token, start) = self.next_token()?;
+        if token == Token::RightBracket {
+            return Ok(JsonValue::Array(items));
+        }
+        items.push(self.value_from(token, start, depth + 1)?);
+
+        loop {
+            match self.next_token()? {
+                (Token::Comma, _) => items.push(self.value(depth + 1)?),
+                (Token::RightBracket, _) => return Ok(JsonValue::Array(items)),
+                (_, position) => return Err(position.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        let mut fields = Vec::new();
+
+        let (mut token, mut start) = self.next_token()?;
+        if token == Token::RightBrace {
+            return Ok(JsonValue::Object(fields));
+        }
+
+        loop {
+            let Token::String(key) = token else {
+                return Err(start.error("expected a string key"));
+            };
+
+            match self.next_token()? {
+                (Token::Colon, _) => {}
+                (_, position) => return Err(position.error("expected `:`")),
+            }
+
+            fields.push((key, self.value(depth + 1)?));
+
+            match self.next_token()? {
+                (Token::Comma, _) => (token, start) = self.next_token()?,
+                (Token::RightBrace, _) => return Ok(JsonValue::Object(fields)),
+                (_, position) => return Err(position.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+// Parses a whole document: exactly one value, and nothing after it
+pub(crate) fn parse(text: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = Parser { tokens: Tokenizer::new(text.chars()) };
+    let value = parser.value(0)?;
+
+    match parser.tokens.next() {
+        None => Ok(value),
+        Some(Ok((_, position))) => Err(position.error("trailing characters after the value")),
+        Some(Err(e)) => Err(e),
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Printing !!!!!!!!!!!!!!!!!!!!
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+fn write_number(out: &mut String, n: f64) {
+    // JSON has no NaN or infinity
+    if !n.is_finite() {
+        out.push_str("null");
+    } else {
+        // `f64`'s `Display` already prints the shortest text that parses back
+        // to the same number, and "3" rather than "3.0" for whole numbers
+        out.push_str(&n.to_string());
+    }
+}
+
+// `indent` is `None` for the compact form
+fn write_value(out: &mut String, value: &JsonValue, indent: Option<usize>, level: usize) {
+    let newline = |out: &mut String, level: usize| {
+        if let Some(width) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(width * level));
+        }
+    };
+
+    match value {
+        JsonValue::Null => out.push_str("null"),
+        JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => write_number(out, *n),
+        JsonValue::String(s) => write_string(out, s),
+        JsonValue::Array(items) if items.is_empty() => out.push_str("[]"),
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, level + 1);
+                write_value(out, item, indent, level + 1);
+            }
+            newline(out, level);
+            out.push(']');
+        }
+        JsonValue::Object(fields) if fields.is_empty() => out.push_str("{}"),
+        JsonValue::Object(fields) => {
+            out.push('{');
+            for (i, (key, item)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, level + 1);
+                write_string(out, key);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_value(out, item, indent, level + 1);
+            }
+            newline(out, level);
+            out.push('}');
+        }
+    }
+}
+
+impl JsonValue {
+    pub(crate) fn to_pretty(&self) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self, Some(2), 0);
+        out
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+// `to_string()` gives the compact form
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        write_value(&mut out, self, None, 0);
+        f.write_str(&out)
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! ToJson & FromJson !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+pub(crate) trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError>;
+
+    // Traits can provide default method definitions, like `Animal::talk`
+    fn from_json_str(text: &str) -> Result<Self, JsonError> {
+        Self::from_json(&parse(text)?)
+    }
+}
+
+// Small helpers, so the implementations below read like a description of the format
+
+fn object(fields: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn field<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, JsonError> {
+    value.get(key).ok_or_else(|| JsonError::Shape(format!("missing field `{}`", key)))
+}
+
+fn as_str<'a>(value: &'a JsonValue, what: &str) -> Result<&'a str, JsonError> {
+    match value {
+        JsonValue::String(s) => Ok(s),
+        other => Err(JsonError::Shape(format!("`{}` should be a string, found {}", what, other))),
+    }
+}
+
+fn as_f64(value: &JsonValue, what: &str) -> Result<f64, JsonError> {
+    match value {
+        JsonValue::Number(n) => Ok(*n),
+        other => Err(JsonError::Shape(format!("`{}` should be a number, found {}", what, other))),
+    }
+}
+
+// Every JSON number is an `f64`, so integers have to be checked on the way back
+fn as_integer<T: TryFrom<i64>>(value: &JsonValue, what: &str) -> Result<T, JsonError> {
+    let n = as_f64(value, what)?;
+    let out_of_range = || JsonError::Shape(format!("`{}` should be a whole number that fits, found {}", what, n));
+
+    if n.fract() != 0.0 || n.abs() > i64::MAX as f64 {
+        return Err(out_of_range());
+    }
+
+    T::try_from(n as i64).map_err(|_| out_of_range())
+}
+
+impl ToJson for Person {
+    fn to_json(&self) -> JsonValue {
+        object(vec![
+            ("name", JsonValue::String(self.name.clone())),
+            ("age", JsonValue::Number(self.age.into())),
+        ])
+    }
+}
+
+impl FromJson for Person {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        Ok(Person {
+            name: as_str(field(value, "name")?, "name")?.to_string(),
+            age: as_integer(field(value, "age")?, "age")?,
+        })
+    }
+}
+
+impl ToJson for PointTest {
+    fn to_json(&self) -> JsonValue {
+        // `f64::from(0.4_f32)` is 0.4000000059604645, because 0.4 can't be
+        // stored exactly. Going through the shortest `f32` text keeps it 0.4.
+        let widen = |n: f32| n.to_string().parse::<f64>().unwrap_or(f64::NAN);
+
+        object(vec![("x", JsonValue::Number(widen(self.x))), ("y", JsonValue::Number(widen(self.y)))])
+    }
+}
+
+impl FromJson for PointTest {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        Ok(PointTest {
+            x: as_f64(field(value, "x")?, "x")? as f32,
+            y: as_f64(field(value, "y")?, "y")? as f32,
+        })
+    }
+}
+
+impl ToJson for Rectangle {
+    fn to_json(&self) -> JsonValue {
+        object(vec![("top_left", self.top_left.to_json()), ("bottom_right", self.bottom_right.to_json())])
+    }
+}
+
+impl FromJson for Rectangle {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        Ok(Rectangle {
+            top_left: PointTest::from_json(field(value, "top_left")?)?,
+            bottom_right: PointTest::from_json(field(value, "bottom_right")?)?,
+        })
+    }
+}
+
+// Enums are written as objects with a "type" tag naming the variant
+impl ToJson for WebEvent {
+    fn to_json(&self) -> JsonValue {
+        let tag = |name: &str| ("type", JsonValue::String(name.to_string()));
+
+        match self {
+            WebEvent::PageLoad => object(vec![tag("PageLoad")]),
+            WebEvent::PageUnload => object(vec![tag("PageUnload")]),
+            WebEvent::KeyPress(c) => object(vec![tag("KeyPress"), ("key", JsonValue::String(c.to_string()))]),
+            WebEvent::Paste(s) => object(vec![tag("Paste"), ("text", JsonValue::String(s.clone()))]),
+            WebEvent::Click { x, y } => object(vec![
+                tag("Click"),
+                ("x", JsonValue::Number(*x as f64)),
+                ("y", JsonValue::Number(*y as f64)),
+            ]),
+        }
+    }
+}
+
+impl FromJson for WebEvent {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        match as_str(field(value, "type")?, "type")? {
+            "PageLoad" => Ok(WebEvent::PageLoad),
+            "PageUnload" => Ok(WebEvent::PageUnload),
+            "KeyPress" => {
+                let key = as_str(field(value, "key")?, "key")?;
+                let mut chars = key.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(WebEvent::KeyPress(c)),
+                    _ => Err(JsonError::Shape(format!("`key` should be a single character, found \"{}\"", key))),
+                }
+            }
+            "Paste" => Ok(WebEvent::Paste(as_str(field(value, "text")?, "text")?.to_string())),
+            "Click" => Ok(WebEvent::Click {
+                x: as_integer(field(value, "x")?, "x")?,
+                y: as_integer(field(value, "y")?, "y")?,
+            }),
+            other => Err(JsonError::Shape(format!("unknown WebEvent type `{}`", other))),
+        }
+    }
+}
+
+impl ToJson for EvenNumber {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(self.0.into())
+    }
+}
+
+// Decoding reuses `TryFrom`, so an odd number is rejected here too
+impl FromJson for EvenNumber {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        let n: i32 = as_integer(value, "EvenNumber")?;
+        EvenNumber::try_from(n).map_err(|_| JsonError::Shape(format!("{} is not an even number", n)))
+    }
+}
+
+// Any list of `ToJson` values is a JSON array
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        match value {
+            JsonValue::Array(items) => items.iter().map(T::from_json).collect(),
+            other => Err(JsonError::Shape(format!("expected an array, found {}", other))),
+        }
+    }
+}
+
+// The sample data of the notes, as a single document: `./main export json <file>`
+pub(crate) fn export_lesson_data() -> JsonValue {
+    let peter = Person { name: String::from("Peter"), age: 27 };
+    let rectangle = Rectangle {
+        top_left: PointTest { x: 5.2, y: 0.4 },
+        bottom_right: PointTest { x: 10.3, y: 0.2 },
+    };
+    let events = [
+        WebEvent::PageLoad,
+        WebEvent::KeyPress('x'),
+        WebEvent::Paste(String::from("my text")),
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::PageUnload,
+    ];
+
+    object(vec![
+        ("people", [peter].to_json()),
+        ("rectangle", rectangle.to_json()),
+        ("events", events.to_json()),
+        ("even_number", EvenNumber(8).to_json()),
+    ])
+}
+
+pub(crate) fn json_demo() {
+    let data = export_lesson_data();
+    println!("{}", data.to_pretty());
+
+    // The compact and the pretty form parse back to the same value
+    assert_eq!(parse(&data.to_string()), Ok(data.clone()));
+    assert_eq!(parse(&data.to_pretty()), Ok(data.clone()));
+
+    // Back to Rust values
+    let events = Vec::<WebEvent>::from_json(field(&data, "events").unwrap()).unwrap();
+    println!("{:?}", events);
+
+    let rectangle = Rectangle::from_json(field(&data, "rectangle").unwrap()).unwrap();
+    assert_eq!(rectangle.top_left, PointTest { x: 5.2, y: 0.4 });
+
+    let person = Person::from_json_str(r#"{"name": "Ekrem ç🦀", "age": 30}"#).unwrap();
+    println!("{}", person);
+
+    // Errors tell us where things went wrong
+    let broken = "{\n  \"name\": \"Peter\",\n  \"age\": 27,,\n}";
+    for input in [broken, "[1, 2", "[01]", "{\"age\": 300, \"name\": \"Old\"}"] {
+        if let Err(e) = Person::from_json_str(input) {
+            println!("{:?} -> error: {}", input, e);
+        }
+    }
+
+    if let Err(e) = EvenNumber::from_json_str("7") {
+        println!("\"7\" -> error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(text: &str) -> (usize, usize, String) {
+        match parse(text) {
+            Err(JsonError::Syntax { line, column, message }) => (line, column, message),
+            other => panic!("{:?} should be a syntax error, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn errors_have_a_line_and_a_column() {
+        let broken = "{\n  \"name\": \"Peter\",\n  \"age\": 27,,\n}";
+        assert_eq!(syntax_error(broken), (3, 13, String::from("expected a string key")));
+        assert_eq!(parse(broken).unwrap_err().to_string(), "3:13: expected a string key");
+
+        assert_eq!(syntax_error("[1, 2"), (1, 6, String::from("unexpected end of input")));
+        assert_eq!(syntax_error("[1 2]"), (1, 4, String::from("expected `,` or `]`")));
+        assert_eq!(syntax_error("{\"a\" 1}"), (1, 6, String::from("expected `:`")));
+        assert_eq!(syntax_error("[01]").0, 1);
+        assert_eq!(syntax_error("nul"), (1, 1, String::from("invalid literal, expected `null`")));
+        assert_eq!(syntax_error("1 2"), (1, 3, String::from("trailing characters after the value")));
+        // Columns count characters, not bytes
+        assert_eq!(syntax_error("[\"ç🦀\", @]"), (1, 8, String::from("unexpected character `@`")));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_ok());
+        let (line, column, message) = syntax_error(&nested(MAX_DEPTH + 2));
+        assert_eq!((line, column), (1, MAX_DEPTH + 2));
+        assert_eq!(message, format!("nested deeper than {} levels", MAX_DEPTH));
+
+        // Far too deep for the stack, if it weren't for the limit
+        assert!(parse(&nested(100_000)).is_err());
+        assert!(parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn escapes() {
+        let parsed = parse(r#""quote \" backslash \\ slash \/ \b\f\n\r\t ç 🦀""#);
+        let expected = "quote \" backslash \\ slash / \u{8}\u{c}\n\r\t ç 🦀";
+        assert_eq!(parsed, Ok(JsonValue::String(String::from(expected))));
+
+        // Printing escapes what JSON requires, and parses back
+        let text = String::from("tab\t new line\n \"quoted\" \\ \u{1} ç🦀");
+        let printed = JsonValue::String(text.clone()).to_string();
+        assert_eq!(printed, r#""tab\t new line\n \"quoted\" \\ \u0001 ç🦀""#);
+        assert_eq!(parse(&printed), Ok(JsonValue::String(text)));
+
+        assert_eq!(syntax_error(r#""\x""#), (1, 2, String::from("invalid escape")));
+        assert_eq!(syntax_error(r#""\u12G4""#), (1, 2, String::from("invalid \\u escape")));
+        assert_eq!(syntax_error(r#""\ud83e""#), (1, 2, String::from("unpaired surrogate")));
+        assert_eq!(syntax_error("\"new\nline\""), (1, 5, String::from("control characters must be escaped")));
+        assert_eq!(syntax_error("\"open"), (1, 1, String::from("unterminated string")));
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let value = parse(r#"{"a": [1, -2.5, 1e3, true, false, null], "b": {"c": "d"}, "e": []}"#).unwrap();
+        assert_eq!(value.get("e"), Some(&JsonValue::Array(Vec::new())));
+        assert_eq!(parse(&value.to_string()), Ok(value.clone()));
+        assert_eq!(parse(&value.to_pretty()), Ok(value));
+    }
+
+    // to_json, then print, then parse, then from_json gives the value back
+    fn round_trip<T: ToJson + FromJson + PartialEq + fmt::Debug>(value: T) {
+        let text = value.to_json().to_string();
+        assert_eq!(T::from_json_str(&text).as_ref(), Ok(&value), "{}", text);
+    }
+
+    #[test]
+    fn types_round_trip() {
+        round_trip(Person { name: String::from("Ekrem ç🦀 \"Doc\""), age: 255 });
+        round_trip(PointTest { x: 0.4, y: -5.2 });
+        round_trip(Rectangle { top_left: PointTest { x: 5.2, y: 0.4 }, bottom_right: PointTest { x: 10.3, y: 0.2 } });
+        round_trip(EvenNumber(-8));
+        let events = [
+            WebEvent::PageLoad,
+            WebEvent::KeyPress('∞'),
+            WebEvent::Paste(String::from("my\ntext")),
+            WebEvent::Click { x: -20, y: 80 },
+            WebEvent::PageUnload,
+        ];
+        // The whole list at once, as an array
+        assert_eq!(Vec::<WebEvent>::from_json_str(&events.to_json().to_string()).as_deref(), Ok(&events[..]));
+        events.into_iter().for_each(round_trip);
+
+        let data = export_lesson_data();
+        assert_eq!(parse(&data.to_pretty()), Ok(data));
+    }
+
+    #[test]
+    fn shape_errors() {
+        let shape = |result: Result<Person, JsonError>| match result {
+            Err(JsonError::Shape(message)) => message,
+            other => panic!("expected a shape error, got {:?}", other),
+        };
+
+        assert!(shape(Person::from_json_str(r#"{"name": "Old", "age": 300}"#)).contains("`age`"));
+        assert!(shape(Person::from_json_str(r#"{"name": "Half", "age": 1.5}"#)).contains("whole number"));
+        assert!(shape(Person::from_json_str(r#"{"age": 3}"#)).contains("name"));
+        assert!(matches!(EvenNumber::from_json_str("7"), Err(JsonError::Shape(_))));
+        assert!(matches!(WebEvent::from_json_str(r#"{"type": "KeyPress", "key": "ab"}"#), Err(JsonError::Shape(_))));
+        assert!(matches!(WebEvent::from_json_str(r#"{"type": "Scroll"}"#), Err(JsonError::Shape(_))));
+    }
+}
@@ -0,0 +1,561 @@
+// !!!!!!!!!!!!!!!!!!!! Typestate: a learner's journey !!!!!!!!!!!!!!!!!!!!
+// https://cliffle.com/blog/rust-typestate/
+
+/*
+`Stage` is a value we can only check while the program runs, with a `match`. With "typestate" the stage
+becomes part of the *type* instead: `Learner<Beginner>` and `Learner<Advanced>` are different types, each
+with its own methods, so asking a beginner to do advanced things doesn't even compile.
+
+    - `Beginner` and `Advanced` below are empty structs, they only exist as type parameters
+    - `PhantomData<S>` tells the compiler that `Learner` "uses" `S` without storing one
+    - `promote(self)` takes the learner by value: the beginner is moved (consumed) and an
+      advanced learner comes back, so the old value can't be used anymore
+
+Types don't survive being saved to a file though, so there's also `SavedLearner`: the same rules,
+but checked at runtime and reported as errors.
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::compile_fail::CompileFailCase;
+use crate::{Role, Stage};
+
+// The stages, as types
+pub(crate) struct Beginner;
+pub(crate) struct Advanced;
+
+// Connects each typestate to its runtime `Stage`
+pub(crate) trait LearnerStage {
+    const STAGE: Stage;
+}
+
+impl LearnerStage for Beginner {
+    const STAGE: Stage = Stage::Beginner;
+}
+
+impl LearnerStage for Advanced {
+    const STAGE: Stage = Stage::Advanced;
+}
+
+// A lesson is tagged with the stage it belongs to
+pub(crate) struct Lesson<S: LearnerStage> {
+    pub(crate) title: &'static str,
+    stage: PhantomData<S>,
+}
+
+impl<S: LearnerStage> Lesson<S> {
+    const fn new(title: &'static str) -> Self {
+        Lesson { title, stage: PhantomData }
+    }
+}
+
+pub(crate) const BEGINNER_LESSONS: [Lesson<Beginner>; 4] = [
+    Lesson::new("Variables"),
+    Lesson::new("Structs"),
+    Lesson::new("Enums"),
+    Lesson::new("Flow of control"),
+];
+
+pub(crate) const ADVANCED_LESSONS: [Lesson<Advanced>; 3] = [
+    Lesson::new("Ownership"),
+    Lesson::new("Borrowing"),
+    Lesson::new("Traits"),
+];
+
+pub(crate) struct Learner<S: LearnerStage> {
+    name: String,
+    role: Role,
+    completed: Vec<&'static str>,
+    stage: PhantomData<S>,
+}
+
+// Methods every learner has, whatever the stage
+impl<S: LearnerStage> Learner<S> {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn stage(&self) -> Stage {
+        S::STAGE
+    }
+
+    // A `Learner<S>` can only take a `Lesson<S>`: same `S` on both sides
+    pub(crate) fn take(&mut self, lesson: &Lesson<S>) {
+        if !self.completed.contains(&lesson.title) {
+            self.completed.push(lesson.title);
+        }
+        println!("{} completed \"{}\"", self.name, lesson.title);
+    }
+}
+
+impl Learner<Beginner> {
+    // The only way to create a learner, so everyone starts as a beginner
+    pub(crate) fn new(name: &str) -> Self {
+        Learner { name: name.to_string(), role: Role::Student, completed: Vec::new(), stage: PhantomData }
+    }
+
+    // `self` is taken by value. On success the beginner is gone and an
+    // advanced learner takes its place; on failure we get the beginner back
+    pub(crate) fn promote(self) -> Result<Learner<Advanced>, Learner<Beginner>> {
+        let ready = BEGINNER_LESSONS.iter().all(|lesson| self.completed.contains(&lesson.title));
+
+        if ready {
+            Ok(Learner { name: self.name, role: self.role, completed: self.completed, stage: PhantomData })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Learner<Advanced> {
+    // Only advanced learners can start teaching
+    pub(crate) fn start_teaching(&mut self) {
+        self.role = Role::Teacher;
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! The runtime-checked version !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LearnerError {
+    UnknownLesson(String),
+    // e.g. a beginner taking "Ownership"
+    WrongStage { lesson: String, stage: Stage },
+    NotReadyForPromotion { missing: Vec<&'static str> },
+    AlreadyAdvanced,
+    BeginnersCannotTeach,
+    // A line of a saved file we can't read
+    InvalidLine(String),
+}
+
+impl fmt::Display for LearnerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownLesson(title) => write!(f, "there is no lesson called \"{}\"", title),
+            Self::WrongStage { lesson, stage } => write!(f, "\"{}\" can't be taken at the {:?} stage", lesson, stage),
+            Self::NotReadyForPromotion { missing } => write!(f, "not ready for promotion, missing {:?}", missing),
+            Self::AlreadyAdvanced => write!(f, "the learner is already advanced"),
+            Self::BeginnersCannotTeach => write!(f, "only advanced learners can be teachers"),
+            Self::InvalidLine(line) => write!(f, "invalid line in saved learner: '{}'", line),
+        }
+    }
+}
+
+impl Error for LearnerError {}
+
+// What a learner looks like on disk: the stage is just a field
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SavedLearner {
+    pub(crate) name: String,
+    pub(crate) stage: Stage,
+    pub(crate) role: Role,
+    pub(crate) completed: Vec<String>,
+}
+
+// A learner loaded from disk, back to its typestate form
+pub(crate) enum AnyLearner {
+    Beginner(Learner<Beginner>),
+    Advanced(Learner<Advanced>),
+}
+
+fn lesson_stage(title: &str) -> Option<(&'static str, Stage)> {
+    let beginner = BEGINNER_LESSONS.iter().map(|lesson| (lesson.title, Beginner::STAGE));
+    let advanced = ADVANCED_LESSONS.iter().map(|lesson| (lesson.title, Advanced::STAGE));
+
+    beginner.chain(advanced).find(|(t, _)| *t == title)
+}
+
+impl SavedLearner {
+    // Same checks as `Learner::take`, but at runtime
+    pub(crate) fn take(&mut self, title: &str) -> Result<(), LearnerError> {
+        let (title, stage) = lesson_stage(title).ok_or_else(|| LearnerError::UnknownLesson(title.to_string()))?;
+
+        if stage != self.stage {
+            return Err(LearnerError::WrongStage { lesson: title.to_string(), stage: self.stage });
+        }
+
+        if !self.completed.iter().any(|t| t == title) {
+            self.completed.push(title.to_string());
+        }
+
+        Ok(())
+    }
+
+    // Same checks as `Learner::promote`
+    pub(crate) fn promote(&mut self) -> Result<(), LearnerError> {
+        if self.stage == Stage::Advanced {
+            return Err(LearnerError::AlreadyAdvanced);
+        }
+
+        let missing: Vec<&'static str> = BEGINNER_LESSONS
+            .iter()
+            .map(|lesson| lesson.title)
+            .filter(|title| !self.completed.iter().any(|t| t == title))
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(LearnerError::NotReadyForPromotion { missing });
+        }
+
+        self.stage = Stage::Advanced;
+        Ok(())
+    }
+
+    pub(crate) fn save(&self) -> String {
+        format!(
+            "name={}\nstage={:?}\nrole={:?}\ncompleted={}\n",
+            self.name,
+            self.stage,
+            self.role,
+            self.completed.join(",")
+        )
+    }
+
+    // Loading replays the history through `take` and `promote`, so a file
+    // that was edited by hand can't skip the rules
+    pub(crate) fn load(text: &str) -> Result<Self, LearnerError> {
+        let mut name = None;
+        let mut stage = Stage::Beginner;
+        let mut role = Role::Student;
+        let mut completed = Vec::new();
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || LearnerError::InvalidLine(line.to_string());
+
+            match line.split_once('=').ok_or_else(invalid)? {
+                ("name", value) => name = Some(value.to_string()),
+                ("stage", "Beginner") => stage = Stage::Beginner,
+                ("stage", "Advanced") => stage = Stage::Advanced,
+                ("role", "Student") => role = Role::Student,
+                ("role", "Teacher") => role = Role::Teacher,
+                ("completed", value) => completed = value.split(',').filter(|t| !t.is_empty()).collect(),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let name = name.ok_or_else(|| LearnerError::InvalidLine(String::from("<missing name>")))?;
+        let mut learner = SavedLearner { name, stage: Stage::Beginner, role: Role::Student, completed: Vec::new() };
+
+        // Beginner lessons first, then the promotion, then the advanced ones
+        let (beginner, advanced): (Vec<&str>, Vec<&str>) =
+            completed.into_iter().partition(|title| matches!(lesson_stage(title), Some((_, Stage::Beginner))));
+
+        for title in beginner {
+            learner.take(title)?;
+        }
+
+        if stage == Stage::Advanced {
+            learner.promote()?;
+        }
+
+        for title in advanced {
+            learner.take(title)?;
+        }
+
+        if role == Role::Teacher && learner.stage != Stage::Advanced {
+            return Err(LearnerError::BeginnersCannotTeach);
+        }
+        learner.role = role;
+
+        Ok(learner)
+    }
+
+    // Once the runtime checks passed, we can go back to the typestate version
+    pub(crate) fn resume(self) -> AnyLearner {
+        let completed = self.completed.iter().filter_map(|title| lesson_stage(title)).map(|(t, _)| t).collect();
+
+        match self.stage {
+            Stage::Beginner => AnyLearner::Beginner(Learner {
+                name: self.name,
+                role: self.role,
+                completed,
+                stage: PhantomData,
+            }),
+            Stage::Advanced => AnyLearner::Advanced(Learner {
+                name: self.name,
+                role: self.role,
+                completed,
+                stage: PhantomData,
+            }),
+        }
+    }
+}
+
+impl<S: LearnerStage> From<Learner<S>> for SavedLearner {
+    fn from(learner: Learner<S>) -> Self {
+        SavedLearner {
+            name: learner.name,
+            stage: S::STAGE,
+            role: learner.role,
+            completed: learner.completed.iter().map(|title| title.to_string()).collect(),
+        }
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! What the types reject !!!!!!!!!!!!!!!!!!!!
+
+// The typestate of this file in a few lines, followed by one misuse. Each case
+// is checked by `./main compile-fail`.
+macro_rules! typestate_case {
+    ($misuse:literal) => {
+        concat!(
+            r#"
+use std::marker::PhantomData;
+
+pub struct Beginner;
+pub struct Advanced;
+
+pub struct Lesson<S>(pub &'static str, PhantomData<S>);
+pub struct Learner<S>(Vec<&'static str>, PhantomData<S>);
+
+impl<S> Learner<S> {
+    pub fn take(&mut self, lesson: &Lesson<S>) {
+        self.0.push(lesson.0);
+    }
+}
+
+impl Learner<Beginner> {
+    pub fn promote(self) -> Result<Learner<Advanced>, Learner<Beginner>> {
+        Ok(Learner(self.0, PhantomData))
+    }
+}
+
+impl Learner<Advanced> {
+    pub fn start_teaching(&mut self) {}
+}
+"#,
+            $misuse
+        )
+    };
+}
+
+pub(crate) const BEGINNER_TAKES_ADVANCED_LESSON: CompileFailCase = CompileFailCase {
+    name: "beginner_takes_advanced_lesson",
+    expected_error: "E0308",
+    source: typestate_case!(
+        r#"
+pub fn main() {
+    let mut peter: Learner<Beginner> = Learner(Vec::new(), PhantomData);
+    let ownership: Lesson<Advanced> = Lesson("Ownership", PhantomData);
+    // Error! expected `&Lesson<Beginner>`, found `&Lesson<Advanced>`
+    peter.take(&ownership);
+}
+"#
+    ),
+};
+
+pub(crate) const BEGINNER_TEACHES: CompileFailCase = CompileFailCase {
+    name: "beginner_teaches",
+    expected_error: "E0599",
+    source: typestate_case!(
+        r#"
+pub fn main() {
+    let mut peter: Learner<Beginner> = Learner(Vec::new(), PhantomData);
+    // Error! no method named `start_teaching` found for `Learner<Beginner>`
+    peter.start_teaching();
+}
+"#
+    ),
+};
+
+pub(crate) const USED_AFTER_PROMOTION: CompileFailCase = CompileFailCase {
+    name: "used_after_promotion",
+    expected_error: "E0382",
+    source: typestate_case!(
+        r#"
+pub fn main() {
+    let mut peter: Learner<Beginner> = Learner(Vec::new(), PhantomData);
+    let _advanced = peter.promote();
+    // Error! borrow of moved value: `peter`
+    peter.take(&Lesson("Variables", PhantomData));
+}
+"#
+    ),
+};
+
+pub(crate) const COMPILE_FAIL_CASES: [&CompileFailCase; 3] =
+    [&BEGINNER_TAKES_ADVANCED_LESSON, &BEGINNER_TEACHES, &USED_AFTER_PROMOTION];
+
+pub(crate) fn learner_demo() {
+    let mut peter = Learner::new("Peter");
+
+    peter.take(&BEGINNER_LESSONS[0]);
+
+    // A `Learner<Beginner>` can't take a `Lesson<Advanced>`, and
+    // `start_teaching` only exists on `Learner<Advanced>`: see
+    // `BEGINNER_TAKES_ADVANCED_LESSON` and `BEGINNER_TEACHES` above
+
+    // Not every beginner lesson is done yet, so `promote` gives `peter` back
+    let mut peter = match peter.promote() {
+        Ok(_) => unreachable!("only one lesson was taken"),
+        Err(peter) => peter,
+    };
+
+    for lesson in &BEGINNER_LESSONS {
+        peter.take(lesson);
+    }
+
+    let Ok(mut advanced_peter) = peter.promote() else {
+        panic!("every beginner lesson was taken");
+    };
+
+    // `peter` was moved into `promote`, it doesn't exist anymore: see
+    // `USED_AFTER_PROMOTION` above
+
+    println!("{} is now at the {:?} stage", advanced_peter.name(), advanced_peter.stage());
+    advanced_peter.take(&ADVANCED_LESSONS[0]);
+    advanced_peter.start_teaching();
+
+    // Saving throws the types away, loading checks the rules again
+    let saved = SavedLearner::from(advanced_peter).save();
+    print!("{}", saved);
+
+    match SavedLearner::load(&saved).map(SavedLearner::resume) {
+        Ok(AnyLearner::Advanced(learner)) => println!("{} resumed as an advanced learner", learner.name()),
+        Ok(AnyLearner::Beginner(learner)) => println!("{} resumed as a beginner", learner.name()),
+        Err(e) => println!("error: {}", e),
+    }
+
+    // The same mistakes as above, but they can only be caught at runtime
+    let mut ekrem = SavedLearner {
+        name: String::from("Ekrem"),
+        stage: Stage::Beginner,
+        role: Role::Student,
+        completed: Vec::new(),
+    };
+
+    for result in [ekrem.take("Ownership"), ekrem.take("Cooking"), ekrem.promote()] {
+        if let Err(e) = result {
+            println!("error: {}", e);
+        }
+    }
+
+    if let Err(e) = SavedLearner::load("name=Ekrem\nstage=Advanced\ncompleted=Variables\n") {
+        println!("error: {}", e);
+    }
+
+    println!(
+        "{} cases in this chapter must fail to compile, run `./main compile-fail` to check them",
+        COMPILE_FAIL_CASES.len()
+    );
+    for case in COMPILE_FAIL_CASES {
+        println!("    {} ({})", case.name, case.expected_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::Outcome;
+
+    fn beginner(completed: &[&str]) -> SavedLearner {
+        SavedLearner {
+            name: String::from("Ekrem"),
+            stage: Stage::Beginner,
+            role: Role::Student,
+            completed: completed.iter().map(|title| title.to_string()).collect(),
+        }
+    }
+
+    const ALL_BEGINNER_LESSONS: [&str; 4] = ["Variables", "Structs", "Enums", "Flow of control"];
+
+    #[test]
+    fn runtime_rules() {
+        let mut ekrem = beginner(&[]);
+        assert_eq!(
+            ekrem.take("Ownership"),
+            Err(LearnerError::WrongStage { lesson: String::from("Ownership"), stage: Stage::Beginner })
+        );
+        assert_eq!(ekrem.take("Cooking"), Err(LearnerError::UnknownLesson(String::from("Cooking"))));
+        assert!(matches!(ekrem.promote(), Err(LearnerError::NotReadyForPromotion { missing }) if missing.len() == 4));
+
+        // Taking a lesson twice only counts once
+        for title in ALL_BEGINNER_LESSONS.iter().chain(&["Variables"]) {
+            ekrem.take(title).unwrap();
+        }
+        assert_eq!(ekrem.completed, ALL_BEGINNER_LESSONS);
+
+        assert_eq!(ekrem.promote(), Ok(()));
+        assert_eq!(ekrem.promote(), Err(LearnerError::AlreadyAdvanced));
+        assert_eq!(ekrem.take("Ownership"), Ok(()));
+        assert!(matches!(ekrem.take("Variables"), Err(LearnerError::WrongStage { .. })));
+    }
+
+    #[test]
+    fn valid_histories_round_trip() {
+        let mut ekrem = beginner(&ALL_BEGINNER_LESSONS);
+        ekrem.promote().unwrap();
+        ekrem.take("Borrowing").unwrap();
+        ekrem.role = Role::Teacher;
+
+        let saved = ekrem.save();
+        assert_eq!(
+            saved,
+            "name=Ekrem\nstage=Advanced\nrole=Teacher\ncompleted=Variables,Structs,Enums,Flow of control,Borrowing\n"
+        );
+        assert_eq!(SavedLearner::load(&saved), Ok(ekrem));
+
+        let peter = beginner(&["Structs"]);
+        assert_eq!(SavedLearner::load(&peter.save()), Ok(peter.clone()));
+        assert!(matches!(peter.resume(), AnyLearner::Beginner(learner) if learner.completed == ["Structs"]));
+
+        // The order in the file doesn't matter: beginner lessons are replayed first
+        let shuffled = "name=Ekrem\ncompleted=Traits,Variables,Structs,Enums,Flow of control\nstage=Advanced\n";
+        assert!(matches!(SavedLearner::load(shuffled).map(SavedLearner::resume), Ok(AnyLearner::Advanced(_))));
+    }
+
+    #[test]
+    fn invalid_histories_are_rejected() {
+        let load = |text: &str| SavedLearner::load(text).unwrap_err();
+
+        // Promoted without the beginner lessons
+        assert!(matches!(
+            load("name=Ekrem\nstage=Advanced\ncompleted=Variables\n"),
+            LearnerError::NotReadyForPromotion { missing } if missing == ["Structs", "Enums", "Flow of control"]
+        ));
+        // An advanced lesson, but still a beginner
+        assert_eq!(
+            load("name=Ekrem\ncompleted=Variables,Ownership\n"),
+            LearnerError::WrongStage { lesson: String::from("Ownership"), stage: Stage::Beginner }
+        );
+        assert_eq!(load("name=Ekrem\nrole=Teacher\n"), LearnerError::BeginnersCannotTeach);
+        assert_eq!(load("name=Ekrem\ncompleted=Cooking\n"), LearnerError::UnknownLesson(String::from("Cooking")));
+
+        // Records we don't know
+        assert_eq!(load("name=Ekrem\nfavourite=Rust\n"), LearnerError::InvalidLine(String::from("favourite=Rust")));
+        assert_eq!(load("name=Ekrem\nstage=Expert\n"), LearnerError::InvalidLine(String::from("stage=Expert")));
+        assert_eq!(load("name Ekrem\n"), LearnerError::InvalidLine(String::from("name Ekrem")));
+        assert_eq!(load("stage=Beginner\n"), LearnerError::InvalidLine(String::from("<missing name>")));
+    }
+
+    // Needs `rustc`: the typestate compiles when used right, and each case doesn't
+    #[test]
+    fn cases_fail_to_compile() {
+        let fine = CompileFailCase {
+            name: "typestate_used_right",
+            expected_error: "E0308",
+            source: typestate_case!(
+                r#"
+pub fn main() {
+    let mut peter: Learner<Beginner> = Learner(Vec::new(), PhantomData);
+    peter.take(&Lesson("Variables", PhantomData));
+    if let Ok(mut advanced) = peter.promote() {
+        advanced.take(&Lesson("Ownership", PhantomData));
+        advanced.start_teaching();
+    }
+}
+"#
+            ),
+        };
+        match fine.check().unwrap() {
+            Outcome::Skipped => return,
+            outcome => assert_eq!(outcome, Outcome::Compiled),
+        }
+
+        for case in COMPILE_FAIL_CASES {
+            assert_eq!(case.check().unwrap(), Outcome::FailedAsExpected, "{}", case.name);
+        }
+    }
+}
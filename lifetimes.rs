@@ -0,0 +1,489 @@
+// !!!!!!!!!!!!!!!!!!!! Lifetimes https://doc.rust-lang.org/rust-by-example/scope/lifetime.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+A reference must never outlive the data it points to. Most of the time the compiler works that out on its own
+("lifetime elision"), but when a function returns a reference, or a struct stores one, we sometimes have to say
+where the reference comes from. `'a` is a name for "as long as some borrowed data lives":
+
+    fn longest<'a>(x: &'a str, y: &'a str) -> &'a str
+
+reads: "the result borrows from `x` or `y`, so it can only be used while both of them are alive".
+
+Lifetime annotations don't change how long anything lives. They only describe it, so the borrow checker can
+reject code where a reference would dangle.
+*/
+
+use crate::compile_fail::CompileFailCase;
+use crate::Person;
+
+// !!!!!!!!!!!!!!!!!!!! Functions !!!!!!!!!!!!!!!!!!!!
+
+// The result may be `x` or `y`, so it must not outlive either of them
+pub(crate) fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() >= y.len() {
+        x
+    } else {
+        y
+    }
+}
+
+// Elision: one reference in, references out, so they all get the same
+// lifetime. Written out, it's `fn split_first_word<'a>(s: &'a str) -> (&'a str, &'a str)`
+pub(crate) fn split_first_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+
+    match s.find(char::is_whitespace) {
+        Some(end) => (&s[..end], s[end..].trim_start()),
+        None => (s, ""),
+    }
+}
+
+// Two lifetime parameters: the results borrow from `text` only, never from
+// `pattern`, so `pattern` can be dropped while we still use them.
+// (`'p` could be elided, it's spelled out here on purpose)
+#[allow(clippy::needless_lifetimes)]
+pub(crate) fn find_all<'t, 'p>(text: &'t str, pattern: &'p str) -> Vec<&'t str> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    text.match_indices(pattern).map(|(start, found)| &text[start..start + found.len()]).collect()
+}
+
+// The lines of `text` containing `pattern`. The iterator keeps borrowing
+// both of them while it runs, `use<'t, 'p>` says so
+pub(crate) fn grep<'t, 'p>(text: &'t str, pattern: &'p str) -> impl Iterator<Item = &'t str> + use<'t, 'p> {
+    text.lines().filter(move |line| line.contains(pattern))
+}
+
+// !!!!!!!!!!!!!!!!!!!! A zero-copy tokenizer !!!!!!!!!!!!!!!!!!!!
+
+/*
+"Zero-copy": the tokens don't own a `String`, they borrow slices of the input. Creating a token never allocates,
+but in exchange a `Token<'a>` can't outlive the `&'a str` it was read from.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TokenKind {
+    Word,
+    Number,
+    // The text between the quotes, without them
+    Quoted,
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    // Byte offset of the token in the input
+    pub(crate) offset: usize,
+}
+
+pub(crate) struct Tokenizer<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Tokenizer { input, offset: 0 }
+    }
+
+    // Everything that's left, as a slice of the input
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    // Takes characters while `f` says so and returns them as one slice
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !f(c)).unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+}
+
+// `Item = Token<'a>`: the tokens borrow from the input, not from the
+// tokenizer, so they can outlive the tokenizer itself
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        self.take_while(char::is_whitespace);
+
+        let start = self.offset;
+        let first = self.rest().chars().next()?;
+
+        let (kind, text) = if first.is_alphabetic() || first == '_' {
+            (TokenKind::Word, self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '\''))
+        } else if first.is_ascii_digit() {
+            (TokenKind::Number, self.take_while(|c| c.is_ascii_digit() || c == '.' || c == '_'))
+        } else if first == '"' {
+            self.offset += 1;
+            let text = self.take_while(|c| c != '"');
+            // Skip the closing quote, if there is one
+            self.offset += usize::from(self.rest().starts_with('"'));
+            (TokenKind::Quoted, text)
+        } else {
+            self.offset += first.len_utf8();
+            (TokenKind::Symbol, &self.input[start..self.offset])
+        };
+
+        Some(Token { kind, text, offset: start })
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Structs holding references !!!!!!!!!!!!!!!!!!!!
+
+// Like `Person`, but the name is borrowed. A `PersonRef<'a>` can't outlive
+// the text its name points into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PersonRef<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) age: u8,
+}
+
+impl<'a> PersonRef<'a> {
+    // "Peter,27" -> PersonRef { name: "Peter", age: 27 }, without allocating
+    pub(crate) fn parse(line: &'a str) -> Option<PersonRef<'a>> {
+        let (name, age) = line.rsplit_once(',')?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(PersonRef { name, age: age.trim().parse().ok()? })
+    }
+
+    // When the data has to outlive the text, copy it into an owned `Person`
+    pub(crate) fn to_person(self) -> Person {
+        Person { name: self.name.to_string(), age: self.age }
+    }
+}
+
+// Elided again: every `PersonRef` borrows from `text`
+pub(crate) fn parse_people(text: &str) -> Vec<PersonRef<'_>> {
+    text.lines().filter_map(PersonRef::parse).collect()
+}
+
+// Methods can return references tied to `self`'s borrowed data (`'a`) rather
+// than to `self` itself, so the result outlives the `PersonRef`
+impl<'a> PersonRef<'a> {
+    pub(crate) fn first_name(&self) -> &'a str {
+        split_first_word(self.name).0
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! What doesn't compile !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) const DANGLING_REFERENCE: CompileFailCase = CompileFailCase {
+    name: "dangling_reference",
+    expected_error: "E0597",
+    source: r#"
+pub fn main() {
+    let r;
+    {
+        let x = 5;
+        r = &x;
+        // Error! `x` does not live long enough
+    }
+    println!("r: {}", r);
+}
+"#,
+};
+
+pub(crate) const RETURN_LOCAL_REFERENCE: CompileFailCase = CompileFailCase {
+    name: "return_local_reference",
+    expected_error: "E0515",
+    source: r#"
+// Error! cannot return reference to local variable `s`
+pub fn dangle<'a>() -> &'a String {
+    let s = String::from("hello");
+    &s
+}
+"#,
+};
+
+pub(crate) const MISSING_LIFETIME: CompileFailCase = CompileFailCase {
+    name: "missing_lifetime",
+    expected_error: "E0106",
+    source: r#"
+// Error! missing lifetime specifier: does the result borrow from `x` or `y`?
+pub fn longest(x: &str, y: &str) -> &str {
+    if x.len() >= y.len() { x } else { y }
+}
+"#,
+};
+
+pub(crate) const LONGEST_OUTLIVES_INPUT: CompileFailCase = CompileFailCase {
+    name: "longest_outlives_input",
+    expected_error: "E0597",
+    source: r#"
+fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() >= y.len() { x } else { y }
+}
+
+pub fn main() {
+    let string1 = String::from("long string is long");
+    let result;
+    {
+        let string2 = String::from("xyz");
+        // Error! `string2` does not live long enough
+        result = longest(string1.as_str(), string2.as_str());
+    }
+    println!("The longest string is {}", result);
+}
+"#,
+};
+
+pub(crate) const ONE_LIFETIME_TOO_FEW: CompileFailCase = CompileFailCase {
+    name: "one_lifetime_too_few",
+    expected_error: "E0597",
+    source: r#"
+// Same as `find_all`, but with a single lifetime for both arguments
+fn find_all<'a>(text: &'a str, pattern: &'a str) -> Vec<&'a str> {
+    text.matches(pattern).collect()
+}
+
+pub fn main() {
+    let text = String::from("a cat and a cat");
+    let found;
+    {
+        let pattern = String::from("cat");
+        // Error! `pattern` does not live long enough, although the results
+        // only point into `text`
+        found = find_all(&text, &pattern);
+    }
+    println!("{:?}", found);
+}
+"#,
+};
+
+pub(crate) const TOKEN_OUTLIVES_INPUT: CompileFailCase = CompileFailCase {
+    name: "token_outlives_input",
+    expected_error: "E0597",
+    source: r#"
+struct Token<'a> {
+    text: &'a str,
+}
+
+fn first_token(input: &str) -> Option<Token<'_>> {
+    input.split_whitespace().next().map(|text| Token { text })
+}
+
+pub fn main() {
+    let token;
+    {
+        let input = String::from("let x = 5;");
+        // Error! `input` does not live long enough
+        token = first_token(&input);
+    }
+    println!("{}", token.map_or("", |t| t.text));
+}
+"#,
+};
+
+pub(crate) const COMPILE_FAIL_CASES: [&CompileFailCase; 6] = [
+    &DANGLING_REFERENCE,
+    &RETURN_LOCAL_REFERENCE,
+    &MISSING_LIFETIME,
+    &LONGEST_OUTLIVES_INPUT,
+    &ONE_LIFETIME_TOO_FEW,
+    &TOKEN_OUTLIVES_INPUT,
+];
+
+pub(crate) fn lifetimes_demo() {
+    let string1 = String::from("long string is long");
+    {
+        let string2 = String::from("xyz");
+        let result = longest(string1.as_str(), string2.as_str());
+        println!("The longest string is {}", result);
+    }
+
+    let (first, rest) = split_first_word("  hello lifetime world");
+    println!("first word: '{}', rest: '{}'", first, rest);
+
+    // `pattern` is dropped before we use what `find_all` returned, which is
+    // fine: the results only borrow from `text`
+    let text = String::from("a cat, a dog and a cat");
+    let found = {
+        let pattern = String::from("cat");
+        find_all(&text, &pattern)
+    };
+    println!("found {:?}", found);
+
+    let source = "let count = 3_000;\nprintln!(\"{}\", count);";
+    let tokens: Vec<Token> = Tokenizer::new(source).collect();
+    for token in &tokens {
+        println!("{:>3} {:?} {:?}", token.offset, token.kind, token.text);
+    }
+
+    // Every token points into `source`: no text was copied
+    let range = source.as_bytes().as_ptr_range();
+    println!("every token points into the source: {}", tokens.iter().all(|token| range.contains(&token.text.as_ptr())));
+
+    for line in grep(source, "count") {
+        println!("grep: {}", line);
+    }
+
+    let people_text = String::from("Peter Parker,27\nEkrem,30\nnot a person\nJohn Doe,41");
+    let people = parse_people(&people_text);
+    println!("{:?}", people);
+    println!("first names: {:?}", people.iter().map(PersonRef::first_name).collect::<Vec<_>>());
+
+    // To keep a person after `people_text` is gone, it has to own its name
+    let peter = people[0].to_person();
+    drop(people);
+    drop(people_text);
+    println!("{}", peter);
+
+    println!(
+        "{} cases in this chapter must fail to compile, run `./main compile-fail` to check them",
+        COMPILE_FAIL_CASES.len()
+    );
+    for case in COMPILE_FAIL_CASES {
+        println!("    {} ({})", case.name, case.expected_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::Outcome;
+
+    fn tokens(input: &str) -> Vec<(TokenKind, &str, usize)> {
+        Tokenizer::new(input).map(|token| (token.kind, token.text, token.offset)).collect()
+    }
+
+    // `inner` is a slice of `outer`'s memory, not a copy
+    fn borrows_from(inner: &str, outer: &str) -> bool {
+        let outer = outer.as_bytes().as_ptr_range();
+        let inner = inner.as_bytes().as_ptr_range();
+        outer.start <= inner.start && inner.end <= outer.end
+    }
+
+    #[test]
+    fn longest_and_first_words() {
+        assert_eq!(longest("long string is long", "xyz"), "long string is long");
+        assert_eq!(longest("ab", "cd"), "ab");
+        assert_eq!(longest("", "a"), "a");
+
+        assert_eq!(split_first_word("  hello lifetime world"), ("hello", "lifetime world"));
+        assert_eq!(split_first_word("alone"), ("alone", ""));
+        assert_eq!(split_first_word("tab\tseparated"), ("tab", "separated"));
+        assert_eq!(split_first_word(""), ("", ""));
+        assert_eq!(split_first_word("   "), ("", ""));
+    }
+
+    #[test]
+    fn searching() {
+        let text = String::from("a cat, a dog and a cat");
+        let found = {
+            let pattern = String::from("cat");
+            find_all(&text, &pattern)
+        };
+        assert_eq!(found, ["cat", "cat"]);
+        assert!(found.iter().all(|cat| borrows_from(cat, &text)));
+        assert_eq!(find_all(&text, "cow"), Vec::<&str>::new());
+        assert_eq!(find_all(&text, ""), Vec::<&str>::new());
+        assert_eq!(find_all("aaaa", "aa"), ["aa", "aa"]);
+
+        let source = "let count = 3;\nprintln!(\"{}\", count);\nlet other = 4;";
+        assert_eq!(grep(source, "count").collect::<Vec<_>>(), ["let count = 3;", "println!(\"{}\", count);"]);
+        assert_eq!(grep(source, "missing").count(), 0);
+        assert_eq!(grep("", "a").count(), 0);
+    }
+
+    #[test]
+    fn tokens_borrow_from_the_input() {
+        let source = String::from("let count = 3_000;\nprintln!(\"{}\", count);");
+        let tokens: Vec<Token> = Tokenizer::new(&source).collect();
+        assert_eq!(tokens.len(), 13);
+        for token in &tokens {
+            assert!(borrows_from(token.text, &source), "{:?}", token);
+            // The offset says where: the text is right there in the input,
+            // after the opening quote for a quoted token
+            let start = token.offset + usize::from(token.kind == TokenKind::Quoted);
+            assert_eq!(token.text.as_ptr(), source[start..].as_ptr());
+        }
+    }
+
+    #[test]
+    fn token_kinds() {
+        use TokenKind::*;
+
+        assert_eq!(
+            tokens("let x_1 = 3_000.5 + don't;"),
+            [
+                (Word, "let", 0),
+                (Word, "x_1", 4),
+                (Symbol, "=", 8),
+                (Number, "3_000.5", 10),
+                (Symbol, "+", 18),
+                (Word, "don't", 20),
+                (Symbol, ";", 25)
+            ]
+        );
+        // Multi-byte characters are one symbol, and offsets count bytes
+        assert_eq!(tokens("→ é"), [(Symbol, "→", 0), (Word, "é", 4)]);
+        assert_eq!(tokens("say \"hi there\"!"), [(Word, "say", 0), (Quoted, "hi there", 4), (Symbol, "!", 14)]);
+    }
+
+    #[test]
+    fn edge_inputs() {
+        assert_eq!(tokens(""), []);
+        assert_eq!(tokens(" \t\n "), []);
+        assert_eq!(tokens("\"\""), [(TokenKind::Quoted, "", 0)]);
+        // An unterminated quote runs to the end of the input
+        assert_eq!(tokens("a \"never closed"), [(TokenKind::Word, "a", 0), (TokenKind::Quoted, "never closed", 2)]);
+        assert_eq!(tokens("\""), [(TokenKind::Quoted, "", 0)]);
+    }
+
+    #[test]
+    fn people_borrow_their_names() {
+        let text = String::from("Peter Parker,27\n  Ekrem , 30 \nnot a person\n,5\nJohn,300\nJohn Doe,41");
+        let people = parse_people(&text);
+        assert_eq!(
+            people,
+            [
+                PersonRef { name: "Peter Parker", age: 27 },
+                PersonRef { name: "Ekrem", age: 30 },
+                PersonRef { name: "John Doe", age: 41 },
+            ]
+        );
+        assert!(people.iter().all(|person| borrows_from(person.name, &text)));
+        assert_eq!(people[0].first_name(), "Peter");
+
+        assert_eq!(PersonRef::parse("Smith, John,41").map(|p| p.name), Some("Smith, John"));
+        assert_eq!(PersonRef::parse(""), None);
+        assert_eq!(PersonRef::parse("Peter,"), None);
+
+        let peter = people[0].to_person();
+        drop(people);
+        drop(text);
+        assert_eq!(peter, Person { name: String::from("Peter Parker"), age: 27 });
+    }
+
+    // `MISSING_LIFETIME` with the lifetime it was missing
+    const WITH_LIFETIME: &str = r#"
+pub fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() >= y.len() { x } else { y }
+}
+"#;
+
+    // Needs `rustc`: each case must fail, and with the error it's about
+    #[test]
+    fn cases_fail_to_compile() {
+        let fixed = CompileFailCase { name: "with_lifetime", expected_error: "E0106", source: WITH_LIFETIME };
+        match fixed.check().unwrap() {
+            Outcome::Skipped => return,
+            outcome => assert_eq!(outcome, Outcome::Compiled),
+        }
+
+        for case in COMPILE_FAIL_CASES {
+            assert_eq!(case.check().unwrap(), Outcome::FailedAsExpected, "{}", case.name);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+// !!!!!!!!!!!!!!!!!!!! macro_rules! https://doc.rust-lang.org/rust-by-example/macros.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+Macros look like functions, except that their name ends with a bang `!`. Instead of being called, a macro is
+expanded: the compiler replaces the call with the code the macro produces, before type checking. That's how
+`println!` takes any number of arguments, and how `vec![1, 2, 3]` can exist although Rust has no variadic
+functions.
+
+A `macro_rules!` macro is a list of rules `(pattern) => { expansion }`. The first rule whose pattern matches the
+tokens of the call wins. Patterns capture pieces of code with "designators":
+
+    $x:expr   an expression        $name:ident  an identifier (a variable or function name)
+    $t:ty     a type               $l:literal   a literal, like `3` or "fizz"
+    $b:block  a `{ ... }` block    $t:tt        any single token (or a whole `(...)` group)
+*/
+
+use std::collections::HashMap;
+
+use crate::is_divisible_by;
+use crate::iterators::fizzbuzz_word;
+
+// !!!!!!!!!!!!!!!!!!!! Designators !!!!!!!!!!!!!!!!!!!!
+
+// The simplest macro: no arguments
+macro_rules! say_hello {
+    () => {
+        println!("Hello from a macro!")
+    };
+}
+
+// `ident` lets a macro define a function with a name chosen by the caller
+macro_rules! create_function {
+    ($func_name:ident) => {
+        fn $func_name() {
+            // `stringify!` turns the identifier back into a `&str`
+            println!("You called {:?}()", stringify!($func_name));
+        }
+    };
+}
+
+create_function!(foo);
+create_function!(bar);
+
+// `expr` captures a whole expression, which `stringify!` can print as written
+macro_rules! print_result {
+    ($expression:expr) => {
+        println!("{:?} = {:?}", stringify!($expression), $expression)
+    };
+}
+
+// !!!!!!!!!!!!!!!!!!!! Repetition !!!!!!!!!!!!!!!!!!!!
+
+/*
+`$(...),*` matches the pattern inside zero or more times, separated by commas (`+` means one or more, `?` zero or
+one). In the expansion, `$(...)*` repeats its content once for every match.
+*/
+
+// `print_result!` for any number of expressions: one `println!` per expression
+macro_rules! print_all {
+    ($($expression:expr),* $(,)?) => {
+        $(println!("{:?} = {:?}", stringify!($expression), $expression);)*
+    };
+}
+
+// !!!!!!!!!!!!!!!!!!!! Recursion !!!!!!!!!!!!!!!!!!!!
+
+// A macro can call itself. `find_min!(a, b, c)` becomes
+// `a.min(find_min!(b, c))`, then `a.min(b.min(find_min!(c)))`, then
+// `a.min(b.min(c))`
+macro_rules! find_min {
+    // Base case
+    ($x:expr) => ($x);
+    // `$x` followed by at least one more `$y`
+    ($x:expr, $($y:expr),+) => (
+        std::cmp::min($x, find_min!($($y),+))
+    );
+}
+
+// Counts its arguments at compile time: `count!(a b c)` is `1 + 1 + 1 + 0`.
+// The result is a constant expression, usable where a constant is expected.
+macro_rules! count {
+    () => (0usize);
+    ($head:tt $($tail:tt)*) => (1usize + count!($($tail)*));
+}
+
+// !!!!!!!!!!!!!!!!!!!! Hygiene !!!!!!!!!!!!!!!!!!!!
+
+/*
+Variables created inside a macro can't be seen, or overwritten, by the code around the call: `square_twice!`
+uses a `tmp` of its own, and the caller's `tmp` is left untouched. To share a variable, the caller has to pass
+its name in, like `with_name!` does.
+*/
+
+macro_rules! square_twice {
+    ($x:expr) => {{
+        let tmp = $x * $x;
+        tmp * tmp
+    }};
+}
+
+// The caller chooses the name, so the caller can use the variable
+macro_rules! with_name {
+    ($name:ident = $value:expr; $body:block) => {{
+        let $name = $value;
+        $body
+    }};
+}
+
+// !!!!!!!!!!!!!!!!!!!! A hashmap! literal !!!!!!!!!!!!!!!!!!!!
+
+// `hashmap!{ "a" => 1, "b" => 2 }`. `count!` gives the number of pairs, so
+// the map is created with the right capacity and never grows. `$key` is
+// used once by `count!` (as a `tt`) and once in the `insert`.
+macro_rules! hashmap {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        // `hashmap! {}` never inserts
+        #[allow(unused_mut)]
+        let mut map = HashMap::with_capacity(count!($(($key))*));
+        $(map.insert($key, $value);)*
+        map
+    }};
+}
+
+// !!!!!!!!!!!!!!!!!!!! A small language: fizzbuzz rules !!!!!!!!!!!!!!!!!!!!
+
+/*
+A macro's input doesn't have to look like Rust, as long as it's made of Rust tokens. `fizzbuzz_rules!` reads a
+list of "divisor => word" rules and writes the function for us:
+
+    fizzbuzz_rules! {
+        fn fizzbuzz_classic {
+            3 => "fizz",
+            5 => "buzz",
+        }
+    }
+
+Every rule whose divisor divides `n` adds its word, in order. No word at all means the number is printed.
+*/
+macro_rules! fizzbuzz_rules {
+    ($(fn $name:ident { $($divisor:literal => $word:literal),+ $(,)? })+) => {
+        $(
+            fn $name(n: u32) -> String {
+                let mut out = String::new();
+                $(
+                    if is_divisible_by(n, $divisor) {
+                        out.push_str($word);
+                    }
+                )+
+                if out.is_empty() {
+                    out = n.to_string();
+                }
+                out
+            }
+        )+
+    };
+}
+
+fizzbuzz_rules! {
+    fn fizzbuzz_classic {
+        3 => "fizz",
+        5 => "buzz",
+    }
+
+    fn fizzbuzz_bazz {
+        3 => "fizz",
+        5 => "buzz",
+        7 => "bazz",
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! lesson! !!!!!!!!!!!!!!!!!!!!
+
+/*
+Every lesson living in its own file has an id, a title, a short description and a function running it. Instead
+of writing the struct out each time, the list at the bottom of main.rs is made of `lesson!` calls:
+
+    lesson! {
+        closures::closures_demo,
+        title: "Closures",
+        body: "Fn, FnMut and FnOnce, ...",
+    }
+
+The id is the module's name, so it can't disagree with the file the lesson lives in. It also finds the file:
+`include_str!` pastes the text of `closures.rs` into the binary, for `./main serve` to show.
+*/
+
+// One entry of the registry, see `LESSONS` in main.rs
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LessonEntry {
+    pub(crate) id: &'static str,
+    pub(crate) title: &'static str,
+    pub(crate) body: &'static str,
+    pub(crate) run: fn(),
+    // The whole file the lesson lives in
+    pub(crate) source: &'static str,
+}
+
+// `$crate` always means this crate, wherever the macro is expanded, so the
+// path to `LessonEntry` works from any module
+macro_rules! lesson {
+    ($module:ident :: $demo:ident, title: $title:literal, body: $body:literal $(,)?) => {
+        $crate::macros::LessonEntry {
+            id: stringify!($module),
+            title: $title,
+            body: $body,
+            run: $module::$demo,
+            source: include_str!(concat!(stringify!($module), ".rs")),
+        }
+    };
+}
+
+// Macros defined with `macro_rules!` can only be used below their definition,
+// in the same file. `use` makes `lesson!` importable from other modules, like
+// any function: `macros::lesson!`
+pub(crate) use lesson;
+
+// Finds a lesson by id
+pub(crate) fn find_lesson<'a>(lessons: &'a [LessonEntry], id: &str) -> Option<&'a LessonEntry> {
+    lessons.iter().find(|lesson| lesson.id == id)
+}
+
+// `./main lessons`: lists the registry
+pub(crate) fn lessons_main(lessons: &[LessonEntry]) {
+    for lesson in lessons {
+        println!("{:<12} {}", lesson.id, lesson.title);
+        println!("{:<12} {}", "", lesson.body);
+    }
+}
+
+// `./main run <id>`: runs a single lesson
+pub(crate) fn run_main(lessons: &[LessonEntry], args: &[String]) {
+    match args.first().and_then(|id| find_lesson(lessons, id)) {
+        Some(lesson) => (lesson.run)(),
+        None => {
+            eprintln!("usage: ./main run <lesson>, see `./main lessons` for the ids");
+            std::process::exit(2);
+        }
+    }
+}
+
+pub(crate) fn macros_demo() {
+    say_hello!();
+
+    foo();
+    bar();
+
+    print_result!(1u32 + 1);
+    // Blocks are expressions too
+    print_result!({
+        let x = 1u32;
+        x * x + 2 * x - 1
+    });
+
+    print_all!(1 + 2, "a".repeat(3), [1, 2, 3].len(),);
+
+    println!("find_min!(5, 2 * 3, 4) = {}", find_min!(5, 2 * 3, 4));
+
+    // `count!` runs at compile time, so it can size an array
+    const ARGS: usize = count!(a b c d);
+    let array = [0u8; ARGS];
+    println!("count!(a b c d) = {}", array.len());
+
+    let tmp = 3;
+    let result = square_twice!(tmp + 1);
+    // The `tmp` inside the macro was a different variable
+    println!("square_twice!(tmp + 1) = {}, and tmp is still {}", result, tmp);
+
+    let doubled = with_name!(x = 21; { x * 2 });
+    println!("with_name!(x = 21; {{ x * 2 }}) = {}", doubled);
+
+    let ages = hashmap! {
+        "Ekrem" => 30,
+        "Peter" => 27,
+    };
+    println!("{} ages, Peter is {}", ages.len(), ages["Peter"]);
+
+    // The classic rules give the same words as the hand-written fizzbuzz
+    let same = (1..=100).all(|n| fizzbuzz_classic(n) == fizzbuzz_word(n).to_string());
+    println!("fizzbuzz_classic agrees with fizzbuzz_word up to 100: {}", same);
+    let words: Vec<String> = (100..=106).map(fizzbuzz_bazz).collect();
+    println!("with bazz: {}", words.join(" "));
+
+    println!("Run `./main lessons` to list the lessons declared with `lesson!`");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros;
+
+    #[test]
+    fn hashmap_literals() {
+        let empty: HashMap<&str, u32> = hashmap! {};
+        assert!(empty.is_empty());
+
+        let ages = hashmap! {
+            "Ekrem" => 30,
+            "Peter" => 27,
+        };
+        assert_eq!(ages.len(), 2);
+        assert_eq!((ages["Ekrem"], ages["Peter"]), (30, 27));
+        assert!(ages.capacity() >= 2);
+
+        // Without the trailing comma, and with a key given twice: the last one wins
+        let keys = hashmap! { 1 => 'a', 2 => 'b', 1 => 'c' };
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[&1], 'c');
+    }
+
+    #[test]
+    fn recursion_and_repetition() {
+        assert_eq!(find_min!(7), 7);
+        assert_eq!(find_min!(3, 1, 2), 1);
+        assert_eq!(find_min!(5, 2 * 3, 4, -1 + 2), 1);
+
+        assert_eq!(count!(), 0);
+        assert_eq!(count!(a), 1);
+        assert_eq!(count!(a b c d), 4);
+        // A bracketed group is a single `tt`
+        assert_eq!(count!((a b) [c] { d e f }), 3);
+        const ARGS: usize = count!(x y z);
+        assert_eq!([0u8; ARGS].len(), 3);
+    }
+
+    #[test]
+    fn hygiene() {
+        let tmp = 3;
+        assert_eq!(square_twice!(tmp + 1), 256);
+        assert_eq!(tmp, 3);
+
+        assert_eq!(with_name!(x = 21; { x * 2 }), 42);
+        let word = with_name!(greeting = String::from("hi"); { greeting + "!" });
+        assert_eq!(word, "hi!");
+    }
+
+    #[test]
+    fn fizzbuzz_dsl() {
+        for n in 1..=100 {
+            assert_eq!(fizzbuzz_classic(n), fizzbuzz_word(n).to_string());
+        }
+
+        let words: Vec<String> = (100..=106).map(fizzbuzz_bazz).collect();
+        assert_eq!(words, ["buzz", "101", "fizz", "103", "104", "fizzbuzzbazz", "106"]);
+        assert_eq!(fizzbuzz_bazz(21), "fizzbazz");
+        assert_eq!(fizzbuzz_bazz(35), "buzzbazz");
+        assert_eq!(fizzbuzz_bazz(0), "fizzbuzzbazz");
+    }
+
+    #[test]
+    fn lessons() {
+        let entry = lesson! {
+            macros::macros_demo,
+            title: "Macros",
+            body: "macro_rules!",
+        };
+        assert_eq!((entry.id, entry.title, entry.body), ("macros", "Macros", "macro_rules!"));
+        assert!(entry.source.contains("macro_rules! lesson {"));
+
+        let lessons = [entry];
+        assert_eq!(find_lesson(&lessons, "macros").map(|lesson| lesson.title), Some("Macros"));
+        assert!(find_lesson(&lessons, "Macros").is_none());
+        assert!(find_lesson(&lessons, "").is_none());
+        assert!(find_lesson(&[], "macros").is_none());
+    }
+
+    #[test]
+    fn the_registry() {
+        for lesson in &crate::LESSONS {
+            // Every id finds its own lesson, so none is shadowed by a duplicate
+            assert!(std::ptr::eq(find_lesson(&crate::LESSONS, lesson.id).unwrap(), lesson));
+            assert!(!lesson.title.is_empty() && !lesson.body.is_empty());
+            assert!(!lesson.source.is_empty());
+        }
+        assert!(find_lesson(&crate::LESSONS, "macros").is_some());
+        assert!(find_lesson(&crate::LESSONS, "no_such_lesson").is_none());
+    }
+}
@@ -4,10 +4,48 @@
 // a module for formatting and printing Strings.
 use std::fmt;
 
+use macros::{lesson, LessonEntry};
+
 // Importing a local file
+mod alloc_counter;
 mod another_file_for_import;
+mod async_rust;
+mod binary;
+mod closures;
+mod collections;
+mod compile_fail;
+mod concurrency;
+mod drop_trace;
+mod errors;
+mod export;
+mod ffi_export;
+mod file_io;
+mod generics;
+mod highlight;
+mod iterators;
+mod json;
+mod learner;
+mod lifetimes;
+mod macros;
+mod network;
+mod patterns;
+mod person;
+mod rng;
+mod serve;
+mod smart_pointers;
+mod teacher;
+#[cfg(test)]
+mod testing;
+mod unsafe_rust;
 
 fn main() {
+    // Subcommands like `./main teacher progress/` run a tool instead of the
+    // notes, see `run_command` at the bottom of this file
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&args);
+    }
+
     println!("Hello, world!");
 
     // Using a function from a different local file
@@ -38,35 +76,16 @@ fn main() {
 
     // For operators and symbols, you can visit: https://doc.rust-lang.org/book/appendix-02-operators.html
 
-    // A basic struct
-    #[derive(Debug)] /*
-     derive(Debug) asks the compiler to auto-generate a suitable implementation of the Debug trait.
-     (https://doc.rust-lang.org/std/fmt/trait.Debug.html)
-    */
-    struct Person {
-        name: String,
-        age: u8,
-    }
+    // A basic struct: `Person` is declared at the bottom of this file, so
+    // that other files (like person.rs) can implement traits for it
 
     // A unit struct
     struct Unit;
 
-    // A tuple struct
-    struct Pair(i32, f32);
+    // A tuple struct (`Pair`) is declared at the bottom of this file too
 
-    // A struct with two fields
-    struct PointTest {
-        x: f32,
-        y: f32,
-    }
-
-    // Structs can be reused as fields of another struct
-    struct Rectangle {
-        // A rectangle can be specified by where the top left and bottom right
-        // corners are in space.
-        top_left: PointTest,
-        bottom_right: PointTest,
-    }
+    // A struct with two fields (`PointTest`) and a struct reusing it as its
+    // fields (`Rectangle`) are declared at the bottom of this file as well
 
     let name = String::from("Peter");
     let age = 27;
@@ -75,6 +94,10 @@ fn main() {
     // Print debug struct
     println!("{:?}", peter);
 
+    // Print with `Display`, and parse it back with `FromStr` (see person.rs)
+    println!("{}", peter);
+    person::person_demo();
+
     // Instantiate a `Point`
     let point: PointTest = PointTest { x: 5.2, y: 0.4 };
     let another_point: PointTest = PointTest { x: 10.3, y: 0.2 };
@@ -100,42 +123,22 @@ fn main() {
     // Instantiate a unit struct
     let _unit = Unit;
 
+    // These structs (and `WebEvent` below) can be turned into JSON and back,
+    // see json.rs
+    json::json_demo();
+
     // Enums
 
-    enum WebEvent {
-        // An `enum` variant may either be `unit-like`,
-        PageLoad,
-        PageUnload,
-        // like tuple structs,
-        KeyPress(char),
-        Paste(String),
-        // or c-like structures.
-        Click { x: i64, y: i64 },
-    }
+    // `WebEvent` is declared at the bottom of this file, so json.rs can use it
 
     // Type aliases
 
-    enum VeryVerboseEnumOfThingsToDoWithNumbers {
-        Add,
-        Subtract,
-    }
-
-    // Creates a type alias
-    type Operations = VeryVerboseEnumOfThingsToDoWithNumbers;
+    // `VeryVerboseEnumOfThingsToDoWithNumbers`, its `Operations` alias and
+    // its `run` method are declared at the bottom of this file, so the tests
+    // can reach them
 
     let _x = Operations::Add;
 
-    // Implementing
-
-    impl VeryVerboseEnumOfThingsToDoWithNumbers {
-        fn run(&self, x: i32, y: i32) -> i32 {
-            match self {
-                Self::Add => x + y,
-                Self::Subtract => x - y,
-            }
-        }
-    }
-
     let add = Operations::Add.run(23, 24);
     println!("{}", add);
 
@@ -165,6 +168,12 @@ fn main() {
         Teacher => println!("Teachers are spreading knowledge!"),
     }
 
+    // The same stages, but checked by the compiler instead, see learner.rs
+    learner::learner_demo();
+
+    // And what teachers do: see teacher.rs
+    teacher::teacher_demo();
+
     // enum with implicit discriminator (starts at 0)
     enum NumberEnum {
         Zero,
@@ -172,12 +181,8 @@ fn main() {
         Two,
     }
 
-    // enum with explicit discriminator
-    enum Color {
-        Red = 0xff0000,
-        Green = 0x00ff00,
-        Blue = 0x0000ff,
-    }
+    // enum with explicit discriminator: `Color`, declared at the bottom of
+    // this file so binary.rs can encode it
 
     // `enums` can be cast as integers.
     println!("zero is {}", NumberEnum::Zero as i32);
@@ -238,6 +243,9 @@ fn main() {
     println!("size of `i` in bytes: {}", std::mem::size_of_val(&i));
     println!("size of `f` in bytes: {}", std::mem::size_of_val(&f));
 
+    // How values look as bytes once encoded, see binary.rs
+    binary::binary_demo();
+
     // Inference
 
     // Because of the annotation, the compiler knows that `elem` has type u8.
@@ -254,6 +262,9 @@ fn main() {
 
     println!("{:?}", vec);
 
+    // HashMap, BTreeMap, HashSet, VecDeque and BinaryHeap, and when to use which: see collections.rs
+    collections::collections_demo();
+
     // Aliasing
 
     type NanoSecond = u64;
@@ -278,16 +289,8 @@ fn main() {
 
     // For custom struct (from & into)
 
-    #[derive(Debug)]
-    struct Number {
-        value: i32,
-    }
-
-    impl From<i32> for Number {
-        fn from(item: i32) -> Self {
-            Number { value: item }
-        }
-    }
+    // `Number` and its `From<i32>` implementation are declared at the bottom
+    // of this file
 
     // impl Into<Number> for i32 {
     //     fn into(self) -> Number {
@@ -304,20 +307,8 @@ fn main() {
 
     // TryFrom & TryInto
 
-    #[derive(Debug, PartialEq)]
-    struct EvenNumber(i32);
-
-    impl TryFrom<i32> for EvenNumber {
-        type Error = ();
-
-        fn try_from(value: i32) -> Result<Self, Self::Error> {
-            if value % 2 == 0 {
-                Ok(EvenNumber(value))
-            } else {
-                Err(())
-            }
-        }
-    }
+    // `EvenNumber(i32)` and its `TryFrom<i32>` implementation are declared at
+    // the bottom of this file, so json.rs can use them too
 
     // TryFrom
 
@@ -333,26 +324,20 @@ fn main() {
 
     // Strings
 
-    struct Circle {
-        radius: i32
-    }
-
-    impl fmt::Display for Circle {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "Circle of radius {}", self.radius)
-        }
-    }
+    // `Circle` and its `Display` implementation are declared at the bottom of
+    // this file
 
     let circle = Circle { radius: 6 };
     println!("{}", circle.to_string());
 
     // Parsing a string
 
-    let parsed: i32 = "5".parse().unwrap();
-    let turbo_parsed = "10".parse::<i32>().unwrap();
-
-    let sum = parsed + turbo_parsed;
-    println!("Sum: {:?}", sum);
+    // Parsing can fail: `parse_sum` (errors.rs) returns a `Result` instead of
+    // calling `unwrap()`, and we decide here what to do with an error
+    match errors::parse_sum("5", "10") {
+        Ok(sum) => println!("Sum: {:?}", sum),
+        Err(e) => println!("{}", errors::report(&e)),
+    }
 
     // Expressions
 
@@ -508,6 +493,8 @@ fn main() {
     }
 
     // https://doc.rust-lang.org/rust-by-example/flow_control/match/guard.html
+    // Guards, `@` bindings, slice patterns, `ref mut` and exhaustiveness: see patterns.rs
+    patterns::patterns_demo();
 
     // if let
 
@@ -532,12 +519,13 @@ fn main() {
     // let else
     let s = "a b c d";
 
-    let mut it = s.split(' ');
-    let (Some(count_str), Some(item)) = (it.next(), it.next()) else {
-        panic!("Can't segment count item pair: '{s}'");
-    };
-
-    println!("{}, {}", count_str, item);
+    // The `else` branch must diverge: `split_count_item` (errors.rs) uses
+    // `let (Some(count_str), Some(item)) = (it.next(), it.next()) else { ... }`
+    // and returns an error from it, where a quick example would `panic!`
+    match errors::split_count_item(s) {
+        Ok((count_str, item)) => println!("{}, {}", count_str, item),
+        Err(e) => println!("{}", errors::report(&e)),
+    }
 
     // while let
 
@@ -581,8 +569,10 @@ fn main() {
         }
     }
 
-    let test = Test { name: "Ekrem".parse().unwrap() };
-    test.say_name();
+    match errors::parse_name("Ekrem") {
+        Ok(name) => Test { name }.say_name(),
+        Err(e) => println!("{}", errors::report(&e)),
+    }
 
     Test::greet_someone("John Doe");
 
@@ -593,8 +583,12 @@ fn main() {
     println!("sum_closure: {}", sum_closure(20));
 
     // https://doc.rust-lang.org/rust-by-example/fn/closures.html
+    // The whole chapter (`Fn`, `FnMut`, `FnOnce`, callbacks...): see closures.rs
+    closures::closures_demo();
 
     // Higher order functions: https://doc.rust-lang.org/rust-by-example/fn/hof.html
+    // Custom iterators, adapter chains and a benchmark: see iterators.rs
+    iterators::iterators_demo();
 
     // super and self: https://doc.rust-lang.org/rust-by-example/mod/super.html
 
@@ -608,6 +602,10 @@ fn main() {
 
     // Attributes: https://doc.rust-lang.org/rust-by-example/attribute.html
 
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/generics.html !!!!!!!!!!!!!!!!!!!!
+    // `Pair<T, U>`, bounds, where clauses, associated types and const generics: see generics.rs
+    generics::generics_demo();
+
     // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/scope.html !!!!!!!!!!!!!!!!!!!!!!!!!!!
 
     // !!!!!!!!!!!!!!!!!!!! RAII (Resource Acquisition Is Initialization), https://en.wikipedia.org/wiki/Resource_acquisition_is_initialization !!!!!!!!!!!!!!!!!!!!
@@ -649,6 +647,11 @@ fn main() {
 
     boxes_init();
 
+    // Don't take the comments' word for it: count the allocations (see
+    // alloc_counter.rs). Every `Box` has to be freed again, nothing leaks.
+    alloc_counter::assert_no_leaks("create_box", create_box);
+    alloc_counter::assert_no_leaks("boxes_init", boxes_init);
+
     // !!!!!!!!!!!!!!!!!!!! Ownership and moves !!!!!!!!!!!!!!!!!!!!
 
     /*
@@ -660,11 +663,8 @@ fn main() {
     After moving resources, the previous owner can no longer be used. This avoids creating dangling pointers.
      */
 
-    fn destroy_box(c: Box<i32>) {
-        println!("Destroying a box that contains {}", c);
-
-        // `c` is destroyed and the memory freed
-    }
+    // `destroy_box` is with the other functions at the bottom of this file,
+    // where drop_trace.rs can call it too
 
     // _Stack_ allocated integer
     let x = 5u32;
@@ -724,14 +724,7 @@ fn main() {
     // !!!!!!!!!!!!!!!!!!!! Borrowing !!!!!!!!!!!!!!!!!!!!
     // https://doc.rust-lang.org/rust-by-example/scope/borrow.html
 
-    fn eat_box_i32(boxed_i32: Box<i32>) {
-        println!("Destroying box that contains {}", boxed_i32);
-    }
-
-    // This function borrows an i32
-    fn borrow_i32(borrowed_i32: &i32) {
-        println!("This int is: {}", borrowed_i32);
-    }
+    // `eat_box_i32` and `borrow_i32` are at the bottom of this file too
 
     // Create a boxed i32 in the heap, and a i32 on the stack
     // Remember: numbers can have arbitrary underscores added for readability
@@ -761,6 +754,9 @@ fn main() {
     // `boxed_i32` can now give up ownership to `eat_box_i32` and be destroyed
     eat_box_i32(boxed_i32);
 
+    // The same moves and borrows, but every step is printed: see drop_trace.rs
+    drop_trace::drop_trace_demo();
+
     // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/scope/borrow/mut.html !!!!!!!!!!!!!!!!!!!!
 
     /*
@@ -768,6 +764,9 @@ fn main() {
     at a time. The original data can be borrowed again only after the mutable reference has been used for the last time.
     */
 
+    // The same rules checked at run time by `RefCell`, shared ownership with `Rc`, and `Weak`: see smart_pointers.rs
+    smart_pointers::smart_pointers_demo();
+
     // https://doc.rust-lang.org/rust-by-example/scope/borrow/ref.html
     // When doing pattern matching or destructuring via the let binding, the ref keyword can be used to take references to the fields of a struct/tuple.
 
@@ -780,6 +779,7 @@ fn main() {
     let ref_c2 = &c;
 
     println!("ref_c1 equals ref_c2: {}", *ref_c1 == *ref_c2);
+    // More `ref` and `ref mut`, and why they're rarely needed: see patterns.rs
 
     // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/scope/lifetime.html !!!!!!!!!!!!!!!!!!!!
     /*
@@ -787,95 +787,240 @@ fn main() {
     created and ends when it is destroyed. While lifetimes and scopes are often referred to together, they are not the same.
     */
 
+    // The whole chapter, with borrowed-data parsers: see lifetimes.rs
+    lifetimes::lifetimes_demo();
+
+    // A lexer for Rust itself, to print code with colors: see highlight.rs
+    highlight::highlight_demo();
+
     // !!!!!!!!!!!!!!!!!!!! Traits https://doc.rust-lang.org/rust-by-example/trait.html
 
-    struct Sheep { naked: bool, name: &'static str }
+    // `Sheep`, the `Animal` trait and its implementation are declared at the
+    // bottom of this file, so the tests can shear a sheep too
+
+    let mut dolly: Sheep = Animal::new("Dolly");
+    // TODO ^ Try removing the type annotations.
+
+    dolly.talk();
+    dolly.shear();
+    dolly.talk();
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/trait/derive.html
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/trait/dyn.html
+
+    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/drop.html (see drop_trace.rs)
+    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/clone.html
+    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/supertraits.html
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/error.html
+    // Custom errors, `?`, `Box<dyn Error>` and cause chains: see errors.rs
+    errors::errors_demo();
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/std_misc/threads.html
+    // Threads, channels, locks, atomics and deadlocks: see concurrency.rs
+    concurrency::concurrency_demo();
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/std_misc/file.html
+    // Buffered reading and writing, paths, and saving annotations safely: see file_io.rs
+    file_io::file_io_demo();
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/std/net/index.html
+    // An echo server, a chat room and WebEvents sent over TCP: see network.rs
+    network::network_demo();
+
+    // !!!!!!!!!!!!!!!!!!!! https://rust-lang.github.io/async-book/
+    // Futures, wakers, Pin, and two executors written from scratch: see async_rust.rs
+    async_rust::async_demo();
+
+    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/macros.html
+    // Repetition, recursion, hygiene and the `lesson!` macro: see macros.rs
+    macros::macros_demo();
+
+    // https://doc.rust-lang.org/rust-by-example/testing.html
+    // The tests of this file, and how to write and organise tests: see testing.rs
+    // https://doc.rust-lang.org/rust-by-example/unsafe.html
+    // Raw pointers, `MaybeUninit` and calling C (and being called by C): see unsafe_rust.rs
+    unsafe_rust::unsafe_demo();
+}
+
+
+// A basic struct
+#[derive(Debug, Clone, PartialEq)] /*
+ derive(Debug) asks the compiler to auto-generate a suitable implementation of the Debug trait.
+ (https://doc.rust-lang.org/std/fmt/trait.Debug.html)
+ derive(PartialEq) lets us compare two people with `==` and `assert_eq!`.
+*/
+struct Person {
+    name: String,
+    age: u8,
+}
+
+// A tuple struct
+#[derive(Debug, PartialEq)]
+struct Pair(i32, f32);
+
+// A struct with two fields
+#[derive(Debug, PartialEq)]
+struct PointTest {
+    x: f32,
+    y: f32,
+}
 
-    trait Animal {
-        // Associated function signature; `Self` refers to the implementor type.
-        fn new(name: &'static str) -> Self;
+// Structs can be reused as fields of another struct
+#[derive(Debug, PartialEq)]
+struct Rectangle {
+    // A rectangle can be specified by where the top left and bottom right
+    // corners are in space.
+    top_left: PointTest,
+    bottom_right: PointTest,
+}
+
+#[derive(Debug, PartialEq)]
+enum WebEvent {
+    // An `enum` variant may either be `unit-like`,
+    PageLoad,
+    PageUnload,
+    // like tuple structs,
+    KeyPress(char),
+    Paste(String),
+    // or c-like structures.
+    Click { x: i64, y: i64 },
+}
+
+// enum with explicit discriminator
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    Red = 0xff0000,
+    Green = 0x00ff00,
+    Blue = 0x0000ff,
+}
 
-        // Method signatures; these will return a string.
-        fn name(&self) -> &'static str;
-        fn noise(&self) -> &'static str;
+#[derive(Debug, PartialEq)]
+struct EvenNumber(i32);
 
-        // Traits can provide default method definitions.
-        fn talk(&self) {
-            println!("{} says {}", self.name(), self.noise());
+impl TryFrom<i32> for EvenNumber {
+    type Error = ();
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        if value % 2 == 0 {
+            Ok(EvenNumber(value))
+        } else {
+            Err(())
         }
     }
+}
 
-    impl Sheep {
-        fn is_naked(&self) -> bool {
-            self.naked
-        }
+// A type alias, see "Type aliases" in main()
+enum VeryVerboseEnumOfThingsToDoWithNumbers {
+    Add,
+    Subtract,
+}
 
-        fn shear(&mut self) {
-            if self.is_naked() {
-                // Implementor methods can use the implementor's trait methods.
-                println!("{} is already naked...", self.name());
-            } else {
-                println!("{} gets a haircut!", self.name);
+// Creates a type alias
+type Operations = VeryVerboseEnumOfThingsToDoWithNumbers;
 
-                self.naked = true;
-            }
+// Implementing
+impl VeryVerboseEnumOfThingsToDoWithNumbers {
+    fn run(&self, x: i32, y: i32) -> i32 {
+        match self {
+            Self::Add => x + y,
+            Self::Subtract => x - y,
         }
     }
+}
 
-    // Implement the `Animal` trait for `Sheep`.
-    impl Animal for Sheep {
-        // `Self` is the implementor type: `Sheep`.
-        fn new(name: &'static str) -> Sheep {
-            Sheep { name: name, naked: false }
-        }
+// For custom struct (from & into)
+#[derive(Debug, PartialEq)]
+struct Number {
+    value: i32,
+}
 
-        fn name(&self) -> &'static str {
-            self.name
-        }
+impl From<i32> for Number {
+    fn from(item: i32) -> Self {
+        Number { value: item }
+    }
+}
 
-        fn noise(&self) -> &'static str {
-            if self.is_naked() {
-                "baaaaah?"
-            } else {
-                "baaaaah!"
-            }
-        }
+// Strings
+struct Circle {
+    radius: i32
+}
 
-        // Default trait methods can be overridden.
-        fn talk(&self) {
-            // For example, we can add some quiet contemplation.
-            println!("{} pauses briefly... {}", self.name, self.noise());
-        }
+impl fmt::Display for Circle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Circle of radius {}", self.radius)
     }
+}
 
-    let mut dolly: Sheep = Animal::new("Dolly");
-    // TODO ^ Try removing the type annotations.
+// Traits
+struct Sheep { naked: bool, name: &'static str }
 
-    dolly.talk();
-    dolly.shear();
-    dolly.talk();
+trait Animal {
+    // Associated function signature; `Self` refers to the implementor type.
+    fn new(name: &'static str) -> Self;
 
-    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/trait/derive.html
-    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/trait/dyn.html
+    // Method signatures; these will return a string.
+    fn name(&self) -> &'static str;
+    fn noise(&self) -> &'static str;
 
-    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/drop.html
-    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/clone.html
-    // !!!!! https://doc.rust-lang.org/rust-by-example/trait/supertraits.html
+    // Traits can provide default method definitions.
+    fn talk(&self) {
+        println!("{} says {}", self.name(), self.noise());
+    }
+}
 
-    // !!!!!!!!!!!!!!!!!!!! https://doc.rust-lang.org/rust-by-example/macros.html
+impl Sheep {
+    fn is_naked(&self) -> bool {
+        self.naked
+    }
 
-    // https://doc.rust-lang.org/rust-by-example/testing.html
-    // https://doc.rust-lang.org/rust-by-example/unsafe.html
+    fn shear(&mut self) {
+        if self.is_naked() {
+            // Implementor methods can use the implementor's trait methods.
+            println!("{} is already naked...", self.name());
+        } else {
+            println!("{} gets a haircut!", self.name);
+
+            self.naked = true;
+        }
+    }
 }
 
+// Implement the `Animal` trait for `Sheep`.
+impl Animal for Sheep {
+    // `Self` is the implementor type: `Sheep`.
+    fn new(name: &'static str) -> Sheep {
+        Sheep { name: name, naked: false }
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn noise(&self) -> &'static str {
+        if self.is_naked() {
+            "baaaaah?"
+        } else {
+            "baaaaah!"
+        }
+    }
+
+    // Default trait methods can be overridden.
+    fn talk(&self) {
+        // For example, we can add some quiet contemplation.
+        println!("{} pauses briefly... {}", self.name, self.noise());
+    }
+}
 
 // Sample enum for "use"
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Stage {
     Beginner,
     Advanced,
 }
 
 // Sample enum for "use"
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Role {
     Student,
     Teacher,
@@ -883,6 +1028,24 @@ enum Role {
 
 // Functions
 
+// Ownership and moves: takes ownership of the box, whatever it holds, so that
+// drop_trace.rs can pass a box of something that logs its own drop
+fn destroy_box<T: std::fmt::Display>(c: Box<T>) {
+    println!("Destroying a box that contains {}", c);
+
+    // `c` is destroyed and the memory freed
+}
+
+// Borrowing: this one takes ownership too...
+fn eat_box_i32<T: std::fmt::Display>(boxed_i32: Box<T>) {
+    println!("Destroying box that contains {}", boxed_i32);
+}
+
+// ...and this function only borrows an i32
+fn borrow_i32(borrowed_i32: &i32) {
+    println!("This int is: {}", borrowed_i32);
+}
+
 // Function that returns a boolean value
 fn is_divisible_by(lhs: u32, rhs: u32) -> bool {
     // Corner case, early return
@@ -896,14 +1059,20 @@ fn is_divisible_by(lhs: u32, rhs: u32) -> bool {
 
 // Functions that "don't" return a value, actually return the unit type `()`
 fn fizzbuzz(n: u32) -> () {
+    write_fizzbuzz(&mut std::io::stdout(), n).unwrap();
+}
+
+// Same, but it writes to any `io::Write`: the terminal, a file, or a
+// `Vec<u8>` that the tests can look at (see testing.rs)
+fn write_fizzbuzz(out: &mut impl std::io::Write, n: u32) -> std::io::Result<()> {
     if is_divisible_by(n, 15) {
-        println!("fizzbuzz");
+        writeln!(out, "fizzbuzz")
     } else if is_divisible_by(n, 3) {
-        println!("fizz");
+        writeln!(out, "fizz")
     } else if is_divisible_by(n, 5) {
-        println!("buzz");
+        writeln!(out, "buzz")
     } else {
-        println!("{}", n);
+        writeln!(out, "{}", n)
     }
 }
 
@@ -914,3 +1083,146 @@ fn fizzbuzz_to(n: u32) {
         fizzbuzz(n);
     }
 }
+
+// Subcommands
+
+// The lessons that live in their own file, see `lesson!` in macros.rs
+const LESSONS: [LessonEntry; 21] = [
+    lesson! {
+        person::person_demo,
+        title: "Person: Display, FromStr and CSV",
+        body: "Printing and parsing a struct, and a CSV reader/writer checked with a round-trip property test.",
+    },
+    lesson! {
+        json::json_demo,
+        title: "A JSON parser and serializer",
+        body: "Enums for values, a streaming tokenizer, a recursive descent parser and the ToJson/FromJson traits.",
+    },
+    lesson! {
+        binary::binary_demo,
+        title: "A binary encoding",
+        body: "Varints, zigzag, length-prefixed strings and the Encode/Decode traits, fuzzed with random input.",
+    },
+    lesson! {
+        collections::collections_demo,
+        title: "Collections",
+        body: "Word frequencies of these notes, an inventory of \"3 apples\" lines and a top-k query with a heap.",
+    },
+    lesson! {
+        learner::learner_demo,
+        title: "The typestate pattern",
+        body: "A Learner whose stage is part of its type, so the compiler rejects lessons taken too early.",
+    },
+    lesson! {
+        teacher::teacher_demo,
+        title: "A teacher dashboard",
+        body: "Reading every student's progress file and aggregating them into a report.",
+    },
+    lesson! {
+        patterns::patterns_demo,
+        title: "Patterns",
+        body: "Guards, @ bindings, slice patterns, ref and ref mut, and matches that must be exhaustive.",
+    },
+    lesson! {
+        generics::generics_demo,
+        title: "Generics",
+        body: "Pair<T, U> with bounded methods, where clauses, associated types and a const generic Matrix.",
+    },
+    lesson! {
+        drop_trace::drop_trace_demo,
+        title: "Drop, and tracing ownership",
+        body: "A Traced<T> wrapper that logs moves, borrows and drops, to watch the ownership rules at work.",
+    },
+    lesson! {
+        lifetimes::lifetimes_demo,
+        title: "Lifetimes",
+        body: "Functions and structs borrowing their data, a zero-copy tokenizer, and code that must not compile.",
+    },
+    lesson! {
+        highlight::highlight_demo,
+        title: "A Rust syntax highlighter",
+        body: "A lexer for Rust's own tokens: lifetimes or chars, raw strings, nested comments, ANSI and HTML.",
+    },
+    lesson! {
+        smart_pointers::smart_pointers_demo,
+        title: "Rc, RefCell and Weak",
+        body: "A tree with parent pointers, strong and weak counts, a leaking cycle, and borrow errors at run time.",
+    },
+    lesson! {
+        closures::closures_demo,
+        title: "Closures",
+        body: "Fn, FnMut and FnOnce, returning closures, a callback registry and memoisation.",
+    },
+    lesson! {
+        iterators::iterators_demo,
+        title: "Iterators",
+        body: "Custom iterators, loops rewritten as adapter chains, and a benchmark of both.",
+    },
+    lesson! {
+        macros::macros_demo,
+        title: "macro_rules!",
+        body: "Repetition, recursion, hygiene, a hashmap! literal and a small fizzbuzz language.",
+    },
+    lesson! {
+        concurrency::concurrency_demo,
+        title: "Concurrency",
+        body: "Threads, channels, Mutex, RwLock, atomics, Condvar, an ordered parallel fizzbuzz and deadlocks.",
+    },
+    lesson! {
+        errors::errors_demo,
+        title: "Error handling",
+        body: "A custom error enum, From conversions, the ? operator, Box<dyn Error> and chains of causes.",
+    },
+    lesson! {
+        unsafe_rust::unsafe_demo,
+        title: "Unsafe Rust",
+        body: "Raw pointers, slices from raw parts, MaybeUninit, and calling C functions from Rust and back.",
+    },
+    lesson! {
+        file_io::file_io_demo,
+        title: "File I/O",
+        body: "BufReader, BufWriter, Path, atomic saves via rename, and the learner's annotations file.",
+    },
+    lesson! {
+        network::network_demo,
+        title: "TCP networking",
+        body: "An echo server, a multi-client chat room, and WebEvents sent as JSON lines to a server.",
+    },
+    lesson! {
+        async_rust::async_demo,
+        title: "async/await",
+        body: "Timer and channel futures, a single-threaded executor, a work-stealing one, and async WebEvents.",
+    },
+];
+
+fn run_command(args: &[String]) {
+    match args[0].as_str() {
+        "teacher" => teacher::teacher_main(&args[1..]),
+        "leaks" => alloc_counter::leaks_main(&LESSONS),
+        "compile-fail" => {
+            let chapters = [
+                &learner::COMPILE_FAIL_CASES[..],
+                &lifetimes::COMPILE_FAIL_CASES,
+                &patterns::COMPILE_FAIL_CASES,
+                &generics::COMPILE_FAIL_CASES,
+            ];
+            compile_fail::compile_fail_main(&chapters.concat())
+        }
+        "bench-iterators" => iterators::bench_main(&args[1..]),
+        "lessons" => macros::lessons_main(&LESSONS),
+        "run" => macros::run_main(&LESSONS, &args[1..]),
+        "ffi" => unsafe_rust::ffi_main(),
+        "note" => file_io::note_main(&LESSONS, &args[1..]),
+        "net" => network::net_main(&args[1..]),
+        "serve" => serve::serve_main(&LESSONS, &args[1..]),
+        "export" => export::export_main(&LESSONS, &args[1..]),
+        "source" => highlight::source_main(&LESSONS, &args[1..]),
+        other => {
+            eprintln!(
+                "unknown command '{}', expected: teacher, leaks, compile-fail, bench-iterators, lessons, run, ffi, note, net, serve, export, source",
+                other
+            );
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,471 @@
+// !!!!!!!!!!!!!!!!!!!! TCP networking https://doc.rust-lang.org/std/net/index.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`std::net` is a thin layer over the operating system's sockets, with no async and no TLS, which is all a local
+tool needs.
+
+    - A server binds a `TcpListener` to an address and port, then `accept`s connections: each one is a `TcpStream`.
+    - A client `TcpStream::connect`s to the server's address.
+    - A `TcpStream` is a byte pipe in both directions, implementing `Read` and `Write` like a file, so
+      `BufReader::lines` and `writeln!` work on it. There are no messages: a `write` of 10 bytes may arrive as two
+      reads of 5. Sending one line per message, and reading line by line, puts the boundaries back.
+    - Binding to port 0 asks the OS for any free port, and `local_addr()` tells which one it picked. Tests use it
+      so they never fight over a port.
+    - `accept` and `read` block the thread. The simplest server gives every connection a thread of its own.
+
+Three servers share the plumbing below: an echo server, a chat room, and one reading `WebEvent`s sent as JSON
+(json.rs), one per line. Run them with:
+
+    ./main net echo|chat|events [address]
+    ./main net send-events <address>
+*/
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::async_rust::describe;
+use crate::json::{FromJson, ToJson};
+use crate::WebEvent;
+
+// A client never waits longer than this for an answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// !!!!!!!!!!!!!!!!!!!! One thread per connection !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) type Handler = Arc<dyn Fn(TcpStream) + Send + Sync>;
+
+// Accepts connections until `stopping` is set, then closes the ones still
+// open and waits for their threads
+pub(crate) fn accept_loop(listener: TcpListener, stopping: &AtomicBool, handler: Handler) {
+    // A second handle on every open connection, to close it from here
+    let open: Arc<Mutex<HashMap<usize, TcpStream>>> = Arc::default();
+    let mut workers: Vec<JoinHandle<()>> = Vec::new();
+
+    for (id, stream) in listener.incoming().enumerate() {
+        if stopping.load(Ordering::Acquire) {
+            break;
+        }
+        // A client that gave up before we accepted it: not our problem
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        if let Ok(clone) = stream.try_clone() {
+            open.lock().unwrap().insert(id, clone);
+        }
+
+        // Threads whose client left can be joined right away
+        let (finished, running) = workers.into_iter().partition(|worker| worker.is_finished());
+        workers = running;
+        for worker in finished {
+            let _ = worker.join();
+        }
+
+        let handler = Arc::clone(&handler);
+        let open = Arc::clone(&open);
+        workers.push(thread::spawn(move || {
+            handler(stream);
+            // Our copy would keep the connection open after the handler is
+            // done with it
+            if let Some(clone) = open.lock().unwrap().remove(&id) {
+                let _ = clone.shutdown(Shutdown::Both);
+            }
+        }));
+    }
+
+    // Reads on a closed socket return "end of file": the handlers return
+    for stream in open.lock().unwrap().values() {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+// A server running on a thread of its own, stopped when dropped
+pub(crate) struct Server {
+    address: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    pub(crate) fn spawn(
+        address: impl ToSocketAddrs,
+        handler: impl Fn(TcpStream) + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let flag = Arc::clone(&stopping);
+        let thread = thread::spawn(move || accept_loop(listener, &flag, Arc::new(handler)));
+
+        Ok(Server { address, stopping, thread: Some(thread) })
+    }
+
+    pub(crate) fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Release);
+        // `accept` is blocked waiting for a client: be that client, so it
+        // returns and sees the flag
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Echo !!!!!!!!!!!!!!!!!!!!
+
+// Sends back every byte it receives. `&TcpStream` implements `Read` and
+// `Write` too, so the same stream is both ends of the copy.
+pub(crate) fn handle_echo(stream: TcpStream) {
+    let _ = io::copy(&mut &stream, &mut &stream);
+}
+
+pub(crate) fn echo(address: SocketAddr, text: &str) -> io::Result<String> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    writeln!(stream, "{}", text)?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    Ok(reply.trim_end_matches('\n').to_string())
+}
+
+// !!!!!!!!!!!!!!!!!!!! Chat !!!!!!!!!!!!!!!!!!!!
+
+/*
+The first line a client sends is its name, every other line is a message for everyone else in the room:
+
+    > alice
+    < * welcome alice, 1 other here
+    < * bob joined
+    < bob: hi alice
+    > hi bob
+*/
+
+// A client that takes longer than this to accept a message is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub(crate) struct ChatRoom {
+    // Every client's id and a handle to write to it
+    clients: Mutex<Vec<(usize, Arc<TcpStream>)>>,
+    next_id: AtomicUsize,
+}
+
+impl ChatRoom {
+    // To every client but `except`. The handles are copied out first: a write
+    // can block, and a client that doesn't read must not lock the whole room.
+    fn broadcast(&self, except: usize, line: &str) {
+        let others: Vec<Arc<TcpStream>> = self
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| *id != except)
+            .map(|(_, stream)| Arc::clone(stream))
+            .collect();
+
+        for stream in others {
+            // Gone, or too slow: disconnecting it ends its own thread, which
+            // removes it from the room
+            if writeln!(&*stream, "{}", line).is_err() {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    pub(crate) fn handle(&self, stream: TcpStream) {
+        let Ok(writer) = stream.try_clone() else {
+            return;
+        };
+        if writer.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+            return;
+        }
+        let mut lines = BufReader::new(stream).lines();
+        let Some(Ok(name)) = lines.next() else {
+            return;
+        };
+        let name = name.trim().to_string();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        // Counted and added under one lock, and welcomed before any broadcast
+        // can reach it. Nothing was sent on this connection yet, so this one
+        // write can't wait for the client.
+        {
+            let mut clients = self.clients.lock().unwrap();
+            let others = clients.len();
+            let plural = if others == 1 { "" } else { "s" };
+            let _ = writeln!(&writer, "* welcome {}, {} other{} here", name, others, plural);
+            clients.push((id, Arc::new(writer)));
+        }
+        self.broadcast(id, &format!("* {} joined", name));
+
+        // Ends when the client disconnects, or the server stops
+        for line in lines.map_while(Result::ok) {
+            self.broadcast(id, &format!("{}: {}", name, line));
+        }
+
+        self.clients.lock().unwrap().retain(|(client, _)| *client != id);
+        self.broadcast(id, &format!("* {} left", name));
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! WebEvents as JSON lines !!!!!!!!!!!!!!!!!!!!
+
+// One `WebEvent` per line, in the JSON format of json.rs. Each one is answered
+// with what RBE's `inspect` prints for it, or the reason it was rejected.
+pub(crate) fn handle_events(stream: TcpStream) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let mut writer = BufWriter::new(writer);
+
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        let reply = match WebEvent::from_json_str(&line) {
+            Ok(event) => describe(&event),
+            Err(e) => format!("error: {}", e),
+        };
+        // Flushed for every line: the client may wait for each answer
+        if writeln!(writer, "{}", reply).and_then(|()| writer.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+// Sends every event, then reads every answer
+pub(crate) fn send_events(address: SocketAddr, events: &[WebEvent]) -> io::Result<Vec<String>> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+
+    let mut writer = BufWriter::new(&stream);
+    for event in events {
+        // `to_string()` is the compact JSON, always on one line
+        writeln!(writer, "{}", event.to_json())?;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    // Closing our half tells the server there's nothing more: it answers the
+    // last line, sees the end of the stream and closes its half too
+    stream.shutdown(Shutdown::Write)?;
+    BufReader::new(&stream).lines().collect()
+}
+
+pub(crate) fn sample_events() -> Vec<WebEvent> {
+    vec![
+        WebEvent::PageLoad,
+        WebEvent::KeyPress('x'),
+        WebEvent::Paste(String::from("my text")),
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::PageUnload,
+    ]
+}
+
+// `./main net echo|chat|events [address]` and `./main net send-events <address>`
+pub(crate) fn net_main(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: ./main net echo|chat|events [address], or ./main net send-events <address>");
+        std::process::exit(2);
+    };
+
+    let (command, address) = match args {
+        [command] if command != "send-events" => (command.as_str(), "127.0.0.1:7878"),
+        [command, address] => (command.as_str(), address.as_str()),
+        _ => usage(),
+    };
+
+    let handler: Handler = match command {
+        "echo" => Arc::new(handle_echo),
+        "chat" => {
+            let room = Arc::new(ChatRoom::default());
+            Arc::new(move |stream| room.handle(stream))
+        }
+        "events" => Arc::new(handle_events),
+        "send-events" => {
+            let result = address
+                .to_socket_addrs()
+                .and_then(|mut addresses| addresses.next().ok_or_else(|| io::Error::other("no address")))
+                .and_then(|address| send_events(address, &sample_events()));
+            match result {
+                Ok(replies) => replies.iter().for_each(|reply| println!("{}", reply)),
+                Err(e) => {
+                    eprintln!("error: can't send the events to {}: {}", address, e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => usage(),
+    };
+
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: can't listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    // The tests bind to port 0 and read the real port from this line
+    match listener.local_addr() {
+        Ok(local) => println!("listening on {}", local),
+        Err(e) => println!("listening, but on which port? {}", e),
+    }
+    let _ = io::stdout().flush();
+
+    accept_loop(listener, &AtomicBool::new(false), handler);
+}
+
+// A connected chat client, for the demo and the tests
+pub(crate) struct ChatClient {
+    stream: TcpStream,
+    lines: io::Lines<BufReader<TcpStream>>,
+}
+
+impl ChatClient {
+    // Returns once the room has welcomed us
+    pub(crate) fn join(address: SocketAddr, name: &str) -> io::Result<(Self, String)> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut client = ChatClient { lines: BufReader::new(stream.try_clone()?).lines(), stream };
+
+        client.say(name)?;
+        let welcome = client.next_line()?;
+        Ok((client, welcome))
+    }
+
+    pub(crate) fn say(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stream, "{}", line)
+    }
+
+    pub(crate) fn next_line(&mut self) -> io::Result<String> {
+        self.lines.next().unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::UnexpectedEof)))
+    }
+}
+
+fn demo() -> io::Result<()> {
+    let echo_server = Server::spawn("127.0.0.1:0", handle_echo)?;
+    println!("echo server on {}", echo_server.address());
+    println!("echoed: {:?}", echo(echo_server.address(), "hello, TCP")?);
+
+    let room = Arc::new(ChatRoom::default());
+    let chat_room = Arc::clone(&room);
+    let chat_server = Server::spawn("127.0.0.1:0", move |stream| chat_room.handle(stream))?;
+
+    let (mut alice, welcome) = ChatClient::join(chat_server.address(), "alice")?;
+    println!("alice < {}", welcome);
+    let (mut bob, welcome) = ChatClient::join(chat_server.address(), "bob")?;
+    println!("bob   < {}", welcome);
+    println!("alice < {}", alice.next_line()?);
+
+    bob.say("hi alice")?;
+    println!("alice < {}", alice.next_line()?);
+    alice.say("hi bob")?;
+    println!("bob   < {}", bob.next_line()?);
+
+    drop(alice);
+    println!("bob   < {}", bob.next_line()?);
+    drop(bob);
+
+    let events_server = Server::spawn("127.0.0.1:0", handle_events)?;
+    for reply in send_events(events_server.address(), &sample_events())? {
+        println!("events server: {}", reply);
+    }
+
+    // Dropping the servers stops them
+    Ok(())
+}
+
+pub(crate) fn network_demo() {
+    if let Err(e) = demo() {
+        println!("error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echo_server() -> io::Result<()> {
+        let server = Server::spawn("127.0.0.1:0", handle_echo)?;
+        assert_ne!(server.address().port(), 0);
+        assert_eq!(echo(server.address(), "one")?, "one");
+        assert_eq!(echo(server.address(), "two")?, "two");
+        Ok(())
+    }
+
+    #[test]
+    fn chat_messages_reach_the_others() -> io::Result<()> {
+        let room = Arc::new(ChatRoom::default());
+        let handle = Arc::clone(&room);
+        let server = Server::spawn("127.0.0.1:0", move |stream| handle.handle(stream))?;
+
+        let (mut alice, welcome) = ChatClient::join(server.address(), "alice")?;
+        assert_eq!(welcome, "* welcome alice, 0 others here");
+        let (mut bob, welcome) = ChatClient::join(server.address(), "bob")?;
+        assert_eq!(welcome, "* welcome bob, 1 other here");
+        assert_eq!(alice.next_line()?, "* bob joined");
+
+        alice.say("hello")?;
+        assert_eq!(bob.next_line()?, "alice: hello");
+
+        drop(bob);
+        assert_eq!(alice.next_line()?, "* bob left");
+        Ok(())
+    }
+
+    #[test]
+    fn events_are_answered_in_order() -> io::Result<()> {
+        let server = Server::spawn("127.0.0.1:0", handle_events)?;
+        let replies = send_events(server.address(), &sample_events())?;
+        assert_eq!(
+            replies,
+            ["page loaded", "pressed 'x'.", "pasted \"my text\".", "clicked at x=20, y=80.", "page unloaded"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bad_lines_get_an_error() -> io::Result<()> {
+        let server = Server::spawn("127.0.0.1:0", handle_events)?;
+        let mut stream = TcpStream::connect(server.address())?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        writeln!(stream, "{{\"type\": \"Scroll\"}}")?;
+        writeln!(stream, "not json")?;
+        stream.shutdown(Shutdown::Write)?;
+
+        let replies: Vec<String> = BufReader::new(&stream).lines().collect::<io::Result<_>>()?;
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0], "error: unknown WebEvent type `Scroll`");
+        assert!(replies[1].starts_with("error: 1:"), "{}", replies[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn stopping_closes_open_connections() -> io::Result<()> {
+        let server = Server::spawn("127.0.0.1:0", handle_echo)?;
+        let stream = TcpStream::connect(server.address())?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        assert_eq!(echo(server.address(), "ping")?, "ping");
+
+        drop(server);
+        // The server closed its end: end of file, not a timeout
+        let mut line = String::new();
+        assert_eq!(BufReader::new(&stream).read_line(&mut line)?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+// !!!!!!!!!!!!!!!!!!!! Patterns https://doc.rust-lang.org/rust-by-example/flow_control/match.html !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/book/ch18-03-pattern-syntax.html
+
+/*
+A pattern is more than a value to compare with: it takes a value apart, checks its shape and names its pieces,
+all at once. The same patterns work in `match`, `if let`, `while let`, `let` and function parameters.
+
+    - Guards: `Click { x, y } if x == y` matches the shape first, then runs the condition.
+    - Bindings: `n @ 13..=19` checks the range and keeps the value. `rest @ ..` keeps a part of a slice.
+    - Slices: `[first, .., last]` matches any slice with at least two elements.
+    - `ref` and `ref mut` borrow a piece instead of moving it out.
+    - Exhaustiveness: a `match` must cover every possible value. Forgetting a case is a compile error (E0004),
+      which is what makes adding a variant to an enum safe: the compiler lists every `match` to update.
+*/
+
+use crate::compile_fail::CompileFailCase;
+use crate::{Person, WebEvent};
+
+// !!!!!!!!!!!!!!!!!!!! Guards and nested destructuring !!!!!!!!!!!!!!!!!!!!
+
+// The arms are tried from top to bottom: the first one that matches wins, so
+// the specific cases come before the general ones
+pub(crate) fn classify(event: &WebEvent) -> String {
+    match event {
+        // A literal inside a nested pattern
+        WebEvent::Click { x: 0, y: 0 } => String::from("click in the corner"),
+        // `..` ignores the other fields
+        WebEvent::Click { x: 0, .. } | WebEvent::Click { y: 0, .. } => String::from("click on an edge"),
+        // Guards can use every binding of the pattern
+        WebEvent::Click { x, y } if x == y => format!("click on the diagonal at {}", x),
+        WebEvent::Click { x, y } if *x < 0 || *y < 0 => String::from("click outside the page"),
+        // Ranges work on fields too
+        WebEvent::Click { x: 1..=100, y: 1..=100 } => String::from("click in the menu"),
+        WebEvent::Click { x, y } => format!("click at ({}, {})", x, y),
+        WebEvent::KeyPress(c) if c.is_ascii_digit() => format!("digit {}", c),
+        WebEvent::KeyPress(c @ ('a'..='z' | 'A'..='Z')) => format!("letter {}", c),
+        WebEvent::KeyPress(c) => format!("other key {:?}", c),
+        WebEvent::Paste(text) if text.is_empty() => String::from("empty paste"),
+        WebEvent::Paste(text) => format!("pasted {} characters", text.chars().count()),
+        WebEvent::PageLoad | WebEvent::PageUnload => String::from("page event"),
+    }
+}
+
+// Patterns nest as deep as the types do
+pub(crate) fn first_click(events: &[Option<WebEvent>]) -> Option<(i64, i64)> {
+    events.iter().find_map(|event| match event {
+        Some(WebEvent::Click { x, y }) => Some((*x, *y)),
+        _ => None,
+    })
+}
+
+// !!!!!!!!!!!!!!!!!!!! @ bindings !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) fn describe_age(age: u32) -> String {
+    match age {
+        0 => String::from("a newborn"),
+        // Without `n @`, the arm would know the age is in 1..=12, but not
+        // which one
+        n @ 1..=12 => format!("a child of {}", n),
+        n @ 13..=19 => format!("a teen of {}", n),
+        // `@` works with alternatives too
+        n @ (20 | 30 | 40 | 50) => format!("{} today, happy birthday!", n),
+        n => format!("an adult of {}", n),
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Slice patterns !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) fn summarize(numbers: &[i32]) -> String {
+    match numbers {
+        [] => String::from("nothing"),
+        [only] => format!("only {}", only),
+        [first, second] => format!("{} and {}", first, second),
+        // `..` stands for any number of elements, here at least one
+        [first, .., last] if first == last => format!("starts and ends with {}", first),
+        // `middle @ ..` keeps what `..` skipped, as a slice
+        [first, middle @ .., last] => format!("{} to {}, with {} in between", first, last, middle.len()),
+    }
+}
+
+// Recursion on slices, like on lists in functional languages
+pub(crate) fn sum(numbers: &[i32]) -> i32 {
+    match numbers {
+        [] => 0,
+        [head, tail @ ..] => head + sum(tail),
+    }
+}
+
+// Commands of a small REPL, matched word by word
+#[derive(Debug, PartialEq)]
+pub(crate) enum Command<'a> {
+    Add { count: u32, item: &'a str },
+    Remove(&'a str),
+    List,
+    Unknown(Vec<&'a str>),
+}
+
+pub(crate) fn parse_command(line: &str) -> Command<'_> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        // String literals match `&str` elements
+        ["add", count, item] => match count.parse() {
+            Ok(count) => Command::Add { count, item },
+            Err(_) => Command::Unknown(words),
+        },
+        ["add", item] => Command::Add { count: 1, item },
+        ["remove" | "rm", item] => Command::Remove(item),
+        ["list"] | ["ls"] => Command::List,
+        _ => Command::Unknown(words),
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! ref and ref mut !!!!!!!!!!!!!!!!!!!!
+
+/*
+Matching on a value moves its pieces into the bindings. `ref` borrows them instead, so the value can still be used
+after the match. `ref mut` borrows mutably, to change a piece in place.
+
+When the value matched is itself a reference (`match &person`), the bindings become references automatically
+("default binding modes"), which is why `ref` is rarely written in modern code. It's still needed when matching
+on an owned value that must not be moved.
+*/
+
+pub(crate) fn birthday(person: &mut Person) -> String {
+    // `ref mut age`: `age` is a `&mut u8` pointing into `person`
+    let Person { ref name, ref mut age } = *person;
+    *age += 1;
+    format!("{} is now {}", name, age)
+}
+
+// `Option<String>`, only the `String` inside is changed
+pub(crate) fn shout(nickname: &mut Option<String>) {
+    if let Some(ref mut name) = *nickname {
+        name.make_ascii_uppercase();
+    }
+}
+
+fn ref_bindings() {
+    let person = Person { name: String::from("Peter"), age: 27 };
+
+    // Without `ref`, `name` would move out of `person`, which couldn't be
+    // printed whole afterwards
+    match person {
+        Person { ref name, age: 13..=19 } => println!("{} is a teen", name),
+        Person { ref name, age } => println!("{} is {}", name, age),
+    }
+    println!("still usable: {:?}", person);
+
+    // The same with default binding modes: `&person` makes every binding a `&`
+    let Person { name, age } = &person;
+    println!("{} ({}), through references", name, age);
+
+    let mut pair = (String::from("left"), 0);
+    let (ref left, ref mut count) = pair;
+    *count += left.len();
+    println!("{:?}", pair);
+
+    let mut person = person;
+    println!("{}", birthday(&mut person));
+
+    let mut nickname = Some(String::from("spidey"));
+    shout(&mut nickname);
+    println!("{:?}", nickname);
+}
+
+// !!!!!!!!!!!!!!!!!!!! Exhaustiveness !!!!!!!!!!!!!!!!!!!!
+
+// Every variant has an arm: compiles
+pub(crate) const ALL_ARMS: &str = r#"
+pub enum WebEvent { PageLoad, PageUnload, KeyPress(char), Paste(String), Click { x: i64, y: i64 } }
+
+pub fn inspect(event: WebEvent) -> String {
+    match event {
+        WebEvent::PageLoad => String::from("page loaded"),
+        WebEvent::PageUnload => String::from("page unloaded"),
+        WebEvent::KeyPress(c) => format!("pressed '{}'.", c),
+        WebEvent::Paste(s) => format!("pasted \"{}\".", s),
+        WebEvent::Click { x, y } => format!("clicked at x={}, y={}.", x, y),
+    }
+}
+"#;
+
+pub(crate) const MISSING_ARM: CompileFailCase = CompileFailCase {
+    name: "missing_arm",
+    expected_error: "E0004",
+    source: r#"
+pub enum WebEvent { PageLoad, PageUnload, KeyPress(char), Paste(String), Click { x: i64, y: i64 } }
+
+pub fn inspect(event: WebEvent) -> String {
+    // Error! non-exhaustive patterns: `WebEvent::PageUnload` not covered
+    match event {
+        WebEvent::PageLoad => String::from("page loaded"),
+        WebEvent::KeyPress(c) => format!("pressed '{}'.", c),
+        WebEvent::Paste(s) => format!("pasted \"{}\".", s),
+        WebEvent::Click { x, y } => format!("clicked at x={}, y={}.", x, y),
+    }
+}
+"#,
+};
+
+pub(crate) const MISSING_RANGE: CompileFailCase = CompileFailCase {
+    name: "missing_range",
+    expected_error: "E0004",
+    source: r#"
+// Error! non-exhaustive patterns: `20_u8..=u8::MAX` not covered. The compiler
+// knows every value of a `u8`.
+pub fn describe(age: u8) -> &'static str {
+    match age {
+        0 => "newborn",
+        1..=12 => "child",
+        13..=19 => "teen",
+    }
+}
+"#,
+};
+
+pub(crate) const REFUTABLE_LET: CompileFailCase = CompileFailCase {
+    name: "refutable_let",
+    expected_error: "E0005",
+    source: r#"
+// Error! refutable pattern in local binding: `let` must always match, this
+// one doesn't if the slice is empty. `let ... else` or `if let` would work.
+pub fn first(numbers: &[i32]) -> i32 {
+    let [first, ..] = numbers;
+    *first
+}
+"#,
+};
+
+pub(crate) const COMPILE_FAIL_CASES: [&CompileFailCase; 3] = [&MISSING_ARM, &MISSING_RANGE, &REFUTABLE_LET];
+
+pub(crate) fn patterns_demo() {
+    let events = [
+        WebEvent::Click { x: 0, y: 0 },
+        WebEvent::Click { x: 0, y: 50 },
+        WebEvent::Click { x: 7, y: 7 },
+        WebEvent::Click { x: -3, y: 10 },
+        WebEvent::Click { x: 20, y: 80 },
+        WebEvent::Click { x: 200, y: 80 },
+        WebEvent::KeyPress('7'),
+        WebEvent::KeyPress('x'),
+        WebEvent::KeyPress('∞'),
+        WebEvent::Paste(String::new()),
+        WebEvent::Paste(String::from("my text")),
+        WebEvent::PageLoad,
+    ];
+    for event in &events {
+        println!("{:?}: {}", event, classify(event));
+    }
+    println!("first click: {:?}", first_click(&[None, Some(WebEvent::PageLoad), Some(WebEvent::Click { x: 1, y: 2 })]));
+
+    for age in [0, 7, 13, 19, 30, 45] {
+        println!("{}: {}", age, describe_age(age));
+    }
+
+    for numbers in [&[][..], &[1], &[1, 2], &[4, 0, 4], &[1, 2, 3, 4, 5]] {
+        println!("{:?}: {}, sum {}", numbers, summarize(numbers), sum(numbers));
+    }
+
+    for line in ["add 3 apples", "add pear", "rm pear", "ls", "add x apples"] {
+        println!("{:?} -> {:?}", line, parse_command(line));
+    }
+
+    ref_bindings();
+
+    println!(
+        "{} cases in this chapter must fail to compile, run `./main compile-fail` to check them",
+        COMPILE_FAIL_CASES.len()
+    );
+    for case in COMPILE_FAIL_CASES {
+        println!("    {} ({})", case.name, case.expected_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::Outcome;
+
+    #[test]
+    fn guards_are_tried_in_order() {
+        assert_eq!(classify(&WebEvent::Click { x: 0, y: 0 }), "click in the corner");
+        assert_eq!(classify(&WebEvent::Click { x: 5, y: 0 }), "click on an edge");
+        assert_eq!(classify(&WebEvent::Click { x: -1, y: -1 }), "click on the diagonal at -1");
+        assert_eq!(classify(&WebEvent::Click { x: -1, y: 5 }), "click outside the page");
+        assert_eq!(classify(&WebEvent::Click { x: 100, y: 1 }), "click in the menu");
+        assert_eq!(classify(&WebEvent::Click { x: 101, y: 1 }), "click at (101, 1)");
+        assert_eq!(classify(&WebEvent::KeyPress('Q')), "letter Q");
+        assert_eq!(classify(&WebEvent::KeyPress('é')), "other key 'é'");
+    }
+
+    #[test]
+    fn at_bindings() {
+        assert_eq!(describe_age(12), "a child of 12");
+        assert_eq!(describe_age(13), "a teen of 13");
+        assert_eq!(describe_age(19), "a teen of 19");
+        assert_eq!(describe_age(20), "20 today, happy birthday!");
+        assert_eq!(describe_age(21), "an adult of 21");
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(summarize(&[]), "nothing");
+        assert_eq!(summarize(&[9]), "only 9");
+        assert_eq!(summarize(&[4, 0, 4]), "starts and ends with 4");
+        assert_eq!(summarize(&[1, 2, 3, 4]), "1 to 4, with 2 in between");
+        assert_eq!(sum(&[1, 2, 3, 4]), 10);
+
+        assert_eq!(parse_command("add 3 apples"), Command::Add { count: 3, item: "apples" });
+        assert_eq!(parse_command("  add   pear "), Command::Add { count: 1, item: "pear" });
+        assert_eq!(parse_command("rm pear"), Command::Remove("pear"));
+        assert_eq!(parse_command("add x apples"), Command::Unknown(vec!["add", "x", "apples"]));
+        assert_eq!(parse_command(""), Command::Unknown(vec![]));
+    }
+
+    #[test]
+    fn ref_mut_changes_in_place() {
+        let mut person = Person { name: String::from("Peter"), age: 27 };
+        assert_eq!(birthday(&mut person), "Peter is now 28");
+        assert_eq!(person.age, 28);
+
+        let mut nickname = Some(String::from("spidey"));
+        shout(&mut nickname);
+        assert_eq!(nickname.as_deref(), Some("SPIDEY"));
+    }
+
+    // Needs `rustc`: with every arm the match compiles, without one it doesn't
+    #[test]
+    fn removing_an_arm_breaks_the_build() {
+        let complete = CompileFailCase { name: "all_arms", expected_error: "E0004", source: ALL_ARMS };
+        match complete.check().unwrap() {
+            Outcome::Skipped => return,
+            outcome => assert_eq!(outcome, Outcome::Compiled),
+        }
+
+        for case in COMPILE_FAIL_CASES {
+            assert_eq!(case.check().unwrap(), Outcome::FailedAsExpected, "{}", case.name);
+        }
+    }
+}
@@ -0,0 +1,438 @@
+// !!!!!!!!!!!!!!!!!!!! Display, FromStr and CSV for `Person` !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/rust-by-example/conversion/string.html
+
+/*
+`{:?}` is fine for debugging, but a user facing type should implement `Display` (printing) and `FromStr` (parsing).
+When both are implemented for the same format, whatever we print can be parsed back: a "round-trip".
+
+A `Person` can be written in two ways:
+    Peter,27
+    name=Peter age=27
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::num::IntErrorKind;
+use std::str::FromStr;
+
+use crate::rng::XorShift64;
+use crate::Person;
+
+// Everything that can go wrong while parsing a `Person`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ParsePersonError {
+    // Neither "Peter,27" nor "name=Peter age=27"
+    UnknownFormat(String),
+    EmptyName,
+    MissingAge,
+    // The age isn't a number at all, e.g. "twenty"
+    InvalidAge(String),
+    // The age is a number, but doesn't fit in a `u8`, e.g. "300" or "-1"
+    AgeOutOfRange(String),
+}
+
+impl fmt::Display for ParsePersonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownFormat(s) => {
+                write!(f, "can't read a person from '{}', expected 'Peter,27' or 'name=Peter age=27'", s)
+            }
+            Self::EmptyName => write!(f, "the name is empty"),
+            Self::MissingAge => write!(f, "the age is missing"),
+            Self::InvalidAge(s) => write!(f, "the age '{}' is not a number", s),
+            Self::AgeOutOfRange(s) => {
+                write!(f, "the age {} is out of range for `u8` ({}..={})", s, u8::MIN, u8::MAX)
+            }
+        }
+    }
+}
+
+// `Error` only needs `Debug` and `Display`, every method has a default
+impl Error for ParsePersonError {}
+
+// We print the "name=... age=..." form, because it reads well and parses back
+impl fmt::Display for Person {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "name={} age={}", self.name, self.age)
+    }
+}
+
+impl FromStr for Person {
+    type Err = ParsePersonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        // The name may contain spaces or commas itself, so we split at the
+        // *last* separator. Between "name=" and " age=" the name is kept as it
+        // is, spaces around it included, so that what we print parses back.
+        let (name, age) = if let Some(rest) = s.strip_prefix("name=") {
+            match rest.rsplit_once(" age=") {
+                Some(pair) => pair,
+                None => return Err(ParsePersonError::MissingAge),
+            }
+        } else if let Some((name, age)) = s.rsplit_once(',') {
+            (name.trim(), age)
+        } else {
+            return Err(ParsePersonError::UnknownFormat(s.to_string()));
+        };
+
+        Ok(Person { name: parse_name(name)?, age: parse_age(age)? })
+    }
+}
+
+// A name needs something else than spaces, but isn't trimmed: " Peter " is a
+// name of its own
+fn parse_name(name: &str) -> Result<String, ParsePersonError> {
+    if name.trim().is_empty() {
+        return Err(ParsePersonError::EmptyName);
+    }
+
+    Ok(name.to_string())
+}
+
+fn parse_age(text: &str) -> Result<u8, ParsePersonError> {
+    let text = text.trim();
+
+    // Parse into a wider type first, so "300" and "-1" can be told apart
+    // from "twenty"
+    match text.parse::<i64>() {
+        Ok(age) => u8::try_from(age).map_err(|_| ParsePersonError::AgeOutOfRange(text.to_string())),
+        Err(e) => match e.kind() {
+            IntErrorKind::Empty => Err(ParsePersonError::MissingAge),
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                Err(ParsePersonError::AgeOutOfRange(text.to_string()))
+            }
+            _ => Err(ParsePersonError::InvalidAge(text.to_string())),
+        },
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! CSV !!!!!!!!!!!!!!!!!!!!
+
+/*
+Quoting rules (the same ones spreadsheets use, see RFC 4180):
+    - fields are separated by commas and records by new lines
+    - a field containing a comma, a quote, a new line or surrounding spaces is wrapped in quotes
+    - spaces around an unquoted field are trimmed, inside quotes they are kept
+    - a quote inside a quoted field is written twice: She said "hi" -> "She said ""hi"""
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CsvErrorKind {
+    UnterminatedQuote,
+    // Something other than a comma or a new line right after a closing quote
+    UnexpectedCharacter(char),
+    WrongFieldCount(usize),
+    Person(ParsePersonError),
+}
+
+// A CSV error remembers the line where the broken record starts
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CsvError {
+    pub(crate) line: usize,
+    pub(crate) kind: CsvErrorKind,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            CsvErrorKind::UnterminatedQuote => write!(f, "a quoted field is never closed"),
+            CsvErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected '{}' after a closing quote", c),
+            CsvErrorKind::WrongFieldCount(n) => write!(f, "expected 2 fields (name,age), found {}", n),
+            CsvErrorKind::Person(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            CsvErrorKind::Person(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+const CSV_HEADER: &str = "name,age";
+
+pub(crate) fn quote_field(field: &str) -> String {
+    let needs_quotes = field.contains([',', '"', '\n', '\r']) || field.trim() != field;
+
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(crate) fn write_csv(people: &[Person]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+
+    for person in people {
+        out.push_str(&quote_field(&person.name));
+        out.push(',');
+        out.push_str(&person.age.to_string());
+        out.push('\n');
+    }
+
+    out
+}
+
+// The field read so far, trimmed unless it was quoted
+fn take_field(field: &mut String, quoted: &mut bool) -> String {
+    let text = std::mem::take(field);
+    if std::mem::take(quoted) {
+        text
+    } else {
+        text.trim().to_string()
+    }
+}
+
+// Splits the text into records of fields, keeping the line each record starts on
+pub(crate) fn split_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // A quoted field, read until the closing quote
+            '"' if field.is_empty() => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => {
+                            return Err(CsvError { line: record_line, kind: CsvErrorKind::UnterminatedQuote });
+                        }
+                    }
+                }
+
+                match chars.peek() {
+                    None | Some(',') | Some('\n') | Some('\r') => {}
+                    Some(&c) => {
+                        return Err(CsvError { line, kind: CsvErrorKind::UnexpectedCharacter(c) });
+                    }
+                }
+            }
+            ',' => fields.push(take_field(&mut field, &mut quoted)),
+            // "\r\n" line endings are handled by skipping the '\r'
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(take_field(&mut field, &mut quoted));
+                records.push((record_line, std::mem::take(&mut fields)));
+                line += 1;
+                record_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+
+    // The last record doesn't need a trailing new line
+    if !field.is_empty() || quoted || !fields.is_empty() {
+        fields.push(take_field(&mut field, &mut quoted));
+        records.push((record_line, fields));
+    }
+
+    Ok(records)
+}
+
+pub(crate) fn read_csv(text: &str) -> Result<Vec<Person>, CsvError> {
+    let mut people = Vec::new();
+
+    for (index, (line, fields)) in split_records(text)?.into_iter().enumerate() {
+        // The header is optional
+        if index == 0 && fields == ["name", "age"] {
+            continue;
+        }
+
+        // Blank lines are skipped
+        if fields.len() == 1 && fields[0].trim().is_empty() {
+            continue;
+        }
+
+        let [name, age] = fields.as_slice() else {
+            return Err(CsvError { line, kind: CsvErrorKind::WrongFieldCount(fields.len()) });
+        };
+
+        let person = parse_name(name)
+            .and_then(|name| Ok(Person { name, age: parse_age(age)? }))
+            .map_err(|e| CsvError { line, kind: CsvErrorKind::Person(e) })?;
+
+        people.push(person);
+    }
+
+    Ok(people)
+}
+
+// !!!!!!!!!!!!!!!!!!!! Round-trips !!!!!!!!!!!!!!!!!!!!
+
+/*
+A property check: instead of a few hand-picked examples, generate lots of random people and assert that
+printing then parsing always gives back the original value.
+*/
+
+fn random_person(rng: &mut XorShift64) -> Person {
+    const PIECES: [&str; 12] =
+        ["Peter", "Ekrem", "O'Neil", "Anne Marie", "de la Cruz", "Zoë", "\"Doc\"", ",", "=", "age", " ", "\t"];
+
+    let mut name = String::new();
+    for _ in 0..=rng.below(3) {
+        if !name.is_empty() {
+            name.push(*rng.pick(&[' ', ',', '-']));
+        }
+        let piece: &&str = rng.pick(&PIECES);
+        name.push_str(piece);
+    }
+    // Padding is fine, but a name of only spaces isn't one
+    if name.trim().is_empty() {
+        name.push_str("Peter");
+    }
+
+    Person { name, age: rng.below(256) as u8 }
+}
+
+pub(crate) fn person_demo() {
+    let peter: Person = "Peter,27".parse().unwrap();
+    println!("Parsed from \"Peter,27\": {}", peter);
+
+    let same_peter: Person = "name=Peter age=27".parse().unwrap();
+    assert_eq!(peter, same_peter);
+
+    // Descriptive errors
+    for input in ["Peter,300", "Peter,-1", " ,27", "Peter,twenty", "Peter"] {
+        match input.parse::<Person>() {
+            Ok(person) => println!("'{}' -> {}", input, person),
+            Err(e) => println!("'{}' -> error: {}", input, e),
+        }
+    }
+
+    // CSV
+    let people = vec![
+        peter,
+        Person { name: String::from("Smith, John"), age: 41 },
+        Person { name: String::from("\"Doc\" Brown"), age: 65 },
+    ];
+
+    let csv = write_csv(&people);
+    print!("{}", csv);
+    match read_csv(&csv) {
+        Ok(read) => println!("read back {} people, the same ones: {}", read.len(), read == people),
+        Err(e) => println!("error: {}", e),
+    }
+
+    if let Err(e) = read_csv("name,age\nPeter,27\n\"Unclosed,5\n") {
+        println!("error: {}", e);
+    }
+
+    // The property checks, on 1000 random people, are in the tests below
+    let mut rng = XorShift64::new(27);
+    println!("a random person: {}", random_person(&mut rng));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_people() -> Vec<Person> {
+        let mut rng = XorShift64::new(27);
+        (0..1_000).map(|_| random_person(&mut rng)).collect()
+    }
+
+    #[test]
+    fn print_then_parse_round_trips() {
+        for person in random_people() {
+            assert_eq!(person.to_string().parse::<Person>(), Ok(person));
+        }
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let people = random_people();
+        assert_eq!(read_csv(&write_csv(&people)), Ok(people));
+    }
+
+    #[test]
+    fn both_formats_parse() {
+        let peter = Person { name: String::from("Peter"), age: 27 };
+        assert_eq!("Peter,27".parse(), Ok(peter.clone()));
+        assert_eq!(" name=Peter age=27 ".parse(), Ok(peter));
+        // Only the loose "Peter,27" form trims the name
+        assert_eq!(" Peter ,27".parse::<Person>().map(|p| p.name), Ok(String::from("Peter")));
+        assert_eq!("name= Peter\t age=27".parse::<Person>().map(|p| p.name), Ok(String::from(" Peter\t")));
+        // The last separator wins: the name may hold the others
+        assert_eq!("Smith, John,41".parse::<Person>().map(|p| p.name), Ok(String::from("Smith, John")));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |input: &str| input.parse::<Person>().unwrap_err();
+
+        assert_eq!(error("Peter,300"), ParsePersonError::AgeOutOfRange(String::from("300")));
+        assert_eq!(error("Peter,-1"), ParsePersonError::AgeOutOfRange(String::from("-1")));
+        // Doesn't even fit in an `i64`
+        let huge = "99999999999999999999";
+        assert_eq!(error(&format!("Peter,{}", huge)), ParsePersonError::AgeOutOfRange(huge.to_string()));
+        assert_eq!(error(" ,27"), ParsePersonError::EmptyName);
+        assert_eq!(error("name= age=27"), ParsePersonError::EmptyName);
+        assert_eq!(error("Peter,twenty"), ParsePersonError::InvalidAge(String::from("twenty")));
+        assert_eq!(error("Peter,"), ParsePersonError::MissingAge);
+        assert_eq!(error("name=Peter"), ParsePersonError::MissingAge);
+        // No separator at all
+        assert_eq!(error("Peter 27"), ParsePersonError::UnknownFormat(String::from("Peter 27")));
+
+        assert_eq!(error("Peter,300").to_string(), "the age 300 is out of range for `u8` (0..=255)");
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote_field("Peter"), "Peter");
+        assert_eq!(quote_field("Smith, John"), "\"Smith, John\"");
+        assert_eq!(quote_field("She said \"hi\""), "\"She said \"\"hi\"\"\"");
+        assert_eq!(quote_field(" padded "), "\" padded \"");
+        assert_eq!(quote_field("two\nlines"), "\"two\nlines\"");
+
+        let people = read_csv("name,age\r\n\"Smith, John\",41\r\n\r\n\"two\nlines\",3\n").unwrap();
+        assert_eq!(people[0].name, "Smith, John");
+        assert_eq!(people[1].name, "two\nlines");
+
+        // Spaces are layout outside of quotes, and part of the name inside
+        let people = read_csv("  Peter  , 27\n\" Peter\t\",27\n").unwrap();
+        assert_eq!(people[0].name, "Peter");
+        assert_eq!(people[1].name, " Peter\t");
+        let padded = vec![Person { name: String::from(" Peter "), age: 27 }];
+        assert_eq!(read_csv(&write_csv(&padded)), Ok(padded));
+    }
+
+    #[test]
+    fn csv_errors() {
+        let error = |text: &str| read_csv(text).unwrap_err();
+
+        assert_eq!(error("Peter,27\n\"Unclosed,5\n").kind, CsvErrorKind::UnterminatedQuote);
+        assert_eq!(error("\"Doc\"x,5").kind, CsvErrorKind::UnexpectedCharacter('x'));
+        assert_eq!(error("Peter,27,extra").kind, CsvErrorKind::WrongFieldCount(3));
+
+        let out_of_range = error("name,age\nPeter,27\nAnne,300\n");
+        assert_eq!(out_of_range.line, 3);
+        assert_eq!(out_of_range.kind, CsvErrorKind::Person(ParsePersonError::AgeOutOfRange(String::from("300"))));
+        assert!(out_of_range.source().is_some());
+    }
+}
@@ -0,0 +1,615 @@
+// !!!!!!!!!!!!!!!!!!!! Serving HTTP https://doc.rust-lang.org/book/ch20-01-single-threaded.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+`./main serve [address]` shows the lessons in a browser: every lesson gets a page with its prose, its code and what
+it printed. HTTP/1.1 is text over a TCP connection (network.rs), which is why a few dozen lines of std are enough
+for a server that only talks to a browser on the same machine:
+
+    GET /lessons/closures HTTP/1.1          the request line: method, path, version
+    Host: 127.0.0.1:8000                    headers, one per line...
+    Accept: text/html
+                                            ...until an empty line
+    HTTP/1.1 200 OK                         the response: status line
+    Content-Type: text/html; charset=utf-8
+    Content-Length: 5120                    how many bytes of body follow
+    Connection: close                       this server answers one request per connection
+                                            empty line, then the body
+    <!DOCTYPE html>...
+
+Lines end with "\r\n". The pages:
+
+    /                   every lesson, with its description
+    /lessons/<lesson>   a lesson: prose, code and output
+    /run/<lesson>       runs the lesson again and returns what it printed, as plain text
+
+A lesson's output comes from running `./main run <lesson>` as a child process: `println!` can't be redirected
+to a string from inside the process, but a child's stdout can be read from its parent. The first run of each
+lesson is kept, `/run/` always starts a new one. A run still going after 30 seconds is killed, and the page
+is an error.
+*/
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::highlight;
+use crate::macros::LessonEntry;
+use crate::network::accept_loop;
+
+// The request line and the headers together, anything longer is refused
+const MAX_HEAD: u64 = 16 * 1024;
+
+// A browser that connects and sends nothing doesn't keep a thread forever
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A lesson stuck in a loop doesn't keep a thread (and a process) forever
+// either. The slowest lesson takes well under a second.
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// !!!!!!!!!!!!!!!!!!!! Requests and responses !!!!!!!!!!!!!!!!!!!!
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Request {
+    pub(crate) method: String,
+    // Without the query string: "/run/closures?again" is "/run/closures"
+    pub(crate) path: String,
+}
+
+#[derive(Debug)]
+pub(crate) enum RequestError {
+    Io(io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Io(e) => write!(f, "can't read the request: {}", e),
+            RequestError::Malformed(reason) => write!(f, "malformed request: {}", reason),
+        }
+    }
+}
+
+impl Error for RequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RequestError::Io(e) => Some(e),
+            RequestError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for RequestError {
+    fn from(e: io::Error) -> Self {
+        RequestError::Io(e)
+    }
+}
+
+// Reads the request line and skips the headers: none of the pages needs them,
+// and GET requests have no body
+pub(crate) fn read_request(reader: impl BufRead) -> Result<Request, RequestError> {
+    let mut head = reader.take(MAX_HEAD);
+
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let (method, path) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        [method, target, version] if version.starts_with("HTTP/1.") => (method.to_string(), target),
+        _ => return Err(RequestError::Malformed(format!("request line {:?}", line.trim_end()))),
+    };
+    let path = path.split('?').next().unwrap_or_default().to_string();
+
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 {
+            return Err(RequestError::Malformed(String::from("the headers never end")));
+        }
+        if header.trim_end().is_empty() {
+            return Ok(Request { method, path });
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) content_type: &'static str,
+    pub(crate) body: String,
+}
+
+impl Response {
+    pub(crate) fn html(status: u16, body: String) -> Self {
+        Response { status, content_type: "text/html; charset=utf-8", body }
+    }
+
+    pub(crate) fn text(status: u16, body: String) -> Self {
+        Response { status, content_type: "text/plain; charset=utf-8", body }
+    }
+
+    pub(crate) fn write_to(&self, mut out: impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        // `len()` counts bytes, like Content-Length
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        )?;
+        out.write_all(self.body.as_bytes())?;
+        out.flush()
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Lesson pages !!!!!!!!!!!!!!!!!!!!
+
+// Every lesson file starts with a `/* ... */` block of prose, after its title
+// comments. Returns the prose and the code below it.
+pub(crate) fn split_prose(source: &str) -> (&str, &str) {
+    let Some(start) = source.find("\n/*\n") else {
+        return ("", source);
+    };
+    let prose = &source[start + 4..];
+    match prose.find("\n*/\n") {
+        Some(end) => (&prose[..end], prose[end + 4..].trim_start_matches('\n')),
+        None => ("", source),
+    }
+}
+
+// Every link of the comments and the prose, in order and without duplicates:
+// the rust-by-example pages a lesson follows
+pub(crate) fn references(source: &str) -> Vec<&str> {
+    let (prose, _) = split_prose(source);
+    let comments = source.lines().filter(|line| line.trim_start().starts_with("//"));
+
+    let mut links: Vec<&str> = Vec::new();
+    for line in comments.chain(prose.lines()) {
+        for (start, _) in line.match_indices("https://") {
+            let link = &line[start..];
+            let end = link.find(|c: char| c.is_whitespace() || "()`\"<>".contains(c)).unwrap_or(link.len());
+            let link = link[..end].trim_end_matches(['.', ',', ':']);
+            if !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// `code` between backticks, and links
+fn inline_html(text: &str) -> String {
+    let mut html = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            let _ = write!(html, "<code>{}</code>", escape_html(part));
+            continue;
+        }
+        for (j, word) in part.split(' ').enumerate() {
+            if j > 0 {
+                html.push(' ');
+            }
+            match word.strip_prefix("https://") {
+                Some(_) => {
+                    let link = word.trim_end_matches(['.', ',', ':', ')']);
+                    let rest = &word[link.len()..];
+                    let _ = write!(html, "<a href=\"{0}\">{0}</a>{1}", escape_html(link), escape_html(rest));
+                }
+                None => html.push_str(&escape_html(word)),
+            }
+        }
+    }
+    html
+}
+
+// Paragraphs are separated by empty lines. Indented ones are tables, lists and
+// examples: they keep their layout.
+pub(crate) fn prose_html(prose: &str) -> String {
+    let mut html = String::new();
+    for paragraph in prose.split("\n\n").map(|p| p.trim_matches('\n')).filter(|p| !p.is_empty()) {
+        if paragraph.lines().all(|line| line.starts_with("    ") || line.trim().is_empty()) {
+            let _ = writeln!(html, "<pre>{}</pre>", escape_html(paragraph));
+        } else {
+            let _ = writeln!(html, "<p>{}</p>", inline_html(&paragraph.replace('\n', " ")));
+        }
+    }
+    html
+}
+
+const STYLE: &str = "
+body { max-width: 60em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+pre { background: #f6f6f4; padding: 0.8em; overflow-x: auto; }
+nav { display: flex; gap: 2em; border-bottom: 1px solid #ddd; padding-bottom: 0.5em; }
+.comment { color: #6a737d; } .string { color: #22863a; } .keyword { color: #d73a49; }
+.macro { color: #6f42c1; } .number { color: #005cc5; } .lifetime { color: #b08800; } .attribute { color: #b31d28; }
+";
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+// How pages link to each other: the server's routes, or files next to each
+// other for `./main export html` (export.rs)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Links {
+    Server,
+    Files,
+}
+
+impl Links {
+    fn index(self) -> &'static str {
+        match self {
+            Links::Server => "/",
+            Links::Files => "index.html",
+        }
+    }
+
+    fn lesson(self, id: &str) -> String {
+        match self {
+            Links::Server => format!("/lessons/{}", id),
+            Links::Files => format!("{}.html", id),
+        }
+    }
+}
+
+pub(crate) fn index_page(lessons: &[LessonEntry], links: Links) -> String {
+    let mut body = String::from("<h1>Rust notes</h1>\n<ul>\n");
+    for lesson in lessons {
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{}\">{}</a>: {}</li>",
+            links.lesson(lesson.id),
+            escape_html(lesson.title),
+            escape_html(lesson.body)
+        );
+    }
+    body.push_str("</ul>\n");
+    page("Rust notes", &body)
+}
+
+// The lesson at `index`, with links to the lessons before and after it
+pub(crate) fn lesson_page(lessons: &[LessonEntry], index: usize, output: &str, links: Links) -> String {
+    let lesson = &lessons[index];
+    let (prose, code) = split_prose(lesson.source);
+
+    let mut body = format!("<nav>\n<a href=\"{}\">all lessons</a>\n", links.index());
+    if let Some(previous) = index.checked_sub(1).map(|i| &lessons[i]) {
+        let _ = writeln!(body, "<a href=\"{}\">&larr; {}</a>", links.lesson(previous.id), escape_html(previous.title));
+    }
+    if let Some(next) = lessons.get(index + 1) {
+        let _ = writeln!(body, "<a href=\"{}\">{} &rarr;</a>", links.lesson(next.id), escape_html(next.title));
+    }
+    body.push_str("</nav>\n");
+
+    let _ = writeln!(body, "<h1>{}</h1>\n<p><em>{}</em></p>", escape_html(lesson.title), escape_html(lesson.body));
+    let references = references(lesson.source);
+    if !references.is_empty() {
+        body.push_str("<ul>\n");
+        for link in references {
+            let _ = writeln!(body, "<li><a href=\"{0}\">{0}</a></li>", escape_html(link));
+        }
+        body.push_str("</ul>\n");
+    }
+    body.push_str(&prose_html(prose));
+
+    let _ = writeln!(body, "<h2>Code: {}.rs</h2>\n<pre><code>{}</code></pre>", lesson.id, highlight::to_html(code));
+    // A static page can't run anything
+    let again = match links {
+        Links::Server => format!(", <a href=\"/run/{}\">run it again</a>", lesson.id),
+        Links::Files => String::new(),
+    };
+    let _ = writeln!(
+        body,
+        "<h2>Output</h2>\n<p>What <code>./main run {}</code> printed{}.</p>\n<pre>{}</pre>",
+        lesson.id,
+        again,
+        escape_html(output)
+    );
+    page(lesson.title, &body)
+}
+
+// What `<program> run <lesson>` prints to stdout, then to stderr
+pub(crate) fn run_lesson(program: &Path, id: &str) -> io::Result<String> {
+    run_notes(program, &["run", id])
+}
+
+// The same for any command of the notes, `&[]` being the notes of main.rs
+pub(crate) fn run_notes(program: &Path, args: &[&str]) -> io::Result<String> {
+    run_with_timeout(Command::new(program).args(args), RUN_TIMEOUT)
+}
+
+// Reads all of `pipe` on a thread of its own
+fn read_in_background(pipe: Option<impl io::Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+// `command.output()`, except that a child still running after `timeout` is
+// killed, and the answer is an error
+fn run_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<String> {
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // Both pipes are read while the child runs: a child printing more than a
+    // pipe holds would wait for us to read, and never end
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    // There's no `wait` with a timeout in std: ask every few milliseconds
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, format!("still running after {:?}", timeout)));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let mut printed = String::from_utf8_lossy(&stdout.join().unwrap_or_default()).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&stderr.join().unwrap_or_default()));
+    if !status.success() {
+        let _ = writeln!(printed, "({})", status);
+    }
+    Ok(printed)
+}
+
+// !!!!!!!!!!!!!!!!!!!! The server !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) struct Site {
+    lessons: Vec<LessonEntry>,
+    // The notes' own binary, run as `<program> run <lesson>`
+    program: PathBuf,
+    // The first output of every lesson
+    outputs: Mutex<HashMap<&'static str, String>>,
+}
+
+impl Site {
+    pub(crate) fn new(lessons: &[LessonEntry], program: PathBuf) -> Self {
+        Site { lessons: lessons.to_vec(), program, outputs: Mutex::default() }
+    }
+
+    // The lock isn't held while the lesson runs: other pages don't wait for it.
+    // Two first visits at once may both run it, the last one wins.
+    fn first_output(&self, lesson: &LessonEntry) -> io::Result<String> {
+        if let Some(output) = self.outputs.lock().unwrap().get(lesson.id) {
+            return Ok(output.clone());
+        }
+        let output = run_lesson(&self.program, lesson.id)?;
+        self.outputs.lock().unwrap().insert(lesson.id, output.clone());
+        Ok(output)
+    }
+
+    pub(crate) fn respond(&self, request: &Request) -> Response {
+        if request.method != "GET" {
+            return Response::text(405, format!("{} isn't supported, only GET\n", request.method));
+        }
+        let not_found = || {
+            Response::html(404, page("Not found", "<h1>Not found</h1>\n<p><a href=\"/\">all lessons</a></p>\n"))
+        };
+        let find = |id: &str| self.lessons.iter().position(|lesson| lesson.id == id);
+
+        let (route, id) = match request.path.trim_start_matches('/').split_once('/') {
+            Some((route, id)) => (route, id),
+            None => (request.path.trim_start_matches('/'), ""),
+        };
+        match (route, find(id)) {
+            ("", _) => Response::html(200, index_page(&self.lessons, Links::Server)),
+            ("lessons", Some(index)) => match self.first_output(&self.lessons[index]) {
+                Ok(output) => Response::html(200, lesson_page(&self.lessons, index, &output, Links::Server)),
+                Err(e) => Response::text(500, format!("can't run {}: {}\n", id, e)),
+            },
+            ("run", Some(index)) => match run_lesson(&self.program, self.lessons[index].id) {
+                Ok(output) => Response::text(200, output),
+                Err(e) => Response::text(500, format!("can't run {}: {}\n", id, e)),
+            },
+            _ => not_found(),
+        }
+    }
+
+    // One request per connection
+    pub(crate) fn handle(&self, stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let response = match read_request(BufReader::new(&stream)) {
+            Ok(request) => self.respond(&request),
+            // The browser gave up: nobody to answer
+            Err(RequestError::Io(_)) => return,
+            Err(e) => Response::text(400, format!("{}\n", e)),
+        };
+        let _ = response.write_to(&stream);
+    }
+}
+
+// `./main serve [address]`
+pub(crate) fn serve_main(lessons: &[LessonEntry], args: &[String]) {
+    let address = match args {
+        [] => "127.0.0.1:8000",
+        [address] => address.as_str(),
+        _ => {
+            eprintln!("usage: ./main serve [address], 127.0.0.1:8000 by default");
+            std::process::exit(2);
+        }
+    };
+
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("error: can't find the notes' own binary: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: can't listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    // The tests bind to port 0 and read the real port from this line
+    match listener.local_addr() {
+        Ok(local) => println!("serving the notes on http://{}/", local),
+        Err(e) => println!("serving the notes, but on which port? {}", e),
+    }
+    let _ = io::stdout().flush();
+
+    let site = Arc::new(Site::new(lessons, program));
+    accept_loop(listener, &AtomicBool::new(false), Arc::new(move |stream| site.handle(stream)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str) -> Request {
+        Request { method: String::from("GET"), path: path.to_string() }
+    }
+
+    #[test]
+    fn requests() {
+        let request = read_request(&b"GET /run/closures?again HTTP/1.1\r\nHost: localhost\r\n\r\n"[..]).unwrap();
+        assert_eq!(request, get("/run/closures"));
+
+        let bad = read_request(&b"hello\r\n\r\n"[..]).unwrap_err();
+        assert_eq!(bad.to_string(), "malformed request: request line \"hello\"");
+        assert!(matches!(read_request(&b"GET / HTTP/1.1\r\nHost: x\r\n"[..]), Err(RequestError::Malformed(_))));
+
+        // Headers that never end
+        let endless = format!("GET / HTTP/1.1\r\n{}", "X-Padding: 0123456789\r\n".repeat(1000));
+        assert!(read_request(endless.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn responses() {
+        let mut bytes = Vec::new();
+        Response::text(404, String::from("∞\n")).write_to(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 4\r\n\
+             Connection: close\r\n\r\n∞\n"
+        );
+    }
+
+    #[test]
+    fn every_lesson_has_prose_and_code() {
+        for lesson in crate::LESSONS {
+            let (prose, code) = split_prose(lesson.source);
+            assert!(!prose.is_empty(), "{} has no prose", lesson.id);
+            assert!(!code.starts_with('\n') && code.contains("fn "), "{}", lesson.id);
+        }
+
+        let (prose, code) = split_prose(crate::LESSONS[0].source);
+        assert!(prose.starts_with("`{:?}` is fine for debugging"), "{}", prose);
+        assert!(code.starts_with("use "), "{}", code);
+    }
+
+    #[test]
+    fn references_come_from_comments_and_prose() {
+        let source = "// !!! Closures https://doc.rust-lang.org/rust-by-example/fn/closures.html !!!\n\
+                      /*\nSee (https://doc.rust-lang.org/book/ch13-01-closures.html).\n*/\n\
+                      // again https://doc.rust-lang.org/rust-by-example/fn/closures.html\n\
+                      let url = \"https://example.com\";\n";
+        assert_eq!(
+            references(source),
+            [
+                "https://doc.rust-lang.org/rust-by-example/fn/closures.html",
+                "https://doc.rust-lang.org/book/ch13-01-closures.html"
+            ]
+        );
+    }
+
+    #[test]
+    fn prose() {
+        assert_eq!(
+            prose_html("Calls `f(&x)`, see https://example.com.\n\n    a < b\n    c\n"),
+            "<p>Calls <code>f(&amp;x)</code>, see <a href=\"https://example.com\">https://example.com</a>.</p>\n\
+             <pre>    a &lt; b\n    c</pre>\n"
+        );
+    }
+
+    #[test]
+    fn pages() {
+        // Nothing here runs a lesson: only pages that don't need the notes' binary
+        let site = Site::new(&crate::LESSONS, PathBuf::from("no-such-program"));
+
+        let index = site.respond(&get("/"));
+        assert_eq!((index.status, index.content_type), (200, "text/html; charset=utf-8"));
+        assert!(index.body.contains("<a href=\"/lessons/closures\">Closures</a>"));
+
+        assert_eq!(site.respond(&get("/lessons/nothing")).status, 404);
+        assert_eq!(site.respond(&get("/elsewhere")).status, 404);
+        assert_eq!(site.respond(&Request { method: String::from("POST"), path: String::from("/") }).status, 405);
+        assert_eq!(site.respond(&get("/run/closures")).status, 500);
+
+        let closures = crate::macros::find_lesson(&crate::LESSONS, "closures").unwrap();
+        let index = crate::LESSONS.iter().position(|lesson| lesson.id == "closures").unwrap();
+        let page = lesson_page(&crate::LESSONS, index, "called <3 times", Links::Server);
+        assert!(page.contains(&format!("<h1>{}</h1>", closures.title)));
+        assert!(page.contains("<a href=\"/lessons/smart_pointers\">&larr; Rc, RefCell and Weak</a>"));
+        assert!(page.contains("<a href=\"https://doc.rust-lang.org/rust-by-example/fn/closures.html\">"));
+        assert!(page.contains("<pre>called &lt;3 times</pre>"));
+        assert!(page.contains("<a href=\"/run/closures\">run it again</a>"));
+
+        let file = lesson_page(&crate::LESSONS, index, "", Links::Files);
+        assert!(file.contains("<a href=\"smart_pointers.html\">&larr; Rc, RefCell and Weak</a>"));
+        assert!(!file.contains("/run/"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn children_are_timed() {
+        let printed = run_with_timeout(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            Duration::from_secs(30),
+        );
+        assert_eq!(printed.unwrap(), "out\nerr\n(exit status: 3)\n");
+
+        // More than a pipe holds
+        let printed =
+            run_with_timeout(Command::new("sh").args(["-c", "yes | head -c 1000000"]), Duration::from_secs(30));
+        assert_eq!(printed.unwrap().len(), 1_000_000);
+
+        let start = Instant::now();
+        let error = run_with_timeout(Command::new("sleep").arg("10"), Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
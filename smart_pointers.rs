@@ -0,0 +1,248 @@
+// !!!!!!!!!!!!!!!!!!!! Rc, RefCell and Weak https://doc.rust-lang.org/rust-by-example/std/rc.html !!!!!!!!!!!!!!!!!!!!
+// https://doc.rust-lang.org/book/ch15-00-smart-pointers.html
+
+/*
+`Box<T>` has exactly one owner. Some data has several: a node of a tree is owned by its parent, but its children
+want to point back to it too.
+
+    - `Rc<T>` ("reference counted") can be cloned: every clone points to the same value, and counts as one more
+      owner. The value is dropped when the last clone is. `Rc::strong_count` tells how many there are.
+    - An `Rc` only gives `&T`, since other owners may be reading. `RefCell<T>` moves the borrow rules from compile
+      time to run time: `borrow()` and `borrow_mut()` count the borrows, and breaking the rules is a panic (or an
+      `Err` with `try_borrow_mut`) instead of a compile error. `Rc<RefCell<T>>` is a value with several owners
+      that can all change it.
+    - Two `Rc`s pointing at each other keep each other alive forever: a leak, even though nothing is `unsafe`.
+      `Weak<T>` is a pointer that doesn't own: it doesn't keep the value alive, and `upgrade()` returns `None` once
+      the value is gone. Parents own their children with `Rc`, children point back with `Weak`.
+*/
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::alloc_counter;
+
+// !!!!!!!!!!!!!!!!!!!! A tree with parent pointers !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) type NodeRef = Rc<RefCell<Node>>;
+
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) name: String,
+    pub(crate) children: Vec<NodeRef>,
+    // `Weak`: a child doesn't own its parent
+    pub(crate) parent: Weak<RefCell<Node>>,
+}
+
+impl Node {
+    pub(crate) fn new(name: &str) -> NodeRef {
+        Rc::new(RefCell::new(Node { name: name.to_string(), children: Vec::new(), parent: Weak::new() }))
+    }
+}
+
+pub(crate) fn add_child(parent: &NodeRef, child: NodeRef) {
+    child.borrow_mut().parent = Rc::downgrade(parent);
+    parent.borrow_mut().children.push(child);
+}
+
+// "notes/chapters/closures": follows the parents up to the root
+pub(crate) fn path(node: &NodeRef) -> String {
+    let mut names = vec![node.borrow().name.clone()];
+
+    // `upgrade` turns the `Weak` back into an `Rc`, if the parent still exists
+    let mut parent = node.borrow().parent.upgrade();
+    while let Some(current) = parent {
+        names.push(current.borrow().name.clone());
+        parent = current.borrow().parent.upgrade();
+    }
+
+    names.reverse();
+    names.join("/")
+}
+
+pub(crate) fn counts(node: &NodeRef) -> (usize, usize) {
+    (Rc::strong_count(node), Rc::weak_count(node))
+}
+
+fn print_counts(label: &str, node: &NodeRef) {
+    let (strong, weak) = counts(node);
+    println!("{:<36} {}: strong = {}, weak = {}", label, node.borrow().name, strong, weak);
+}
+
+fn tree() {
+    let leaf = Node::new("closures");
+    print_counts("leaf created", &leaf);
+
+    {
+        let chapters = Node::new("chapters");
+        add_child(&chapters, Rc::clone(&leaf));
+        // The leaf now has two owners: `leaf` and `chapters`
+        print_counts("leaf added to chapters", &leaf);
+        // `chapters` is owned once, and pointed at by one `Weak`
+        print_counts("chapters has a child", &chapters);
+
+        let root = Node::new("notes");
+        add_child(&root, Rc::clone(&chapters));
+        print_counts("chapters added to the root", &chapters);
+
+        println!("path of the leaf: {}", path(&leaf));
+        let names: Vec<_> = root.borrow().children.iter().map(|child| child.borrow().name.clone()).collect();
+        println!("children of the root: {:?}", names);
+
+        // `root` and `chapters` go out of scope here. The leaf's `Weak` didn't
+        // keep its parent alive.
+    }
+
+    print_counts("the parents are gone", &leaf);
+    println!("parent of the leaf: {:?}", leaf.borrow().parent.upgrade().map(|p| p.borrow().name.clone()));
+    println!("path of the leaf: {}", path(&leaf));
+}
+
+// !!!!!!!!!!!!!!!!!!!! A reference cycle !!!!!!!!!!!!!!!!!!!!
+
+pub(crate) struct Link {
+    pub(crate) next: RefCell<Option<Rc<Link>>>,
+    pub(crate) back: RefCell<Weak<Link>>,
+}
+
+impl Link {
+    fn new() -> Rc<Link> {
+        Rc::new(Link { next: RefCell::new(None), back: RefCell::new(Weak::new()) })
+    }
+}
+
+// Two links pointing at each other: `a -> b` always owns, `b -> a` owns only
+// if `strong_back`. Returns a `Weak` to `a`, to check whether it survived.
+pub(crate) fn make_pair(strong_back: bool) -> Weak<Link> {
+    let a = Link::new();
+    let b = Link::new();
+
+    *a.next.borrow_mut() = Some(Rc::clone(&b));
+    if strong_back {
+        *b.next.borrow_mut() = Some(Rc::clone(&a));
+    } else {
+        *b.back.borrow_mut() = Rc::downgrade(&a);
+    }
+
+    Rc::downgrade(&a)
+    // `a` and `b` are dropped here. With a strong cycle, each count only goes
+    // from 2 down to 1: neither link is freed.
+}
+
+// The counting allocator (alloc_counter.rs) sees the leak
+fn cycles() {
+    // The `Weak` is dropped inside the measurement too: as long as a `Weak`
+    // exists, the block holding the counts can't be freed (the value can)
+    let (alive, stats) = alloc_counter::measure(|| make_pair(false).upgrade().is_some());
+    println!("with a Weak back pointer: {} (a alive: {})", stats, alive);
+
+    let (a, stats) = alloc_counter::measure(|| make_pair(true));
+    println!("with an Rc back pointer:  {} (a alive: {})", stats, a.upgrade().is_some());
+    assert_eq!(stats.leaked(), 2);
+
+    // Breaking the cycle by hand frees both links: `a` goes, taking the last
+    // owner of `b` with it
+    if let Some(a) = a.upgrade() {
+        let b = a.next.borrow_mut().take();
+        drop(b);
+    }
+    println!("after breaking the cycle, a alive: {}", a.upgrade().is_some());
+}
+
+// !!!!!!!!!!!!!!!!!!!! The borrow rules, at run time !!!!!!!!!!!!!!!!!!!!
+
+/*
+The same rules as the borrowing section of main.rs: any number of `&`, or one `&mut`. With references, breaking
+them doesn't compile. With a `RefCell`, it compiles, and `borrow_mut()` panics with "already borrowed:
+BorrowMutError" when the program runs. `try_borrow_mut()` returns that error instead of panicking.
+*/
+fn borrow_rules() {
+    let shared = Rc::new(RefCell::new(vec![1, 2, 3]));
+    let owner = Rc::clone(&shared);
+
+    // Any number of readers
+    let first = shared.borrow();
+    let second = owner.borrow();
+    println!("two readers: {:?} {:?}", *first, *second);
+
+    // No writer while they read. With `&` and `&mut` this is a compile error.
+    match owner.try_borrow_mut() {
+        Ok(_) => println!("borrowed mutably"),
+        Err(e) => println!("can't write while reading: {:?} ({})", e, e),
+    }
+
+    // The readers are done, the writer can go
+    drop(first);
+    drop(second);
+    owner.borrow_mut().push(4);
+    println!("both owners see the push: {:?}", shared.borrow());
+
+    // Only one writer at a time
+    let writer = shared.borrow_mut();
+    println!("second writer: {:?}", owner.try_borrow_mut().map(|_| ()));
+    drop(writer);
+}
+
+pub(crate) fn smart_pointers_demo() {
+    tree();
+    cycles();
+    borrow_rules();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_follow_the_owners() {
+        let leaf = Node::new("leaf");
+        assert_eq!(counts(&leaf), (1, 0));
+
+        let parent = Node::new("parent");
+        add_child(&parent, Rc::clone(&leaf));
+        assert_eq!(counts(&leaf), (2, 0));
+        assert_eq!(counts(&parent), (1, 1));
+        assert_eq!(path(&leaf), "parent/leaf");
+
+        drop(parent);
+        assert_eq!(counts(&leaf), (1, 0));
+        assert!(leaf.borrow().parent.upgrade().is_none());
+        assert_eq!(path(&leaf), "leaf");
+    }
+
+    #[test]
+    fn only_strong_cycles_keep_values_alive() {
+        assert!(make_pair(false).upgrade().is_none());
+
+        let a = make_pair(true);
+        let alive = a.upgrade().expect("the cycle keeps `a` alive");
+        // `a` is owned by `b`, and by `alive`
+        assert_eq!(Rc::strong_count(&alive), 2);
+
+        // Break it, or this test leaks
+        alive.next.borrow_mut().take();
+        drop(alive);
+        assert!(a.upgrade().is_none());
+    }
+
+    #[test]
+    fn runtime_borrow_errors() {
+        let cell = RefCell::new(0);
+        let reader = cell.borrow();
+        assert!(cell.try_borrow().is_ok());
+        assert!(cell.try_borrow_mut().is_err());
+        drop(reader);
+
+        let writer = cell.borrow_mut();
+        assert!(cell.try_borrow().is_err());
+        drop(writer);
+        assert!(cell.try_borrow_mut().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn borrow_mut_panics_while_borrowed() {
+        let cell = RefCell::new(0);
+        let _reader = cell.borrow();
+        let _writer = cell.borrow_mut();
+    }
+}
@@ -0,0 +1,444 @@
+// !!!!!!!!!!!!!!!!!!!! Teacher dashboard !!!!!!!!!!!!!!!!!!!!
+
+/*
+`Role::Teacher` finally gets something to do: reading the progress of every student and finding the
+lessons where people are stuck.
+
+Every student has a progress file named after them, e.g. `progress/peter.progress`:
+
+    lesson,completed,attempts,failed
+    Structs,true,2,0
+    Borrowing,false,6,5
+
+`attempts` counts exercise attempts and `failed` how many of them didn't pass. The files use the same
+CSV quoting rules as person.rs, so a lesson title may contain commas if it's quoted.
+
+Run it with:
+    ./main teacher progress/          (text report)
+    ./main teacher progress/ --csv    (CSV report, e.g. for a spreadsheet)
+*/
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::person::{quote_field, split_records, CsvError};
+
+pub(crate) const PROGRESS_EXTENSION: &str = "progress";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LessonProgress {
+    pub(crate) lesson: String,
+    pub(crate) completed: bool,
+    pub(crate) attempts: u32,
+    pub(crate) failed: u32,
+}
+
+impl LessonProgress {
+    // Most of this student's attempts failed. In `u64`, where doubling a `u32`
+    // can't overflow
+    fn is_failing(&self) -> bool {
+        u64::from(self.failed) * 2 > u64::from(self.attempts)
+    }
+
+    // Started, but not finished
+    fn is_stuck(&self) -> bool {
+        self.attempts > 0 && !self.completed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StudentProgress {
+    pub(crate) student: String,
+    pub(crate) lessons: Vec<LessonProgress>,
+}
+
+#[derive(Debug)]
+pub(crate) enum DashboardError {
+    Io(PathBuf, io::Error),
+    Csv(PathBuf, CsvError),
+    InvalidRecord { path: PathBuf, line: usize, message: String },
+    NoStudents(PathBuf),
+}
+
+impl fmt::Display for DashboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Csv(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::InvalidRecord { path, line, message } => write!(f, "{}: line {}: {}", path.display(), line, message),
+            Self::NoStudents(path) => {
+                write!(f, "{}: no `*.{}` files found", path.display(), PROGRESS_EXTENSION)
+            }
+        }
+    }
+}
+
+impl Error for DashboardError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Csv(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// `path` is only used in error messages
+pub(crate) fn parse_progress(student: &str, text: &str, path: &Path) -> Result<StudentProgress, DashboardError> {
+    let records = split_records(text).map_err(|e| DashboardError::Csv(path.to_path_buf(), e))?;
+    let mut lessons = Vec::new();
+
+    for (index, (line, fields)) in records.into_iter().enumerate() {
+        if index == 0 && fields == ["lesson", "completed", "attempts", "failed"] {
+            continue;
+        }
+
+        if fields.len() == 1 && fields[0].trim().is_empty() {
+            continue;
+        }
+
+        let invalid = |message: String| DashboardError::InvalidRecord { path: path.to_path_buf(), line, message };
+
+        let [lesson, completed, attempts, failed] = fields.as_slice() else {
+            return Err(invalid(format!("expected 4 fields (lesson,completed,attempts,failed), found {}", fields.len())));
+        };
+
+        let completed = match completed.trim() {
+            "true" | "yes" => true,
+            "false" | "no" => false,
+            other => return Err(invalid(format!("`completed` should be true or false, found '{}'", other))),
+        };
+
+        let count = |name: &str, value: &str| {
+            value.trim().parse::<u32>().map_err(|e| invalid(format!("`{}` '{}': {}", name, value, e)))
+        };
+        let attempts = count("attempts", attempts)?;
+        let failed = count("failed", failed)?;
+
+        if failed > attempts {
+            return Err(invalid(format!("{} failed out of only {} attempts", failed, attempts)));
+        }
+
+        lessons.push(LessonProgress { lesson: lesson.trim().to_string(), completed, attempts, failed });
+    }
+
+    Ok(StudentProgress { student: student.to_string(), lessons })
+}
+
+// Reads every `*.progress` file of a directory, sorted by student name
+pub(crate) fn read_progress_dir(dir: &Path) -> Result<Vec<StudentProgress>, DashboardError> {
+    let entries = fs::read_dir(dir).map_err(|e| DashboardError::Io(dir.to_path_buf(), e))?;
+    let mut students = Vec::new();
+
+    for entry in entries {
+        let path = entry.map_err(|e| DashboardError::Io(dir.to_path_buf(), e))?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some(PROGRESS_EXTENSION) {
+            continue;
+        }
+
+        let student = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let text = fs::read_to_string(&path).map_err(|e| DashboardError::Io(path.clone(), e))?;
+
+        students.push(parse_progress(&student, &text, &path)?);
+    }
+
+    if students.is_empty() {
+        return Err(DashboardError::NoStudents(dir.to_path_buf()));
+    }
+
+    students.sort_by(|a, b| a.student.cmp(&b.student));
+    Ok(students)
+}
+
+// !!!!!!!!!!!!!!!!!!!! Aggregation !!!!!!!!!!!!!!!!!!!!
+
+// Everything we know about one lesson, over all students
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct LessonSummary {
+    // Students with the lesson in their progress file
+    pub(crate) students: u32,
+    pub(crate) completed: u32,
+    // Sums of every student's `u32` counts: a `u64` holds 2^32 students at
+    // `u32::MAX` attempts each, where a `u32` would overflow with two
+    pub(crate) attempts: u64,
+    pub(crate) failed: u64,
+    // Students whose attempts mostly failed
+    pub(crate) failing: Vec<String>,
+    // Students who started but didn't complete it
+    pub(crate) stuck: Vec<String>,
+}
+
+impl LessonSummary {
+    // The lessons a teacher should look at first
+    pub(crate) fn needs_attention(&self) -> bool {
+        self.failing.len() * 2 > self.students as usize
+    }
+}
+
+pub(crate) struct Report {
+    pub(crate) students: usize,
+    // A `BTreeMap` keeps the lessons sorted by title
+    pub(crate) lessons: BTreeMap<String, LessonSummary>,
+}
+
+pub(crate) fn aggregate(students: &[StudentProgress]) -> Report {
+    let mut lessons: BTreeMap<String, LessonSummary> = BTreeMap::new();
+
+    for student in students {
+        for progress in &student.lessons {
+            let summary = lessons.entry(progress.lesson.clone()).or_default();
+
+            summary.students += 1;
+            summary.completed += u32::from(progress.completed);
+            summary.attempts += u64::from(progress.attempts);
+            summary.failed += u64::from(progress.failed);
+
+            if progress.is_failing() {
+                summary.failing.push(student.student.clone());
+            }
+            if progress.is_stuck() {
+                summary.stuck.push(student.student.clone());
+            }
+        }
+    }
+
+    Report { students: students.len(), lessons }
+}
+
+fn names(list: &[String]) -> String {
+    if list.is_empty() {
+        String::from("-")
+    } else {
+        list.join(", ")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Teacher dashboard: {} students", self.students)?;
+
+        let width = self.lessons.keys().map(|title| title.chars().count()).max().unwrap_or(0).max(6);
+        writeln!(f, "   {:<width$}  {:>9}  {:>8}  {:>6}  stuck", "lesson", "completed", "attempts", "failed")?;
+
+        for (title, summary) in &self.lessons {
+            writeln!(
+                f,
+                "{:<2} {:<width$}  {:>9}  {:>8}  {:>6}  {}",
+                if summary.needs_attention() { "!!" } else { "" },
+                title,
+                format!("{}/{}", summary.completed, summary.students),
+                summary.attempts,
+                summary.failed,
+                names(&summary.stuck),
+            )?;
+        }
+
+        let flagged: Vec<&str> =
+            self.lessons.iter().filter(|(_, summary)| summary.needs_attention()).map(|(title, _)| title.as_str()).collect();
+
+        if flagged.is_empty() {
+            writeln!(f, "No lesson where most students fail the exercises")
+        } else {
+            writeln!(f, "!! most students fail the exercises of: {}", flagged.join(", "))
+        }
+    }
+}
+
+impl Report {
+    pub(crate) fn to_csv(&self) -> String {
+        let mut out = String::from("lesson,students,completed,attempts,failed,needs_attention,failing,stuck\n");
+
+        for (title, summary) in &self.lessons {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                quote_field(title),
+                summary.students,
+                summary.completed,
+                summary.attempts,
+                summary.failed,
+                summary.needs_attention(),
+                quote_field(&summary.failing.join(";")),
+                quote_field(&summary.stuck.join(";")),
+            ));
+        }
+
+        out
+    }
+}
+
+// `./main teacher <dir> [--csv]`
+pub(crate) fn teacher_main(args: &[String]) {
+    let (dir, csv) = match args {
+        [dir] => (dir, false),
+        [dir, flag] if flag == "--csv" => (dir, true),
+        _ => {
+            eprintln!("usage: main teacher <progress directory> [--csv]");
+            std::process::exit(2);
+        }
+    };
+
+    match read_progress_dir(Path::new(dir)) {
+        Ok(students) if csv => print!("{}", aggregate(&students).to_csv()),
+        Ok(students) => print!("{}", aggregate(&students)),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub(crate) fn teacher_demo() {
+    let files = [
+        ("ayse", "lesson,completed,attempts,failed\nStructs,true,2,0\nBorrowing,false,6,5\n"),
+        ("john", "lesson,completed,attempts,failed\nStructs,true,1,0\nBorrowing,true,4,3\n"),
+        ("peter", "Structs,true,3,1\nBorrowing,false,2,1\n\"Flow of control\",false,0,0\n"),
+    ];
+
+    let students: Result<Vec<StudentProgress>, DashboardError> =
+        files.iter().map(|(student, text)| parse_progress(student, text, Path::new(student))).collect();
+    let students = match students {
+        Ok(students) => students,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        }
+    };
+
+    let report = aggregate(&students);
+    print!("{}", report);
+    print!("{}", report.to_csv());
+
+    if let Err(e) = parse_progress("broken", "Borrowing,false,2,3\n", Path::new("broken.progress")) {
+        println!("error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_io::ScratchDir;
+
+    fn progress(student: &str, text: &str) -> StudentProgress {
+        parse_progress(student, text, Path::new(student)).unwrap()
+    }
+
+    fn lesson(lesson: &str, completed: bool, attempts: u32, failed: u32) -> LessonProgress {
+        LessonProgress { lesson: String::from(lesson), completed, attempts, failed }
+    }
+
+    #[test]
+    fn headers_and_blank_lines_are_skipped() {
+        let text = "lesson,completed,attempts,failed\n\nStructs,true,2,0\n  \n\"Flow, of control\",no,3,1";
+        let peter = progress("peter", text);
+        assert_eq!(peter.student, "peter");
+        assert_eq!(peter.lessons, [lesson("Structs", true, 2, 0), lesson("Flow, of control", false, 3, 1)]);
+
+        // Only the first line can be the header
+        let error = parse_progress("peter", "Structs,true,2,0\nlesson,completed,attempts,failed\n", Path::new("p"));
+        assert!(matches!(error, Err(DashboardError::InvalidRecord { line: 2, .. })));
+    }
+
+    #[test]
+    fn invalid_records() {
+        let error = |text: &str| parse_progress("peter", text, Path::new("peter.progress")).unwrap_err().to_string();
+
+        assert_eq!(error("Borrowing,false,2,3\n"), "peter.progress: line 1: 3 failed out of only 2 attempts");
+        assert_eq!(
+            error("Structs,true,2\n"),
+            "peter.progress: line 1: expected 4 fields (lesson,completed,attempts,failed), found 3"
+        );
+        assert_eq!(
+            error("Structs,maybe,2,0\n"),
+            "peter.progress: line 1: `completed` should be true or false, found 'maybe'"
+        );
+        assert!(error("Structs,true,-2,0\n").starts_with("peter.progress: line 1: `attempts` '-2': "));
+        assert!(matches!(
+            parse_progress("peter", "\"Structs,true,2,0\n", Path::new("p")),
+            Err(DashboardError::Csv(..))
+        ));
+    }
+
+    #[test]
+    fn directories() {
+        let dir = ScratchDir::new("teacher").unwrap();
+        assert!(matches!(read_progress_dir(dir.path()), Err(DashboardError::NoStudents(_))));
+
+        // Only `*.progress` files count, sorted by name
+        fs::write(dir.path().join("notes.txt"), "not a student").unwrap();
+        assert!(matches!(read_progress_dir(dir.path()), Err(DashboardError::NoStudents(_))));
+        fs::write(dir.path().join("peter.progress"), "Structs,true,1,0\n").unwrap();
+        fs::write(dir.path().join("ayse.progress"), "Structs,false,4,3\n").unwrap();
+        let students = read_progress_dir(dir.path()).unwrap();
+        assert_eq!(students.iter().map(|s| s.student.as_str()).collect::<Vec<_>>(), ["ayse", "peter"]);
+
+        fs::write(dir.path().join("john.progress"), "Structs,true,1,2\n").unwrap();
+        let error = read_progress_dir(dir.path()).unwrap_err().to_string();
+        assert!(error.ends_with("john.progress: line 1: 2 failed out of only 1 attempts"), "{}", error);
+
+        assert!(matches!(read_progress_dir(&dir.path().join("missing")), Err(DashboardError::Io(..))));
+    }
+
+    #[test]
+    fn aggregation() {
+        let students = [
+            progress("ayse", "Structs,true,2,0\nBorrowing,false,6,5\n"),
+            progress("john", "Structs,true,1,0\nBorrowing,true,4,3\n"),
+            progress("peter", "Structs,true,3,1\nBorrowing,false,2,1\n"),
+        ];
+        let report = aggregate(&students);
+        assert_eq!(report.students, 3);
+
+        let borrowing = &report.lessons["Borrowing"];
+        assert_eq!((borrowing.students, borrowing.completed, borrowing.attempts, borrowing.failed), (3, 1, 12, 9));
+        // Half the attempts failing isn't "most" of them: peter isn't failing
+        assert_eq!(borrowing.failing, ["ayse", "john"]);
+        assert_eq!(borrowing.stuck, ["ayse", "peter"]);
+        assert!(borrowing.needs_attention());
+        assert!(!report.lessons["Structs"].needs_attention());
+
+        // Two failing students out of four is not most of them
+        let summary = |failing: usize, students: u32| LessonSummary {
+            students,
+            failing: vec![String::new(); failing],
+            ..LessonSummary::default()
+        };
+        assert!(!summary(2, 4).needs_attention());
+        assert!(summary(3, 4).needs_attention());
+        assert!(!summary(0, 0).needs_attention());
+
+        // Counts that don't fit in a `u32` once added up
+        let big = format!("Structs,false,{},{}\n", u32::MAX, u32::MAX);
+        let report = aggregate(&[progress("ayse", &big), progress("john", &big)]);
+        assert_eq!(report.lessons["Structs"].attempts, 2 * u64::from(u32::MAX));
+        assert_eq!(report.lessons["Structs"].failing, ["ayse", "john"]);
+    }
+
+    #[test]
+    fn reports() {
+        let students = [
+            progress("ayse", "Borrowing,false,6,5\n\"Flow, of control\",true,1,0\n"),
+            progress("john", "Borrowing,true,4,3\n"),
+        ];
+        let report = aggregate(&students);
+
+        assert_eq!(
+            report.to_csv(),
+            "lesson,students,completed,attempts,failed,needs_attention,failing,stuck\n\
+             Borrowing,2,1,10,8,true,ayse;john,ayse\n\
+             \"Flow, of control\",1,1,1,0,false,,\n"
+        );
+        // The report reads back with the same CSV rules
+        let records = split_records(&report.to_csv()).unwrap();
+        assert_eq!(records[2].1[0], "Flow, of control");
+
+        let text = report.to_string();
+        assert!(text.starts_with("Teacher dashboard: 2 students\n"));
+        assert!(text.contains("!! Borrowing"));
+        assert!(text.ends_with("!! most students fail the exercises of: Borrowing\n"));
+    }
+}
@@ -0,0 +1,193 @@
+// !!!!!!!!!!!!!!!!!!!! Testing https://doc.rust-lang.org/rust-by-example/testing.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+A test is a function marked with `#[test]`. It passes if it returns, and fails if it panics, which is what
+`assert!`, `assert_eq!` and `unwrap` do when something is wrong. Compiling with `--test` replaces `main` with a
+test runner that calls every `#[test]` function, each on its own thread:
+
+    rustc --edition 2021 --test main.rs -o notes_test
+    ./notes_test                  run them all
+    ./notes_test fizzbuzz         only the tests whose name contains "fizzbuzz"
+    ./notes_test --nocapture      show what the tests print (hidden by default)
+
+Organisation:
+    - Unit tests test one function or type from the inside. They usually live at the bottom of the file they
+      test, in a `#[cfg(test)] mod tests`, and can reach private items. main.rs is the notes, so its tests live
+      here instead, in the chapter about tests: `mod testing` is declared with `#[cfg(test)]`, this whole file
+      is left out of the normal build.
+    - Integration tests use the program like a user would, from the outside. They live in the tests/ directory,
+      see tests/cli.rs: it builds the notes and runs their subcommands.
+
+With Cargo, `cargo test` does all of this for us, and compiles every file in tests/ as its own crate.
+*/
+
+use std::io;
+
+use crate::person::ParsePersonError;
+use crate::{
+    fizzbuzz, is_divisible_by, write_fizzbuzz, Animal, Circle, EvenNumber, Number, Operations, Person, Sheep,
+};
+
+// Runs `write_fizzbuzz` for 1..=n into a `Vec<u8>` instead of the terminal
+fn fizzbuzz_output(n: u32) -> String {
+    let mut out = Vec::new();
+    for i in 1..=n {
+        write_fizzbuzz(&mut out, i).unwrap();
+    }
+    String::from_utf8(out).unwrap()
+}
+
+// !!!!!!!!!!!!!!!!!!!! Asserting !!!!!!!!!!!!!!!!!!!!
+
+#[test]
+fn is_divisible_by_works() {
+    assert!(is_divisible_by(15, 3));
+    assert!(is_divisible_by(15, 5));
+    assert!(is_divisible_by(0, 7));
+    assert!(!is_divisible_by(7, 2));
+}
+
+// The corner case: without the early return, `lhs % 0` would panic
+#[test]
+fn is_divisible_by_zero_is_false() {
+    assert!(!is_divisible_by(10, 0));
+    assert!(!is_divisible_by(0, 0));
+}
+
+#[test]
+fn fizzbuzz_prints_the_right_words() {
+    assert_eq!(fizzbuzz_output(5), "1\n2\nfizz\n4\nbuzz\n");
+
+    let lines: Vec<String> = fizzbuzz_output(30).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 30);
+    // `assert_eq!` can take a message, printed when it fails
+    assert_eq!(lines[14], "fizzbuzz", "15 is divisible by both 3 and 5");
+    assert_eq!(lines[29], "fizzbuzz");
+    assert_eq!(lines[8], "fizz");
+    assert_eq!(lines[9], "buzz");
+}
+
+// `fizzbuzz` itself prints to the terminal: all we can check is that it
+// doesn't panic. (That's why `write_fizzbuzz` exists.)
+#[test]
+fn fizzbuzz_to_the_terminal() {
+    fizzbuzz(15);
+}
+
+#[test]
+fn operations_run() {
+    assert_eq!(Operations::Add.run(23, 24), 47);
+    assert_eq!(Operations::Subtract.run(23, 24), -1);
+    assert_eq!(Operations::Subtract.run(i32::MIN + 1, 1), i32::MIN);
+}
+
+#[test]
+fn even_number_try_from() {
+    assert_eq!(EvenNumber::try_from(8), Ok(EvenNumber(8)));
+    assert_eq!(EvenNumber::try_from(0), Ok(EvenNumber(0)));
+    assert_eq!(EvenNumber::try_from(-4), Ok(EvenNumber(-4)));
+    assert_eq!(EvenNumber::try_from(5), Err(()));
+    assert_eq!(EvenNumber::try_from(-3), Err(()));
+}
+
+#[test]
+fn number_from_and_into() {
+    assert_eq!(Number::from(30), Number { value: 30 });
+
+    // Implementing `From` gives us `Into` for free
+    let number: Number = 5.into();
+    assert_eq!(number.value, 5);
+}
+
+#[test]
+fn circle_display() {
+    assert_eq!(Circle { radius: 6 }.to_string(), "Circle of radius 6");
+    assert_eq!(format!("{:>20}", Circle { radius: 1 }.to_string()), "  Circle of radius 1");
+}
+
+#[test]
+fn sheep_shear() {
+    let mut dolly: Sheep = Animal::new("Dolly");
+    assert!(!dolly.is_naked());
+    assert_eq!(dolly.noise(), "baaaaah!");
+
+    dolly.shear();
+    assert!(dolly.is_naked());
+    assert_eq!(dolly.noise(), "baaaaah?");
+
+    // Shearing a naked sheep changes nothing
+    dolly.shear();
+    assert!(dolly.is_naked());
+}
+
+// !!!!!!!!!!!!!!!!!!!! #[should_panic] !!!!!!!!!!!!!!!!!!!!
+
+/*
+Sometimes panicking is the correct behaviour. `#[should_panic]` turns the test around: it passes only if the
+function panics. `expected = "..."` also checks the panic message contains that text, so the test doesn't pass
+because of some other, unrelated panic.
+*/
+
+// What `is_divisible_by` protects us from
+#[test]
+#[should_panic(expected = "divisor of zero")]
+fn remainder_by_zero_panics() {
+    // `black_box` hides the zero from the compiler, which would otherwise
+    // refuse to build this
+    let zero = std::hint::black_box(0u32);
+    let _ = 10 % zero;
+}
+
+#[test]
+#[should_panic(expected = "Err")]
+fn unwrapping_an_odd_number_panics() {
+    EvenNumber::try_from(7).unwrap();
+}
+
+// !!!!!!!!!!!!!!!!!!!! Tests returning Result !!!!!!!!!!!!!!!!!!!!
+
+/*
+A test can return a `Result` instead of panicking: `Ok(())` passes, `Err` fails, and the error is printed with
+`Debug`. This lets us use `?` instead of `unwrap()` everywhere. (A `#[should_panic]` test can't return a
+`Result`: an `Err` is a failure, not a panic.)
+*/
+
+#[test]
+fn write_fizzbuzz_to_a_buffer() -> io::Result<()> {
+    let mut out = Vec::new();
+    write_fizzbuzz(&mut out, 3)?;
+    write_fizzbuzz(&mut out, 10)?;
+
+    assert_eq!(out, b"fizz\nbuzz\n");
+    Ok(())
+}
+
+#[test]
+fn parse_a_person() -> Result<(), ParsePersonError> {
+    let peter: Person = "Peter,27".parse()?;
+    assert_eq!(peter, Person { name: String::from("Peter"), age: 27 });
+
+    let again: Person = peter.to_string().parse()?;
+    assert_eq!(again, peter);
+    Ok(())
+}
+
+// !!!!!!!!!!!!!!!!!!!! The lessons !!!!!!!!!!!!!!!!!!!!
+
+#[test]
+fn lesson_ids_are_unique() {
+    let mut ids: Vec<&str> = crate::LESSONS.iter().map(|lesson| lesson.id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), crate::LESSONS.len());
+}
+
+// `#[ignore]`d tests only run with `./notes_test --ignored`: this one prints
+// a lot, and `./main leaks` already runs every lesson
+#[test]
+#[ignore]
+fn every_lesson_runs() {
+    for lesson in crate::LESSONS {
+        (lesson.run)();
+    }
+}
@@ -0,0 +1,148 @@
+// Integration tests: the notes, used from the outside like a user would.
+// Build and run them from the repository's root:
+//
+//     rustc --edition 2021 --test tests/cli.rs -o cli_test && ./cli_test
+//
+// The first test to run compiles main.rs with the local `rustc` (or `$RUSTC`),
+// the others reuse that binary (see common/mod.rs).
+
+mod common;
+
+use std::env;
+use std::process::Command;
+
+use common::{notes, notes_binary, stdout};
+
+#[test]
+fn the_notes_run() {
+    let output = notes(&[]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("fizzbuzz"));
+}
+
+#[test]
+fn lessons_are_listed() {
+    let output = notes(&["lessons"]);
+    assert!(output.status.success());
+
+    let listing = stdout(&output);
+    for id in ["person", "json", "closures", "macros"] {
+        assert!(listing.lines().any(|line| line.starts_with(id)), "{} is missing", id);
+    }
+}
+
+#[test]
+fn a_single_lesson_runs() {
+    let output = notes(&["run", "iterators"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("The first 10 Fibonacci numbers"));
+}
+
+#[test]
+fn a_lesson_source_is_printed() {
+    // Not a terminal: no colors
+    let output = notes(&["source", "closures"]);
+    assert!(output.status.success());
+    let source = std::fs::read_to_string(common::main_rs().with_file_name("closures.rs")).unwrap();
+    assert_eq!(stdout(&output).trim_end(), source.trim_end());
+    assert_eq!(notes(&["source", "no-such-lesson"]).status.code(), Some(2));
+}
+
+#[test]
+fn unknown_lessons_and_commands_fail() {
+    assert_eq!(notes(&["run", "no-such-lesson"]).status.code(), Some(2));
+    assert_eq!(notes(&["no-such-command"]).status.code(), Some(2));
+}
+
+#[test]
+fn nothing_leaks() {
+    let output = notes(&["leaks"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn c_calls_rust() {
+    let output = notes(&["ffi"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let printed = stdout(&output);
+    assert!(printed.contains("notes_sum = 2147483657"), "{}", printed);
+    assert!(printed.contains("notes_count_letters(NULL) = -1"));
+}
+
+#[test]
+fn the_teacher_dashboard() {
+    let dir = env::temp_dir().join(format!("rust-notes-cli-teacher-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let teacher = |args: &[&str]| notes(&[&["teacher", dir.to_str().unwrap()], args].concat());
+
+    // No progress files yet
+    let empty = teacher(&[]);
+    assert_eq!(empty.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&empty.stderr).contains("no `*.progress` files found"));
+
+    std::fs::write(dir.join("ayse.progress"), "lesson,completed,attempts,failed\nBorrowing,false,6,5\n").unwrap();
+    std::fs::write(dir.join("john.progress"), "Borrowing,true,4,1\n").unwrap();
+
+    let report = stdout(&teacher(&[]));
+    assert!(report.starts_with("Teacher dashboard: 2 students\n"), "{}", report);
+    assert!(report.contains("No lesson where most students fail the exercises"), "{}", report);
+    assert_eq!(
+        stdout(&teacher(&["--csv"])),
+        "lesson,students,completed,attempts,failed,needs_attention,failing,stuck\n\
+         Borrowing,2,1,10,6,false,ayse,ayse\n"
+    );
+    assert_eq!(teacher(&["--pdf"]).status.code(), Some(2));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn annotations_are_saved() {
+    let file = env::temp_dir().join(format!("rust-notes-cli-annotations-{}.txt", std::process::id()));
+    let note = |args: &[&str]| {
+        Command::new(notes_binary())
+            .arg("note")
+            .args(args)
+            .env("RUST_NOTES_ANNOTATIONS", &file)
+            .output()
+            .expect("failed to run the notes")
+    };
+
+    assert!(note(&["closures", "FnOnce", "runs", "once"]).status.success());
+    assert!(note(&["iterators", "lazy"]).status.success());
+
+    let listing = stdout(&note(&["closures"]));
+    assert!(listing.contains("- FnOnce runs once"), "{}", listing);
+    assert!(!listing.contains("lazy"));
+    assert!(stdout(&note(&[])).contains("2 annotations"));
+    assert_eq!(note(&["no-such-lesson", "text"]).status.code(), Some(2));
+
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn the_notes_export() {
+    let dir = env::temp_dir().join(format!("rust-notes-cli-export-{}", std::process::id()));
+    let book = dir.join("book");
+
+    let output = notes(&["export", "mdbook", book.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let summary = std::fs::read_to_string(book.join("src").join("SUMMARY.md")).unwrap();
+    assert!(summary.contains("- [Closures](closures.md)"), "{}", summary);
+    let chapter = std::fs::read_to_string(book.join("src").join("closures.md")).unwrap();
+    assert!(chapter.contains("[1]: https://doc.rust-lang.org/rust-by-example/fn/closures.html"), "{}", chapter);
+    assert!(chapter.contains("Hello from a closure"));
+    // The notes of main.rs come first
+    assert!(summary.starts_with("# Summary\n\n- [The main notes](main.md)\n"), "{}", summary);
+    let main = std::fs::read_to_string(book.join("src").join("main.md")).unwrap();
+    assert!(main.contains("]: https://doc.rust-lang.org/rust-by-example/scope/borrow.html\n"));
+    assert!(main.contains("Hello, world!"));
+
+    let site = dir.join("site");
+    assert!(notes(&["export", "html", site.to_str().unwrap()]).status.success());
+    assert!(std::fs::read_to_string(site.join("index.html")).unwrap().contains("href=\"closures.html\""));
+
+    assert_eq!(notes(&["export", "pdf", site.to_str().unwrap()]).status.code(), Some(2));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,80 @@
+// Shared by the integration tests: every file of tests/ is a crate of its own,
+// and includes this one with `mod common;`. (A tests/common.rs would be taken
+// for a test file itself.)
+
+// Each test crate only uses some of these
+#![allow(dead_code)]
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::OnceLock;
+
+// tests/common/mod.rs -> main.rs, next to the tests/ directory
+pub fn main_rs() -> PathBuf {
+    let common_dir = PathBuf::from(file!()).parent().unwrap().to_path_buf();
+    env::current_dir().unwrap().join(common_dir).join("../../main.rs")
+}
+
+// The tests run on several threads at once, `OnceLock` makes sure the notes
+// are only compiled once
+pub fn notes_binary() -> &'static PathBuf {
+    static BINARY: OnceLock<PathBuf> = OnceLock::new();
+
+    BINARY.get_or_init(|| {
+        let binary = env::temp_dir().join(format!("rust-notes-cli-test-{}", std::process::id()));
+        let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| String::from("rustc")))
+            .args(["--edition", "2021", "-o"])
+            .arg(&binary)
+            .arg(main_rs())
+            .status()
+            .expect("failed to run rustc");
+
+        assert!(status.success(), "main.rs doesn't compile");
+        binary
+    })
+}
+
+pub fn notes(args: &[&str]) -> Output {
+    Command::new(notes_binary()).args(args).output().expect("failed to run the notes")
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// A server started by the notes, killed when dropped
+pub struct ServerProcess {
+    child: Child,
+    pub address: SocketAddr,
+}
+
+// Runs the notes with `args`, a server binding to port 0. Its first line
+// says which port the OS picked: `prefix`, then the address, e.g.
+// "listening on 127.0.0.1:41234" or "serving the notes on http://127.0.0.1:41234/".
+pub fn spawn_server(args: &[&str], prefix: &str) -> ServerProcess {
+    let mut child = Command::new(notes_binary())
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start the server");
+
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    let address = line
+        .trim()
+        .strip_prefix(prefix)
+        .map(|address| address.trim_end_matches('/'))
+        .unwrap_or_else(|| panic!("unexpected {:?}", line));
+
+    ServerProcess { address: address.parse().unwrap(), child }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
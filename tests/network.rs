@@ -0,0 +1,85 @@
+// Integration tests of `./main net`: the servers run as separate processes,
+// on a port picked by the OS, and the tests talk to them over TCP on
+// localhost. No network access needed.
+//
+//     rustc --edition 2021 --test tests/network.rs -o network_test && ./network_test
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use common::{notes, spawn_server, stdout, ServerProcess};
+
+// `./main net <server> 127.0.0.1:0`
+fn start(server: &str) -> ServerProcess {
+    spawn_server(&["net", server, "127.0.0.1:0"], "listening on ")
+}
+
+fn connect(server: &ServerProcess) -> TcpStream {
+    let stream = TcpStream::connect(server.address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream
+}
+
+fn read_line(reader: &mut impl BufRead) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    line.trim_end().to_string()
+}
+
+#[test]
+fn echo() {
+    let server = start("echo");
+    let mut stream = connect(&server);
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    for text in ["hello", "é ∞ 🦀"] {
+        writeln!(stream, "{}", text).unwrap();
+        assert_eq!(read_line(&mut reader), text);
+    }
+}
+
+#[test]
+fn chat() {
+    let server = start("chat");
+
+    let mut alice = connect(&server);
+    let mut alice_reader = BufReader::new(alice.try_clone().unwrap());
+    writeln!(alice, "alice").unwrap();
+    assert_eq!(read_line(&mut alice_reader), "* welcome alice, 0 others here");
+
+    let mut bob = connect(&server);
+    let mut bob_reader = BufReader::new(bob.try_clone().unwrap());
+    writeln!(bob, "bob").unwrap();
+    assert_eq!(read_line(&mut bob_reader), "* welcome bob, 1 other here");
+    assert_eq!(read_line(&mut alice_reader), "* bob joined");
+
+    writeln!(bob, "hi alice").unwrap();
+    assert_eq!(read_line(&mut alice_reader), "bob: hi alice");
+    writeln!(alice, "hi bob").unwrap();
+    assert_eq!(read_line(&mut bob_reader), "alice: hi bob");
+}
+
+#[test]
+fn web_events_client_and_server() {
+    let server = start("events");
+
+    let output = notes(&["net", "send-events", &server.address.to_string()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        stdout(&output).lines().collect::<Vec<_>>(),
+        ["page loaded", "pressed 'x'.", "pasted \"my text\".", "clicked at x=20, y=80.", "page unloaded"]
+    );
+}
+
+#[test]
+fn nobody_listening() {
+    // Bound then dropped: nothing listens on that port anymore
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    let output = notes(&["net", "send-events", &address.to_string()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can't send the events"));
+}
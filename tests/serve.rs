@@ -0,0 +1,74 @@
+// Integration tests of `./main serve`: the server runs as a separate process,
+// on a port picked by the OS, and the tests are its browser.
+//
+//     rustc --edition 2021 --test tests/serve.rs -o serve_test && ./serve_test
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use common::{notes, spawn_server, stdout, ServerProcess};
+
+// `./main serve 127.0.0.1:0`
+fn start() -> ServerProcess {
+    spawn_server(&["serve", "127.0.0.1:0"], "serving the notes on http://")
+}
+
+// The status line and the body
+fn get(server: &ServerProcess, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(server.address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, server.address).unwrap();
+
+    // The server closes the connection after its answer
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_string();
+
+    let length = head.lines().find_map(|line| line.strip_prefix("Content-Length: ")).unwrap();
+    assert_eq!(length.parse::<usize>().unwrap(), body.len());
+    (status, body.to_string())
+}
+
+#[test]
+fn pages() {
+    let server = start();
+
+    let (status, index) = get(&server, "/");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(index.contains("<a href=\"/lessons/closures\">Closures</a>"));
+
+    let (status, page) = get(&server, "/lessons/closures");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(page.contains("<span class=\"keyword\">fn</span>"));
+    assert!(page.contains("https://doc.rust-lang.org/rust-by-example/fn/closures.html"));
+    // The lesson's output, as `./main run closures` prints it
+    let printed = stdout(&notes(&["run", "closures"]));
+    assert!(page.contains(printed.lines().next().unwrap()));
+
+    let (status, _) = get(&server, "/lessons/nothing");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn lessons_run_again() {
+    let server = start();
+
+    let (status, output) = get(&server, "/run/closures");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(output, stdout(&notes(&["run", "closures"])));
+}
+
+#[test]
+fn malformed_requests() {
+    let server = start();
+
+    let mut stream = TcpStream::connect(server.address).unwrap();
+    stream.write_all(b"hello\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", response);
+}
@@ -0,0 +1,359 @@
+// !!!!!!!!!!!!!!!!!!!! Unsafe Rust https://doc.rust-lang.org/rust-by-example/unsafe.html !!!!!!!!!!!!!!!!!!!!
+
+/*
+The compiler can't check everything. `unsafe` unlocks five things it can't verify:
+    - dereferencing a raw pointer (`*const T`, `*mut T`)
+    - calling an `unsafe fn` (including every function from C)
+    - accessing a `static mut`
+    - implementing an `unsafe trait` (like `GlobalAlloc` in alloc_counter.rs)
+    - accessing the fields of a `union`
+
+`unsafe` doesn't turn the borrow checker off. It means "I checked this myself". Every `unsafe fn` documents
+what the caller has to guarantee (its safety contract), and every `unsafe { }` block says, in a `// SAFETY:`
+comment, why the contract holds there. If it doesn't, the behaviour is undefined: the program may crash, or
+worse, seem to work.
+
+The usual pattern is a small `unsafe` core behind a safe function that checks the contract itself, so callers
+never need `unsafe` at all.
+
+Miri is an interpreter that runs Rust code and reports undefined behaviour (out of bounds reads, reading
+uninitialised memory, aliasing violations...). It needs Cargo and a nightly toolchain (`cargo +nightly miri
+test`), and it can't call C, so the tests of this file that do are marked `#[cfg_attr(miri, ignore)]`.
+*/
+
+use std::env;
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::io;
+use std::mem::MaybeUninit;
+use std::process::Command;
+
+use crate::ffi_export;
+
+// !!!!!!!!!!!!!!!!!!!! Raw pointers !!!!!!!!!!!!!!!!!!!!
+
+// Creating raw pointers is safe, only using them isn't. `&raw mut x` makes a
+// pointer without a reference in between: `&x as *const i32` would borrow `x`,
+// and the `&mut x` after it would invalidate that first pointer, so reading
+// through it would be undefined behaviour even though it points to `x`.
+pub(crate) fn raw_pointers() -> i32 {
+    let mut x = 10;
+    let writable = &raw mut x;
+    let read_only = writable as *const i32;
+
+    // SAFETY: both pointers come from the same `&raw mut x`, with no reference
+    // to `x` created since, and `x` is alive and initialised
+    let read = unsafe {
+        *writable += 1;
+        *read_only
+    };
+    println!("x through a raw pointer: {}", read);
+
+    // A pointer can be null, a reference never is
+    let nothing: *const i32 = std::ptr::null();
+    assert!(nothing.is_null());
+    read
+}
+
+// Swaps two values through raw pointers.
+//
+// Safety contract: `a` and `b` are valid for reads and writes and point to
+// initialised `i32`s. They may be equal.
+pub(crate) unsafe fn swap_raw(a: *mut i32, b: *mut i32) {
+    // SAFETY: given by the caller. `ptr::read` copies the value out without
+    // needing a reference, so `a == b` is fine
+    unsafe {
+        let tmp = std::ptr::read(a);
+        std::ptr::copy(b, a, 1);
+        std::ptr::write(b, tmp);
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Slices from raw parts !!!!!!!!!!!!!!!!!!!!
+
+/*
+A slice `&[T]` is a pointer and a length. `slice::from_raw_parts(ptr, len)` builds one from those two pieces,
+trusting us that `len` elements really live there. That's how `split_at_mut` is implemented: the borrow checker
+can't know that two halves of one slice don't overlap, so it would refuse two `&mut` to the same slice.
+*/
+
+// Our own `split_at_mut`: a safe function around an unsafe core, because it
+// checks `mid` itself
+pub(crate) fn split_at_mut(values: &mut [i32], mid: usize) -> (&mut [i32], &mut [i32]) {
+    let len = values.len();
+    assert!(mid <= len, "mid is out of bounds");
+
+    let ptr = values.as_mut_ptr();
+
+    // SAFETY: `mid <= len`, so both halves are inside `values`, they don't
+    // overlap, and they borrow `values` mutably for as long as they live
+    unsafe { (std::slice::from_raw_parts_mut(ptr, mid), std::slice::from_raw_parts_mut(ptr.add(mid), len - mid)) }
+}
+
+// The bytes of some `u32`s, without copying them
+pub(crate) fn as_bytes(values: &[u32]) -> &[u8] {
+    // SAFETY: a `u32` is 4 initialised bytes without padding, any byte is a
+    // valid `u8`, and `u8` has no alignment requirement
+    unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values)) }
+}
+
+// !!!!!!!!!!!!!!!!!!!! MaybeUninit !!!!!!!!!!!!!!!!!!!!
+
+/*
+Rust never lets us read a variable before it's initialised. `MaybeUninit<T>` is the escape hatch: memory that
+may not hold a valid `T` yet. We write it, then promise with `assume_init` that it's ready. Reading it before
+that is undefined behaviour, even for an integer.
+*/
+
+// The first `N` squares, written in place
+pub(crate) fn squares<const N: usize>() -> [u64; N] {
+    let mut squares = [const { MaybeUninit::<u64>::uninit() }; N];
+
+    for (i, square) in squares.iter_mut().enumerate() {
+        square.write((i * i) as u64);
+    }
+
+    // SAFETY: the loop wrote every element
+    squares.map(|square| unsafe { square.assume_init() })
+}
+
+// An "out parameter", like in C: the function fills in memory it's given.
+// It returns `false` and leaves `out` uninitialised if `text` isn't a number
+pub(crate) fn parse_into(text: &str, out: &mut MaybeUninit<i64>) -> bool {
+    match text.trim().parse() {
+        Ok(value) => {
+            out.write(value);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Calling C !!!!!!!!!!!!!!!!!!!!
+
+// Functions of the C library, which every Rust program on Linux is linked
+// with anyway. Rust can't check them, so calling them is always `unsafe`.
+extern "C" {
+    // size_t strlen(const char *s);
+    fn strlen(s: *const c_char) -> usize;
+    // pid_t getpid(void);
+    fn getpid() -> i32;
+}
+
+// Safe wrapper: a `&CStr` is always NUL-terminated, which is `strlen`'s only
+// requirement
+pub(crate) fn c_strlen(s: &CStr) -> usize {
+    // SAFETY: `s` is a valid, NUL-terminated string
+    unsafe { strlen(s.as_ptr()) }
+}
+
+pub(crate) fn process_id() -> u32 {
+    // SAFETY: `getpid` has no requirements and can't fail
+    unsafe { getpid() as u32 }
+}
+
+// !!!!!!!!!!!!!!!!!!!! Exporting functions to C !!!!!!!!!!!!!!!!!!!!
+
+/*
+The other direction: ffi_export.rs has `#[no_mangle] extern "C"` functions, and call_rust.c calls them. Run:
+    ./main ffi
+
+It compiles ffi_export.rs into a static library with `rustc --crate-type staticlib`, links it with call_rust.c
+using the local `cc`, and runs the result. The sources are embedded with `include_str!`, so this works from any
+directory.
+*/
+
+const FFI_EXPORT_RS: &str = include_str!("ffi_export.rs");
+const CALL_RUST_C: &str = include_str!("call_rust.c");
+
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{:?} failed:\n{}",
+            command,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Builds and runs call_rust.c, returns what it printed
+pub(crate) fn call_rust_from_c() -> io::Result<String> {
+    let dir = env::temp_dir().join(format!("rust-notes-ffi-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+
+    let result = (|| {
+        fs::write(dir.join("ffi_export.rs"), FFI_EXPORT_RS)?;
+        fs::write(dir.join("call_rust.c"), CALL_RUST_C)?;
+
+        let library = dir.join("libnotes_ffi.a");
+        let program = dir.join("call_rust");
+
+        run(Command::new(env::var("RUSTC").unwrap_or_else(|_| String::from("rustc")))
+            .args(["--edition", "2021", "--crate-type", "staticlib", "-o"])
+            .arg(&library)
+            .arg(dir.join("ffi_export.rs")))?;
+
+        // The Rust standard library inside the `.a` needs a few system
+        // libraries that a C program doesn't link by default
+        run(Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+            .arg(dir.join("call_rust.c"))
+            .arg(&library)
+            .args(["-lpthread", "-ldl", "-lm", "-o"])
+            .arg(&program))?;
+
+        run(&mut Command::new(&program))
+    })();
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+// `./main ffi`
+pub(crate) fn ffi_main() {
+    match call_rust_from_c() {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub(crate) fn unsafe_demo() {
+    raw_pointers();
+
+    let (mut a, mut b) = (1, 2);
+    // SAFETY: both pointers come from live, initialised locals
+    unsafe { swap_raw(&mut a, &mut b) };
+    println!("swapped: a = {}, b = {}", a, b);
+
+    let mut values = [1, 2, 3, 4, 5];
+    let (left, right) = split_at_mut(&mut values, 2);
+    left[0] = 10;
+    right[0] = 30;
+    println!("{:?} and {:?}", left, right);
+
+    println!("the bytes of [1, 256]: {:?}", as_bytes(&[1, 256]));
+
+    println!("squares: {:?}", squares::<6>());
+
+    let mut number = MaybeUninit::uninit();
+    if parse_into("42", &mut number) {
+        // SAFETY: `parse_into` returned true, so it wrote `number`
+        println!("parsed {}", unsafe { number.assume_init() });
+    }
+
+    let hello = CString::new("hello").unwrap();
+    println!("strlen(\"hello\") = {}", c_strlen(&hello));
+    println!("getpid() = {}", process_id());
+    assert_eq!(process_id(), std::process::id());
+
+    // The exported functions are ordinary Rust functions too
+    let numbers = [1, 2, 3];
+    // SAFETY: the pointer and the length come from the same array
+    let sum = unsafe { ffi_export::notes_sum(numbers.as_ptr(), numbers.len()) };
+    println!("notes_sum([1, 2, 3]) = {}", sum);
+
+    println!("Run `./main ffi` to call them from C");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_pointers_see_the_write() {
+        assert_eq!(raw_pointers(), 11);
+    }
+
+    #[test]
+    fn swap_raw_swaps() {
+        let (mut a, mut b) = (1, 2);
+        // SAFETY: both pointers come from live, initialised locals
+        unsafe { swap_raw(&mut a, &mut b) };
+        assert_eq!((a, b), (2, 1));
+    }
+
+    #[test]
+    fn swap_raw_with_itself() {
+        let mut a = 7;
+        let p: *mut i32 = &mut a;
+        // SAFETY: `p` points to `a`, and the contract allows `a == b`
+        unsafe { swap_raw(p, p) };
+        assert_eq!(a, 7);
+    }
+
+    #[test]
+    fn split_at_mut_halves() {
+        let mut values = [1, 2, 3, 4];
+        let (left, right) = split_at_mut(&mut values, 1);
+        left[0] += 10;
+        right[2] += 10;
+        assert_eq!(values, [11, 2, 3, 14]);
+
+        let (left, right) = split_at_mut(&mut values, 4);
+        assert_eq!((left.len(), right.len()), (4, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn split_at_mut_checks_mid() {
+        split_at_mut(&mut [1, 2], 3);
+    }
+
+    #[test]
+    fn bytes_are_native_endian() {
+        let bytes = as_bytes(&[0x0403_0201]);
+        assert_eq!(bytes, 0x0403_0201u32.to_ne_bytes());
+        assert_eq!(as_bytes(&[]), &[] as &[u8]);
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn bytes_are_little_endian_here() {
+        assert_eq!(as_bytes(&[0x0403_0201, 256]), [1, 2, 3, 4, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn squares_are_initialised() {
+        assert_eq!(squares::<5>(), [0, 1, 4, 9, 16]);
+        assert_eq!(squares::<0>(), []);
+    }
+
+    #[test]
+    fn parse_into_only_writes_numbers() {
+        let mut out = MaybeUninit::uninit();
+        assert!(!parse_into("fizz", &mut out));
+        assert!(parse_into(" -3 ", &mut out));
+        // SAFETY: `parse_into` returned true, so it wrote `out`
+        assert_eq!(unsafe { out.assume_init() }, -3);
+    }
+
+    #[test]
+    fn exported_functions() {
+        // SAFETY: two `i32`s behind the pointer, and nothing is read when `len` is 0
+        unsafe {
+            assert_eq!(ffi_export::notes_sum([i32::MAX, 1].as_ptr(), 2), 1 << 31);
+            assert_eq!(ffi_export::notes_sum(std::ptr::null(), 0), 0);
+        }
+
+        let word = CString::new("fizz-buzz 15").unwrap();
+        // SAFETY: `word` is NUL-terminated and outlives the call, and null is allowed
+        unsafe {
+            assert_eq!(ffi_export::notes_count_letters(word.as_ptr()), 8);
+            assert_eq!(ffi_export::notes_count_letters(std::ptr::null()), -1);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn libc_functions() {
+        assert_eq!(c_strlen(c"fizzbuzz"), 8);
+        assert_eq!(c_strlen(c""), 0);
+        assert_eq!(process_id(), std::process::id());
+    }
+}